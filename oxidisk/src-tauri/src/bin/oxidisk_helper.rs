@@ -1,15 +1,20 @@
 use plist::Value as PlistValue;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use flate2::read::GzDecoder;
 use flate2::write::GzEncoder;
 use flate2::Compression;
+use md5::Md5;
+use sha1::Sha1;
 use sha2::{Digest, Sha256};
 use regex::Regex;
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::PathBuf;
-use std::process::Command;
-use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
 
 #[cfg(target_os = "macos")]
 use std::os::unix::fs::OpenOptionsExt;
@@ -17,7 +22,11 @@ use std::os::unix::fs::OpenOptionsExt;
 #[path = "../partitioning/fs_driver.rs"]
 mod fs_driver;
 
-use fs_driver::{default_drivers, FileSystemDriver};
+#[path = "../partitioning/block_sizes.rs"]
+mod block_sizes;
+
+use block_sizes::device_block_sizes;
+use fs_driver::{check_mkfs_args, default_drivers, FileSystemDriver, MkfsOptions};
 
 #[derive(Deserialize)]
 struct HelperRequest {
@@ -28,63 +37,276 @@ struct HelperRequest {
 #[derive(Serialize)]
 struct HelperResponse {
     ok: bool,
+    code: Option<String>,
     message: Option<String>,
     details: Option<Value>,
 }
 
+// Bei dryRun werden diskutil-/Sidecar-Aufrufe nur protokolliert, nicht ausgefuehrt.
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+static DRY_RUN_COMMANDS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+// Nur diese Aktionen sind vollstaendig gegen dryRun abgesichert; alle anderen lehnen es ab
+const DRY_RUN_ACTIONS: &[&str] = &[
+    "wipe_device",
+    "create_partition_table",
+    "convert_partition_table",
+    "delete_partition",
+    "format_partition",
+];
+// stderr erfolgreicher diskutil-Aufrufe; landet in details.warnings der Antwort
+static TOOL_WARNINGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+// Von force_unmount_disk tatsaechlich ausgehaengte Volumes; landet in details.unmountedVolumes
+static UNMOUNTED_VOLUMES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+// Anteil (Start, Breite in Prozent) der aktuellen Phase an der Gesamtoperation fuer overallPercent
+static PROGRESS_SPAN: Mutex<(u64, u64)> = Mutex::new((0, 100));
+
+fn set_progress_span(start: u64, width: u64) {
+    if let Ok(mut span) = PROGRESS_SPAN.lock() {
+        *span = (start, width);
+    }
+}
+
+fn is_dry_run() -> bool {
+    DRY_RUN.load(Ordering::SeqCst)
+}
+
+fn log_dry_run<I, S>(program: &str, args: I)
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<std::ffi::OsStr>,
+{
+    let mut line = program.to_string();
+    for arg in args {
+        line.push(' ');
+        line.push_str(&arg.as_ref().to_string_lossy());
+    }
+    emit_log("dry-run", &line);
+    if let Ok(mut commands) = DRY_RUN_COMMANDS.lock() {
+        commands.push(line);
+    }
+}
+
+// Von SIGTERM gesetzt; Schreibschleifen pruefen das Flag nur zwischen zwei Bloecken
+// (copy_blocks erst nach sync_data + Journal), ein laufender Block wird nie abgebrochen.
+// Externe Werkzeuge (resize2fs, sgdisk, diskutil, ...) laufen immer zu Ende, siehe ToolWatch.
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_sigterm(_signal: libc::c_int) {
+    CANCELLED.store(true, Ordering::SeqCst);
+}
+
+fn install_sigterm_handler() {
+    unsafe {
+        libc::signal(libc::SIGTERM, on_sigterm as extern "C" fn(libc::c_int) as libc::sighandler_t);
+    }
+}
+
+fn ensure_not_cancelled() -> Result<(), String> {
+    if CANCELLED.load(Ordering::SeqCst) {
+        return Err(coded("CANCELLED", "Operation cancelled"));
+    }
+    Ok(())
+}
+
+// Muss deutlich unter dem kleinsten Leerlauf-Timeout der App liegen (MIN_HELPER_TIMEOUT_SECS)
+const TOOL_HEARTBEAT: Duration = Duration::from_secs(30);
+
+// Begleitet ein laufendes externes Werkzeug: ein Abbruch wird nicht an das Werkzeug
+// weitergegeben, sondern als ausstehend gemeldet, bis es fertig ist. Werkzeuge wie
+// "diskutil apfs resizeContainer" oder secureErase schweigen teils stundenlang; der
+// Heartbeat haelt solange den Leerlauf-Timeout der App offen.
+struct ToolWatch {
+    done: Arc<AtomicBool>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+fn watch_tool(name: &str) -> ToolWatch {
+    let done = Arc::new(AtomicBool::new(false));
+    let finished = Arc::clone(&done);
+    let name = name.to_string();
+    let thread = std::thread::spawn(move || {
+        let mut cancel_reported = false;
+        let mut last_beat = Instant::now();
+        while !finished.load(Ordering::SeqCst) {
+            if !cancel_reported && CANCELLED.load(Ordering::SeqCst) {
+                emit_log("cancel", &format!("Cancel pending: waiting for {name} to finish"));
+                cancel_reported = true;
+            }
+            if last_beat.elapsed() >= TOOL_HEARTBEAT {
+                emit_heartbeat(&name);
+                last_beat = Instant::now();
+            }
+            std::thread::park_timeout(Duration::from_millis(200));
+        }
+    });
+    ToolWatch { done, thread: Some(thread) }
+}
+
+impl Drop for ToolWatch {
+    fn drop(&mut self) {
+        self.done.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
 fn main() {
+    install_sigterm_handler();
+
     let mut input = String::new();
     if std::io::stdin().read_to_string(&mut input).is_err() {
-        write_response(false, Some("Failed to read request".to_string()), None);
+        write_response(false, Some("INVALID_REQUEST".to_string()), Some("Failed to read request".to_string()), None);
         return;
     }
 
     let request: HelperRequest = match serde_json::from_str(&input) {
         Ok(req) => req,
         Err(err) => {
-            write_response(false, Some(format!("Invalid request: {err}")), None);
+            write_response(false, Some("INVALID_REQUEST".to_string()), Some(format!("Invalid request: {err}")), None);
             return;
         }
     };
 
+    let dry_run = request.payload.get("dryRun").and_then(|v| v.as_bool()).unwrap_or(false);
+    if dry_run && !DRY_RUN_ACTIONS.contains(&request.action.as_str()) {
+        write_response(
+            false,
+            Some("DRY_RUN_UNSUPPORTED".to_string()),
+            Some(format!("dryRun is not supported for action: {}", request.action)),
+            None,
+        );
+        return;
+    }
+    DRY_RUN.store(dry_run, Ordering::SeqCst);
+
     let result = match request.action.as_str() {
         "wipe_device" => handle_wipe_device(&request.payload),
         "create_partition_table" => handle_create_partition_table(&request.payload),
+        "convert_partition_table" => handle_convert_partition_table(&request.payload),
         "create_partition" => handle_create_partition(&request.payload),
+        "apply_partition_layout" => handle_apply_partition_layout(&request.payload),
         "delete_partition" => handle_delete_partition(&request.payload),
         "format_partition" => handle_format_partition(&request.payload),
         "check_partition" => handle_check_partition(&request.payload),
+        "smart_info" => handle_smart_info(&request.payload),
+        "trim_device" => handle_trim_device(&request.payload),
         "resize_partition" => handle_resize_partition(&request.payload),
+        "preview_resize" => handle_preview_resize(&request.payload),
         "move_partition" => handle_move_partition(&request.payload),
+        "resume_move" => handle_resume_move(),
         "copy_partition" => handle_copy_partition(&request.payload),
         "set_label_uuid" => handle_set_label_uuid(&request.payload),
+        "rename_volume" => handle_rename_volume(&request.payload),
+        "set_partition_name" => handle_set_partition_name(&request.payload),
         "preflight_check" => handle_preflight_check(&request.payload),
         "force_unmount" => handle_force_unmount(&request.payload),
+        "safe_eject" => handle_safe_eject(&request.payload),
+        "wipe_signatures" => handle_wipe_signatures(&request.payload),
+        "fstab_entry" => handle_fstab_entry(&request.payload),
+        "filesystem_constraints" => handle_filesystem_constraints(),
+        "supported_filesystems" => handle_supported_filesystems(),
+        "mount_ntfs_rw" => handle_mount_ntfs_rw(&request.payload),
+        "benchmark_device" => handle_benchmark_device(&request.payload),
         "secure_erase" => handle_secure_erase(&request.payload),
         "apfs_list_volumes" => handle_apfs_list_volumes(&request.payload),
         "apfs_add_volume" => handle_apfs_add_volume(&request.payload),
         "apfs_delete_volume" => handle_apfs_delete_volume(&request.payload),
+        "encrypt_volume" => handle_encrypt_volume(&request.payload),
+        "apfs_set_quota" => handle_apfs_set_quota(&request.payload),
+        "apfs_list_snapshots" => handle_apfs_list_snapshots(&request.payload),
+        "apfs_delete_snapshot" => handle_apfs_delete_snapshot(&request.payload),
         "flash_image" => handle_flash_image(&request.payload),
         "inspect_image" => handle_inspect_image(&request.payload),
         "hash_image" => handle_hash_image(&request.payload),
         "backup_image" => handle_backup_image(&request.payload),
         "windows_install" => handle_windows_install(&request.payload),
+        "version" => handle_version(),
         "get_journal" => handle_get_journal(),
         "clear_journal" => handle_clear_journal(),
-        _ => Err("Unknown action".to_string()),
+        "get_audit_log" => handle_get_audit_log(&request.payload),
+        "clear_audit_log" => handle_clear_audit_log(),
+        _ => Err(coded("UNKNOWN_ACTION", "Unknown action")),
     };
 
+    append_audit_entry(&request.action, &request.payload, &result);
+
+    // Abgebrochene Operationen: Daten auf das Geraet bringen und Journal als abgebrochen markieren,
+    // statt es zu loeschen, damit get_journal den Zustand beim naechsten Start meldet.
+    if CANCELLED.load(Ordering::SeqCst) {
+        unsafe {
+            libc::sync();
+        }
+        let _ = annotate_journal("aborted", json!(true));
+    }
+
+    let result = result.map(|details| {
+        if !is_dry_run() {
+            return details;
+        }
+        let mut details = details.unwrap_or_else(|| json!({}));
+        if let Some(map) = details.as_object_mut() {
+            map.insert("dryRun".to_string(), json!(true));
+            let commands = DRY_RUN_COMMANDS.lock().map(|c| c.clone()).unwrap_or_default();
+            map.insert("commands".to_string(), json!(commands));
+        }
+        Some(details)
+    });
+
+    let result = result.map(|details| {
+        let tool_warnings = TOOL_WARNINGS.lock().map(|w| w.clone()).unwrap_or_default();
+        if tool_warnings.is_empty() {
+            return details;
+        }
+        let mut details = details.unwrap_or_else(|| json!({}));
+        if let Some(map) = details.as_object_mut() {
+            let warnings = map.entry("warnings").or_insert_with(|| json!([]));
+            if let Some(list) = warnings.as_array_mut() {
+                list.extend(tool_warnings.into_iter().map(Value::String));
+            }
+        }
+        Some(details)
+    });
+
+    let result = result.map(|details| {
+        let unmounted = UNMOUNTED_VOLUMES.lock().map(|v| v.clone()).unwrap_or_default();
+        if unmounted.is_empty() {
+            return details;
+        }
+        let mut details = details.unwrap_or_else(|| json!({}));
+        if let Some(map) = details.as_object_mut() {
+            map.entry("unmountedVolumes").or_insert_with(|| json!(unmounted));
+        }
+        Some(details)
+    });
+
     match result {
-        Ok(details) => write_response(true, None, details),
-        Err(message) => write_response(false, Some(message), None),
+        Ok(details) => write_response(true, None, None, details),
+        Err(message) => {
+            let (code, message) = split_error_code(&message);
+            write_response(false, code, Some(message), None)
+        }
     }
 }
 
+// Protokollrevision des JSON-Formats; bei inkompatiblen Aenderungen erhoehen
+// (Gegenstueck: HELPER_PROTOCOL_REVISION in partitioning/mod.rs).
+const HELPER_PROTOCOL_REVISION: u64 = 1;
+
+fn handle_version() -> Result<Option<Value>, String> {
+    Ok(Some(json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "protocol": HELPER_PROTOCOL_REVISION,
+    })))
+}
+
 fn handle_wipe_device(payload: &Value) -> Result<Option<Value>, String> {
-    let device_identifier = read_string(payload, "deviceIdentifier")?;
+    let device_identifier = read_device_identifier(payload, "deviceIdentifier")?;
     let table_type = read_string(payload, "tableType")?;
     let format_type = read_string(payload, "formatType")?;
     let label = read_string(payload, "label")?;
+    let (label, label_warning) = checked_label(&format_type, &label)?;
 
     let scheme = match table_type.to_lowercase().as_str() {
         "gpt" => "GPT",
@@ -93,7 +315,9 @@ fn handle_wipe_device(payload: &Value) -> Result<Option<Value>, String> {
     };
 
     let device = normalize_device(&device_identifier);
+    require_confirmation(&device, payload)?;
 
+    begin_journal("wipe", &device, payload)?;
     force_unmount_disk(&device)?;
 
     let result = match format_type.to_lowercase().as_str() {
@@ -115,20 +339,29 @@ fn handle_wipe_device(payload: &Value) -> Result<Option<Value>, String> {
         "xfs" => wipe_linux_device(&device, scheme, "xfs", &label),
         "f2fs" => wipe_linux_device(&device, scheme, "f2fs", &label),
         "swap" => wipe_linux_device(&device, scheme, "swap", &label),
+        "zfs" => wipe_linux_device(&device, scheme, "zfs", &label),
         other => Err(format!("Unsupported format type: {other}")),
     };
 
     if result.is_ok() {
         sync_kernel_table(&device);
+        clear_journal();
     }
-    result
+    let mount_after = read_mount_after(payload);
+    mount_after_operation(attach_label_warning(result, label_warning), &device, None, mount_after)
 }
 
 fn handle_secure_erase(payload: &Value) -> Result<Option<Value>, String> {
-    let device_identifier = read_string(payload, "deviceIdentifier")?;
+    let device_identifier = read_device_identifier(payload, "deviceIdentifier")?;
     let level = read_u64(payload, "level")?;
+    // diskutil secureErase nur auf ausdruecklichen Wunsch; Standard ist das eigene Ueberschreiben
+    let fast_path = payload
+        .get("fastPath")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
 
     let device = normalize_device(&device_identifier);
+    require_confirmation(&device, payload)?;
     let info = disk_info_dict(&device)?;
     let is_internal = info
         .get("Internal")
@@ -144,12 +377,32 @@ fn handle_secure_erase(payload: &Value) -> Result<Option<Value>, String> {
         .unwrap_or("")
         .to_string();
 
+    // Pass-Muster je Level: 0 = Nullen, 1 = Zufall, 2 = 7 Durchgaenge (DoD), 3 = Zufall, Zufall, Nullen (DoE).
+    let passes: &[ErasePattern] = match level {
+        0 => &[ErasePattern::Zero],
+        1 => &[ErasePattern::Random],
+        2 => &[
+            ErasePattern::Zero,
+            ErasePattern::Ones,
+            ErasePattern::Random,
+            ErasePattern::Random,
+            ErasePattern::Zero,
+            ErasePattern::Ones,
+            ErasePattern::Random,
+        ],
+        3 => &[ErasePattern::Random, ErasePattern::Random, ErasePattern::Zero],
+        other => return Err(format!("Unsupported secure erase level: {other}")),
+    };
+    let diskutil_level = diskutil_secure_erase_level(level)?;
+
+    begin_journal("secure_erase", &device, payload)?;
     force_unmount_disk(&device)?;
 
     if is_internal && is_solid_state {
         let container = find_apfs_container_for_disk(&device)?;
         run_diskutil(["apfs", "deleteContainer", &container])?;
         sync_kernel_table(&device);
+        clear_journal();
         return Ok(Some(json!({
             "device": device,
             "mode": "crypto",
@@ -158,26 +411,123 @@ fn handle_secure_erase(payload: &Value) -> Result<Option<Value>, String> {
         })));
     }
 
-    let level_str = match level {
-        0 => "0",
-        1 => "1",
-        2 => "2",
-        3 => "3",
-        other => return Err(format!("Unsupported secure erase level: {other}")),
-    };
+    // Ueberschreiben bringt bei SSDs wegen Wear-Leveling wenig; diskutil nutzt dort TRIM/Secure Erase.
+    if is_solid_state && fast_path {
+        emit_log("secure_erase", "SSD erkannt, nutze diskutil secureErase");
+        run_diskutil(["secureErase", &diskutil_level.to_string(), &device])?;
+        sync_kernel_table(&device);
+        clear_journal();
+        return Ok(Some(json!({
+            "device": device,
+            "mode": "secureErase",
+            "level": level,
+            "diskutilLevel": diskutil_level,
+            "busProtocol": bus_protocol,
+        })));
+    }
+
+    let disk_size = read_disk_size(&device).unwrap_or(0);
+    if disk_size == 0 {
+        return Err("Unable to determine device size".to_string());
+    }
+    let raw_device = raw_device_path(&device);
+    for (idx, pattern) in passes.iter().enumerate() {
+        emit_log(
+            "secure_erase",
+            &format!("Durchgang {}/{} ({})", idx + 1, passes.len(), pattern.as_str()),
+        );
+        overwrite_device_pass(&raw_device, disk_size, *pattern, idx as u64, passes.len() as u64)?;
+    }
 
-    run_diskutil(["secureErase", level_str, &device])?;
     sync_kernel_table(&device);
+    clear_journal();
     Ok(Some(json!({
         "device": device,
-        "mode": "secureErase",
+        "mode": "overwrite",
         "level": level,
+        "passes": passes.len(),
+        "bytes": disk_size,
         "busProtocol": bus_protocol,
     })))
 }
 
+#[derive(Clone, Copy)]
+enum ErasePattern {
+    Zero,
+    Ones,
+    Random,
+}
+
+impl ErasePattern {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ErasePattern::Zero => "zero",
+            ErasePattern::Ones => "ones",
+            ErasePattern::Random => "random",
+        }
+    }
+}
+
+// Oxidisk-Level -> diskutil secureErase-Level. diskutil zaehlt anders: 2 = DoD 7-pass,
+// 3 = Gutmann 35-pass, 4 = DoE 3-pass. Unser Level 3 (3 Durchgaenge) entspricht daher 4.
+fn diskutil_secure_erase_level(level: u64) -> Result<u64, String> {
+    match level {
+        0 => Ok(0),
+        1 => Ok(1),
+        2 => Ok(2),
+        3 => Ok(4),
+        other => Err(format!("Unsupported secure erase level: {other}")),
+    }
+}
+
+fn overwrite_device_pass(
+    target_device: &str,
+    total_bytes: u64,
+    pattern: ErasePattern,
+    pass: u64,
+    passes: u64,
+) -> Result<(), String> {
+    let mut target = open_device_for_write(target_device)?;
+    let mut random = match pattern {
+        ErasePattern::Random => Some(
+            std::fs::File::open("/dev/urandom").map_err(|e| format!("Open /dev/urandom failed: {e}"))?,
+        ),
+        ErasePattern::Zero | ErasePattern::Ones => None,
+    };
+
+    let buffer_size = 4 * 1024 * 1024;
+    let fill = if matches!(pattern, ErasePattern::Ones) { 0xFF } else { 0 };
+    let mut buffer = vec![fill; buffer_size];
+    let mut remaining = total_bytes;
+    let mut copied: u64 = 0;
+    let progress_step: u64 = 50 * 1024 * 1024;
+    let mut next_progress = progress_step;
+    let overall_total = total_bytes.saturating_mul(passes);
+
+    while remaining > 0 {
+        ensure_not_cancelled()?;
+        let chunk = std::cmp::min(buffer_size as u64, remaining) as usize;
+        if let Some(random) = random.as_mut() {
+            random.read_exact(&mut buffer[..chunk]).map_err(|e| e.to_string())?;
+        }
+        target.write_all(&buffer[..chunk]).map_err(|e| e.to_string())?;
+        remaining -= chunk as u64;
+        copied += chunk as u64;
+        if copied >= next_progress || remaining == 0 {
+            let overall = pass * total_bytes + copied;
+            let percent = ((overall as f64 / overall_total as f64) * 100.0).round() as u64;
+            emit_progress_bytes("secure_erase", percent, 100, Some("Overwriting device"), overall, overall_total);
+            let _ = update_journal_progress(overall);
+            next_progress += progress_step;
+        }
+    }
+
+    target.flush().map_err(|e| format!("Flush failed: {e}"))?;
+    Ok(())
+}
+
 fn handle_create_partition_table(payload: &Value) -> Result<Option<Value>, String> {
-    let device_identifier = read_string(payload, "deviceIdentifier")?;
+    let device_identifier = read_device_identifier(payload, "deviceIdentifier")?;
     let table_type = read_string(payload, "tableType")?;
 
     let scheme = match table_type.to_lowercase().as_str() {
@@ -187,6 +537,7 @@ fn handle_create_partition_table(payload: &Value) -> Result<Option<Value>, Strin
     };
 
     let device = normalize_device(&device_identifier);
+    require_confirmation(&device, payload)?;
 
     force_unmount_disk(&device)?;
     run_diskutil([
@@ -204,16 +555,143 @@ fn handle_create_partition_table(payload: &Value) -> Result<Option<Value>, Strin
     Ok(Some(json!({ "device": device, "scheme": scheme })))
 }
 
+// Wandelt die Tabelle in place um (sgdisk --mbrtogpt bzw. --gpttombr). Vorher wird geprueft,
+// ob das Layout in das Zielformat passt; sonst Abbruch mit Begruendung statt Datenverlust.
+fn handle_convert_partition_table(payload: &Value) -> Result<Option<Value>, String> {
+    let device_identifier = read_device_identifier(payload, "deviceIdentifier")?;
+    let target = read_string(payload, "target")?.to_lowercase();
+    if target != "gpt" && target != "mbr" {
+        return Err(format!("Unsupported table type: {target}"));
+    }
+
+    let device = normalize_device(&device_identifier);
+    require_confirmation(&device, payload)?;
+    if find_sidecar("sgdisk").is_err() {
+        return Err(coded("SIDECAR_MISSING", "sgdisk is required to convert the partition table"));
+    }
+
+    let info = disk_info_dict(&device)?;
+    let current = match info.get("Content").and_then(|v| v.as_string()) {
+        Some("GUID_partition_scheme") => "gpt",
+        Some("FDisk_partition_scheme") => "mbr",
+        other => {
+            return Err(format!(
+                "Only MBR and GPT tables can be converted (found {})",
+                other.unwrap_or("none")
+            ))
+        }
+    };
+    if current == target {
+        return Err(format!("{device} already uses {target}"));
+    }
+
+    let (disk_size, block_size) = disk_geometry(&device)?;
+    let mut extents = partition_extents(&device)?;
+    extents.sort_by_key(|(_, offset, _)| *offset);
+    if let Some(reason) = table_conversion_blocker(&target, &extents, disk_size, block_size) {
+        return Err(coded("CONVERSION_NOT_POSSIBLE", &reason));
+    }
+
+    let mut args: Vec<String> = Vec::new();
+    if target == "gpt" {
+        args.push("--mbrtogpt".to_string());
+    } else {
+        let numbers: Vec<String> = extents
+            .iter()
+            .filter_map(|(identifier, _, _)| partition_number(identifier))
+            .map(|number| number.to_string())
+            .collect();
+        args.push(format!("--gpttombr={}", numbers.join(":")));
+    }
+    args.push(device.clone());
+
+    begin_journal("convert_table", &device, payload)?;
+    force_unmount_disk(&device)?;
+    let output = if is_dry_run() {
+        log_dry_run("sgdisk", &args);
+        String::new()
+    } else {
+        run_sidecar_capture("sgdisk", &args)?
+    };
+    sync_kernel_table(&device);
+    clear_journal();
+
+    Ok(Some(json!({
+        "device": device,
+        "from": current,
+        "to": target,
+        "partitions": extents.len(),
+        "output": output,
+    })))
+}
+
+// Grund, warum die Umwandlung nicht ohne Datenverlust geht; None = machbar.
+// extents: (Identifier, Offset, Groesse) nach Offset sortiert.
+fn table_conversion_blocker(
+    target: &str,
+    extents: &[(String, u64, u64)],
+    disk_size: u64,
+    block_size: u64,
+) -> Option<String> {
+    let block_size = block_size.max(1);
+    match target {
+        "mbr" => {
+            if extents.len() > 4 {
+                return Some(format!("MBR allows at most 4 primary partitions, disk has {}", extents.len()));
+            }
+            // MBR speichert Start-LBA und Sektoranzahl als 32 Bit
+            let limit = u32::MAX as u64;
+            for (identifier, offset, size) in extents {
+                if offset / block_size > limit || size / block_size > limit {
+                    return Some(format!("{identifier} lies beyond the MBR addressing limit"));
+                }
+            }
+            None
+        }
+        "gpt" => {
+            // Primaere GPT belegt die ersten 34 Sektoren, die Sicherung die letzten 33
+            let header_end = 34 * block_size;
+            let footer_start = disk_size.saturating_sub(33 * block_size);
+            if let Some((identifier, offset, _)) = extents.first() {
+                if *offset < header_end {
+                    return Some(format!("{identifier} starts inside the space needed for the GPT header"));
+                }
+            }
+            if let Some((identifier, offset, size)) = extents.iter().max_by_key(|(_, offset, size)| offset + size) {
+                if offset + size > footer_start {
+                    return Some(format!("{identifier} ends inside the space needed for the backup GPT"));
+                }
+            }
+            None
+        }
+        _ => Some(format!("Unsupported table type: {target}")),
+    }
+}
+
 fn handle_create_partition(payload: &Value) -> Result<Option<Value>, String> {
-    let device_identifier = read_string(payload, "deviceIdentifier")?;
+    let device_identifier = read_device_identifier(payload, "deviceIdentifier")?;
     let format_type = read_string(payload, "formatType")?;
     let label = read_string(payload, "label")?;
+    let (label, label_warning) = checked_label(&format_type, &label)?;
     let size = read_string(payload, "size")?;
+    let opts = read_mkfs_options(payload, &format_type)?;
+    let start = payload
+        .get("start")
+        .and_then(|value| value.as_str())
+        .map(|value| value.to_string());
 
     let device = normalize_device(&device_identifier);
+    require_confirmation(&device, payload)?;
 
     force_unmount_disk(&device)?;
 
+    let mount_after = read_mount_after(payload);
+
+    if let Some(start) = start {
+        let result = create_partition_at(&device, &format_type.to_lowercase(), &label, &start, &size, &opts);
+        return mount_after_operation(attach_label_warning(result, label_warning), &device, None, mount_after);
+    }
+
     let result = match format_type.to_lowercase().as_str() {
         "exfat" => {
             run_diskutil(["addPartition", &device, "ExFAT", &label, &size])?;
@@ -223,24 +701,129 @@ fn handle_create_partition(payload: &Value) -> Result<Option<Value>, String> {
             run_diskutil(["addPartition", &device, "MS-DOS", &label, &size])?;
             Ok(Some(json!({ "device": device, "format": "MS-DOS", "size": size })))
         }
-        "ext4" => create_linux_partition(&device, "ext4", &label, &size),
-        "ntfs" => create_linux_partition(&device, "ntfs", &label, &size),
-        "btrfs" => create_linux_partition(&device, "btrfs", &label, &size),
-        "xfs" => create_linux_partition(&device, "xfs", &label, &size),
-        "f2fs" => create_linux_partition(&device, "f2fs", &label, &size),
-        "swap" => create_linux_partition(&device, "swap", &label, &size),
+        "ext4" => create_linux_partition(&device, "ext4", &label, &size, &opts),
+        "ntfs" => create_linux_partition(&device, "ntfs", &label, &size, &opts),
+        "btrfs" => create_linux_partition(&device, "btrfs", &label, &size, &opts),
+        "xfs" => create_linux_partition(&device, "xfs", &label, &size, &opts),
+        "f2fs" => create_linux_partition(&device, "f2fs", &label, &size, &opts),
+        "swap" => create_linux_partition(&device, "swap", &label, &size, &opts),
+        "zfs" => create_linux_partition(&device, "zfs", &label, &size, &opts),
         other => Err(format!("Unsupported format type: {other}")),
     };
 
     if result.is_ok() {
         sync_kernel_table(&device);
     }
-    result
+    mount_after_operation(attach_label_warning(result, label_warning), &device, None, mount_after)
+}
+
+// Legt alle Partitionen mit einem einzigen diskutil partitionDisk an. Linux-Dateisysteme
+// werden zuerst als MS-DOS mit temporärem Label angelegt und danach per Treiber formatiert.
+fn handle_apply_partition_layout(payload: &Value) -> Result<Option<Value>, String> {
+    let device_identifier = read_device_identifier(payload, "deviceIdentifier")?;
+    let table_type = read_string(payload, "tableType")?;
+    let entries = payload
+        .get("partitions")
+        .and_then(|value| value.as_array())
+        .ok_or_else(|| "Missing partitions".to_string())?;
+    if entries.is_empty() {
+        return Err("Layout contains no partitions".to_string());
+    }
+
+    let scheme = match table_type.to_lowercase().as_str() {
+        "gpt" => "GPT",
+        "mbr" => "MBR",
+        other => return Err(format!("Unsupported table type: {other}")),
+    };
+
+    let device = normalize_device(&device_identifier);
+    require_confirmation(&device, payload)?;
+    let stamp = current_timestamp() % 100_000;
+
+    // (format, label, size, temporäres Label für Linux-Dateisysteme)
+    let mut specs: Vec<(String, String, String, Option<String>)> = Vec::new();
+    let mut label_warnings: Vec<Option<String>> = Vec::new();
+    for (idx, entry) in entries.iter().enumerate() {
+        let format_type = read_string(entry, "formatType")?.to_lowercase();
+        let (label, label_warning) = checked_label(&format_type, &read_string(entry, "label")?)?;
+        let size = read_string(entry, "size")?;
+        let temp_label = match format_type.as_str() {
+            "exfat" | "fat32" | "apfs" => None,
+            other if driver_for(other).is_some() => Some(format!("OXL{idx}{stamp}")),
+            other => return Err(format!("Unsupported format type: {other}")),
+        };
+        specs.push((format_type, label, size, temp_label));
+        label_warnings.push(label_warning);
+    }
+
+    begin_journal("apply_partition_layout", &device, payload)?;
+
+    force_unmount_disk(&device)?;
+
+    let total = specs.len() as u64 + 1;
+    emit_progress("layout", 0, total, Some("Create partition table"));
+
+    let mut args = vec![
+        "partitionDisk".to_string(),
+        device.clone(),
+        specs.len().to_string(),
+        scheme.to_string(),
+    ];
+    for (format_type, label, size, temp_label) in &specs {
+        let diskutil_format = match format_type.as_str() {
+            "exfat" => "ExFAT",
+            "apfs" => "APFS",
+            _ => "MS-DOS",
+        };
+        args.push(diskutil_format.to_string());
+        args.push(temp_label.clone().unwrap_or_else(|| label.clone()));
+        args.push(size.clone());
+    }
+    run_diskutil(&args)?;
+    emit_progress("layout", 1, total, Some("Partition table created"));
+
+    let mut created: Vec<Value> = Vec::new();
+    for (idx, (format_type, label, size, temp_label)) in specs.iter().enumerate() {
+        let message = format!("Format partition {} ({format_type})", idx + 1);
+        emit_progress("layout", idx as u64 + 1, total, Some(&message));
+
+        let mut partition: Option<String> = None;
+        let mut warning: Option<String> = None;
+        if let Some(temp_label) = temp_label {
+            let new_partition = find_partition_by_label(temp_label)?
+                .ok_or_else(|| format!("Failed to locate partition {}", idx + 1))?;
+            let new_device = normalize_device(&new_partition);
+            run_diskutil(["unmount", &new_device])?;
+
+            let (bin, args) = driver_for(format_type)
+                .and_then(|driver| driver.mkfs_command(&new_device, label))
+                .ok_or_else(|| "Unsupported filesystem".to_string())?;
+            run_sidecar_stream(&bin, args)?;
+            warning = set_partition_typecode(&new_device, format_type)?;
+            partition = Some(new_device);
+        }
+
+        created.push(json!({
+            "partition": partition,
+            "format": format_type,
+            "label": label,
+            "size": size,
+            "warning": warning,
+            "labelWarning": label_warnings[idx],
+        }));
+    }
+
+    sync_kernel_table(&device);
+    clear_journal();
+    emit_progress("layout", total, total, Some("Layout applied"));
+
+    Ok(Some(json!({ "device": device, "scheme": scheme, "partitions": created })))
 }
 
 fn handle_delete_partition(payload: &Value) -> Result<Option<Value>, String> {
-    let partition_identifier = read_string(payload, "partitionIdentifier")?;
+    let partition_identifier = read_device_identifier(payload, "partitionIdentifier")?;
     let device = normalize_device(&partition_identifier);
+    require_confirmation(&device, payload)?;
 
     maybe_swapoff(&device)?;
     force_unmount_disk(&device)?;
@@ -253,12 +836,16 @@ fn handle_delete_partition(payload: &Value) -> Result<Option<Value>, String> {
 }
 
 fn handle_format_partition(payload: &Value) -> Result<Option<Value>, String> {
-    let partition_identifier = read_string(payload, "partitionIdentifier")?;
+    let partition_identifier = read_device_identifier(payload, "partitionIdentifier")?;
     let format_type = read_string(payload, "formatType")?;
     let label = read_string(payload, "label")?;
+    let (label, label_warning) = checked_label(&format_type, &label)?;
+    let opts = read_mkfs_options(payload, &format_type)?;
 
     let device = normalize_device(&partition_identifier);
+    require_confirmation(&device, payload)?;
 
+    begin_journal("format", &device, payload)?;
     maybe_swapoff(&device)?;
     force_unmount_disk(&device)?;
 
@@ -275,23 +862,244 @@ fn handle_format_partition(payload: &Value) -> Result<Option<Value>, String> {
             run_diskutil(["eraseVolume", "APFS", &label, &device])?;
             Ok(Some(json!({ "device": device, "format": "APFS" })))
         }
-        "ext4" => format_linux_partition(&device, "ext4", &label),
-        "ntfs" => format_linux_partition(&device, "ntfs", &label),
-        "btrfs" => format_linux_partition(&device, "btrfs", &label),
-        "xfs" => format_linux_partition(&device, "xfs", &label),
-        "f2fs" => format_linux_partition(&device, "f2fs", &label),
-        "swap" => format_linux_partition(&device, "swap", &label),
+        "ext4" => format_linux_partition(&device, "ext4", &label, &opts),
+        "ntfs" => format_linux_partition(&device, "ntfs", &label, &opts),
+        "btrfs" => format_linux_partition(&device, "btrfs", &label, &opts),
+        "xfs" => format_linux_partition(&device, "xfs", &label, &opts),
+        "f2fs" => format_linux_partition(&device, "f2fs", &label, &opts),
+        "swap" => format_linux_partition(&device, "swap", &label, &opts),
+        "zfs" => format_linux_partition(&device, "zfs", &label, &opts),
         other => Err(format!("Unsupported format type: {other}")),
     };
 
     if result.is_ok() {
         sync_kernel_table(&device);
+        clear_journal();
     }
-    result
+    let mount_after = read_mount_after(payload);
+    mount_after_operation(attach_label_warning(result, label_warning), &device, Some(&device), mount_after)
+}
+
+// Entfernt nur Dateisystem-/Partitionstabellen-Signaturen (wipefs -a), ohne die Daten zu
+// ueberschreiben. Hilft bei Phantom-Partitionen nach einem abgebrochenen Flash.
+fn handle_wipe_signatures(payload: &Value) -> Result<Option<Value>, String> {
+    let device_identifier = read_device_identifier(payload, "deviceIdentifier")?;
+    let device = normalize_device(&device_identifier);
+    if is_boot_volume(&device) || disk_hosts_system_volume(&device) {
+        return Err(coded("PROTECTED_VOLUME", "Refusing to wipe signatures on the system disk"));
+    }
+    require_confirmation(&device, payload)?;
+    let wipefs = find_sidecar("wipefs")?;
+
+    begin_journal("wipe_signatures", &device, payload)?;
+    force_unmount_disk(&device)?;
+
+    let output = if is_dry_run() {
+        log_dry_run(&wipefs.to_string_lossy(), ["-a", device.as_str()]);
+        String::new()
+    } else {
+        run_sidecar_capture("wipefs", ["-a", device.as_str()])?
+    };
+    let removed: Vec<Value> = parse_wipefs_output(&output)
+        .into_iter()
+        .map(|(signature, offset, bytes)| json!({ "signature": signature, "offset": offset, "bytes": bytes }))
+        .collect();
+
+    sync_kernel_table(&device);
+    clear_journal();
+
+    Ok(Some(json!({
+        "device": device,
+        "removed": removed,
+        "output": output,
+    })))
+}
+
+// "/dev/disk4: 8 bytes were erased at offset 0x00000200 (gpt): 45 46 49 20 50 41 52 54"
+// -> (Signatur, Offset, Bytes)
+fn parse_wipefs_output(output: &str) -> Vec<(String, u64, u64)> {
+    let Ok(pattern) = Regex::new(r"(\d+) bytes? (?:was|were) erased at offset 0x([0-9a-fA-F]+) \(([^)]+)\)") else {
+        return Vec::new();
+    };
+    pattern
+        .captures_iter(output)
+        .filter_map(|captures| {
+            let bytes = captures.get(1)?.as_str().parse::<u64>().ok()?;
+            let offset = u64::from_str_radix(captures.get(2)?.as_str(), 16).ok()?;
+            Some((captures.get(3)?.as_str().to_string(), offset, bytes))
+        })
+        .collect()
+}
+
+fn read_mount_after(payload: &Value) -> bool {
+    payload.get("mountAfter").and_then(|v| v.as_bool()).unwrap_or(false)
+}
+
+// mountAfter: die neue/formatierte Partition direkt einhaengen und den Mountpunkt in den
+// Details melden. Ohne partition-Hinweis gilt details.partition bzw. die hoechste Partition
+// der Disk (eraseDisk/addPartition nennen die neue Partition nicht). Scheitert das Mounten
+// (z.B. ext4 ohne FUSE-Treiber), bleibt die Operation erfolgreich und meldet mountError.
+fn mount_after_operation(
+    result: Result<Option<Value>, String>,
+    device: &str,
+    partition: Option<&str>,
+    mount_after: bool,
+) -> Result<Option<Value>, String> {
+    // Im Dry-Run gibt es die neue Partition nicht; mount_ntfs wuerde sonst real einhaengen
+    if !mount_after || is_dry_run() {
+        return result;
+    }
+    let mut details = result?.unwrap_or_else(|| json!({}));
+    if details.get("format").and_then(|v| v.as_str()) == Some("swap") {
+        return Ok(Some(details));
+    }
+
+    let partition = partition
+        .map(|p| p.to_string())
+        .or_else(|| details.get("partition").and_then(|v| v.as_str()).map(normalize_device))
+        .or_else(|| {
+            list_disk_partitions(device).ok().and_then(|ids| {
+                ids.into_iter()
+                    .max_by_key(|id| partition_number(id).unwrap_or(0))
+                    .map(|id| normalize_device(&id))
+            })
+        });
+    let Some(partition) = partition else {
+        details["mountError"] = json!("No partition found to mount");
+        return Ok(Some(details));
+    };
+
+    // NTFS mountet macOS nur lesend; wenn vorhanden, direkt per ntfs-3g beschreibbar einhaengen
+    if detect_fs_type(&partition).ok().as_deref() == Some("ntfs") {
+        match mount_ntfs(&partition) {
+            Ok(mount) => {
+                details["mountPoint"] = json!(mount.mount_point);
+                details["readWrite"] = json!(mount.read_write);
+                if let Some(warning) = mount.warning {
+                    details["mountWarning"] = json!(warning);
+                }
+            }
+            Err(err) => details["mountError"] = json!(err),
+        }
+        details["mountedPartition"] = json!(partition);
+        return Ok(Some(details));
+    }
+
+    match run_diskutil(["mount", &partition]) {
+        Ok(()) => {
+            let mount_point = read_mount_point(&partition).ok().flatten();
+            details["mountPoint"] = json!(mount_point);
+        }
+        Err(err) => details["mountError"] = json!(err),
+    }
+    details["mountedPartition"] = json!(partition);
+    Ok(Some(details))
+}
+
+fn handle_mount_ntfs_rw(payload: &Value) -> Result<Option<Value>, String> {
+    let device_identifier = read_device_identifier(payload, "deviceIdentifier")?;
+    let device = normalize_device(&device_identifier);
+
+    let fs_type = detect_fs_type(&device)?;
+    if fs_type != "ntfs" {
+        return Err(format!("{device} is not an NTFS volume ({fs_type})"));
+    }
+
+    let mount = mount_ntfs(&device)?;
+    let mut details = json!({
+        "device": device,
+        "mountPoint": mount.mount_point,
+        "readWrite": mount.read_write,
+        "driver": if mount.read_write { "ntfs-3g" } else { "diskutil" },
+    });
+    if let Some(warning) = mount.warning {
+        details["warnings"] = json!([warning]);
+    }
+    Ok(Some(details))
+}
+
+struct NtfsMount {
+    mount_point: Option<String>,
+    read_write: bool,
+    warning: Option<String>,
+}
+
+// Haengt ein NTFS-Volume ueber den ntfs-3g-Sidecar (macFUSE) beschreibbar ein. Fehlt ntfs-3g,
+// wird wie bisher per diskutil nur lesend gemountet und eine Warnung zurueckgegeben.
+fn mount_ntfs(device: &str) -> Result<NtfsMount, String> {
+    let ntfs_3g = match find_sidecar("ntfs-3g") {
+        Ok(path) => path,
+        Err(_) => {
+            let warning = "ntfs-3g not found; NTFS volume mounted read-only".to_string();
+            emit_log("mount", &warning);
+            if read_mount_point(device)?.is_none() {
+                run_diskutil(["mount", "readOnly", device])?;
+            }
+            return Ok(NtfsMount {
+                mount_point: read_mount_point(device).ok().flatten(),
+                read_write: false,
+                warning: Some(warning),
+            });
+        }
+    };
+
+    // Ein vorhandener (lesender) Mount von macOS muss erst weg, sonst ist das Device belegt
+    if read_mount_point(device)?.is_some() {
+        run_diskutil(["unmount", device])?;
+    }
+
+    let info = disk_info_dict(device)?;
+    let volume_name = info
+        .get("VolumeName")
+        .and_then(|v| v.as_string())
+        .map(|name| name.trim().replace('/', "_"))
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| device.trim_start_matches("/dev/").to_string());
+    let mut mount_point = PathBuf::from("/Volumes").join(&volume_name);
+    let occupied = std::fs::read_dir(&mount_point)
+        .map(|mut entries| entries.next().is_some())
+        .unwrap_or(false);
+    if occupied {
+        mount_point = PathBuf::from("/Volumes").join(format!(
+            "{volume_name} ({})",
+            device.trim_start_matches("/dev/")
+        ));
+    }
+    let mount_point = mount_point.to_string_lossy().to_string();
+    let volname = format!("volname={volume_name}");
+    let args = [
+        device,
+        mount_point.as_str(),
+        "-o",
+        "local",
+        "-o",
+        "allow_other",
+        "-o",
+        "auto_xattr",
+        "-o",
+        volname.as_str(),
+    ];
+
+    if is_dry_run() {
+        log_dry_run(&ntfs_3g.to_string_lossy(), args);
+    } else {
+        std::fs::create_dir_all(&mount_point).map_err(|e| format!("Mount dir failed: {e}"))?;
+        if let Err(err) = run_sidecar_capture("ntfs-3g", args) {
+            let _ = std::fs::remove_dir(&mount_point);
+            return Err(err);
+        }
+    }
+    emit_log("mount", &format!("{device} per ntfs-3g beschreibbar unter {mount_point} eingehaengt"));
+
+    Ok(NtfsMount {
+        mount_point: Some(mount_point),
+        read_write: true,
+        warning: None,
+    })
 }
 
 fn handle_set_label_uuid(payload: &Value) -> Result<Option<Value>, String> {
-    let partition_identifier = read_string(payload, "partitionIdentifier")?;
+    let partition_identifier = read_device_identifier(payload, "partitionIdentifier")?;
     let device = normalize_device(&partition_identifier);
 
     let label = payload
@@ -308,6 +1116,13 @@ fn handle_set_label_uuid(payload: &Value) -> Result<Option<Value>, String> {
     }
 
     let fs_type = detect_fs_type(&device)?;
+    let (label, label_warning) = match label {
+        Some(label) => {
+            let (label, warning) = checked_label(&fs_type, &label)?;
+            (Some(label), warning)
+        }
+        None => (None, None),
+    };
     match fs_type.as_str() {
         "apfs" => {
             if let Some(new_label) = label.as_ref() {
@@ -327,8 +1142,8 @@ fn handle_set_label_uuid(payload: &Value) -> Result<Option<Value>, String> {
                     }
                 }
                 if let Some(new_uuid) = uuid.as_ref() {
-                    validate_uuid(new_uuid)?;
-                    if let Some((bin, args)) = driver.uuid_command(&device, new_uuid) {
+                    let new_uuid = validate_volume_id(&fs_type, new_uuid)?;
+                    if let Some((bin, args)) = driver.uuid_command(&device, &new_uuid) {
                         run_sidecar_stream(&bin, args)?;
                     } else {
                         return Err("UUID change not supported".to_string());
@@ -338,10 +1153,24 @@ fn handle_set_label_uuid(payload: &Value) -> Result<Option<Value>, String> {
         }
         "exfat" | "fat32" => {
             if let Some(new_label) = label.as_ref() {
-                run_diskutil(["renameVolume", &device, new_label])?;
+                // exfatlabel/fatlabel bevorzugen, diskutil erkennt unter Linux formatierte Volumes nicht immer
+                let driver_command = driver_for(&fs_type)
+                    .and_then(|driver| driver.label_command(&device, new_label))
+                    .filter(|(bin, _)| find_sidecar(bin).is_ok());
+                if let Some((bin, args)) = driver_command {
+                    let _ = run_diskutil(["unmount", &device]);
+                    run_sidecar_stream(&bin, args)?;
+                } else {
+                    run_diskutil(["renameVolume", &device, new_label])?;
+                }
             }
-            if uuid.is_some() {
-                return Err("FAT/ExFAT UUID change is not supported".to_string());
+            if let Some(new_uuid) = uuid.as_ref() {
+                let serial = validate_volume_id(&fs_type, new_uuid)?;
+                let (bin, args) = driver_for(&fs_type)
+                    .and_then(|driver| driver.uuid_command(&device, &serial))
+                    .ok_or_else(|| "UUID change not supported".to_string())?;
+                let _ = run_diskutil(["unmount", &device]);
+                run_sidecar_stream(&bin, args)?;
             }
         }
         _ => return Err("Unsupported filesystem for label/UUID".to_string()),
@@ -349,11 +1178,49 @@ fn handle_set_label_uuid(payload: &Value) -> Result<Option<Value>, String> {
 
     sync_kernel_table(&device);
 
-    Ok(Some(json!({ "device": device, "label": label, "uuid": uuid, "fs": fs_type })))
+    Ok(Some(json!({ "device": device, "label": label, "uuid": uuid, "fs": fs_type, "labelWarning": label_warning })))
+}
+
+// Nur der Name, ohne UUID: Linux-Dateisysteme und FAT/exFAT laufen ueber set_label_uuid
+// (Treiber + Label-Pruefung), APFS/HFS+ und Unbekanntes direkt ueber diskutil renameVolume.
+fn handle_rename_volume(payload: &Value) -> Result<Option<Value>, String> {
+    let device_identifier = read_device_identifier(payload, "deviceIdentifier")?;
+    let name = read_string(payload, "name")?;
+    if name.trim().is_empty() {
+        return Err("Volume name must not be empty".to_string());
+    }
+    let device = normalize_device(&device_identifier);
+
+    let fs_type = detect_fs_type(&device)?;
+    if driver_for(&fs_type).is_some() {
+        return handle_set_label_uuid(&json!({ "partitionIdentifier": device_identifier, "label": name }));
+    }
+
+    run_diskutil(["renameVolume", &device, &name])?;
+    Ok(Some(json!({ "device": device, "label": name, "fs": fs_type })))
+}
+
+// GPT-Partitionsname (PARTLABEL), unabhaengig vom Dateisystem-Label
+fn handle_set_partition_name(payload: &Value) -> Result<Option<Value>, String> {
+    let partition_identifier = read_device_identifier(payload, "partitionIdentifier")?;
+    let name = read_string(payload, "name")?;
+    let device = normalize_device(&partition_identifier);
+
+    if name.encode_utf16().count() > 36 {
+        return Err("Partition name exceeds the GPT limit of 36 UTF-16 characters".to_string());
+    }
+
+    let part_number = partition_number(&device).ok_or_else(|| "Invalid partition identifier".to_string())?;
+    let disk = parent_disk_identifier(&device).ok_or_else(|| "Invalid disk identifier".to_string())?;
+    run_sidecar("sgdisk", [format!("--change-name={part_number}:{name}"), disk.clone()])?;
+
+    sync_kernel_table(&device);
+
+    Ok(Some(json!({ "device": device, "disk": disk, "partitionNumber": part_number, "name": name })))
 }
 
 fn handle_apfs_list_volumes(payload: &Value) -> Result<Option<Value>, String> {
-    let container_identifier = read_string(payload, "containerIdentifier")?;
+    let container_identifier = read_device_identifier(payload, "containerIdentifier")?;
     let normalized = normalize_device(&container_identifier);
     let needle = strip_device_prefix(&normalized);
 
@@ -368,6 +1235,16 @@ fn handle_apfs_list_volumes(payload: &Value) -> Result<Option<Value>, String> {
     }
 
     let plist = PlistValue::from_reader_xml(&output.stdout[..]).map_err(|e| e.to_string())?;
+    let mut container = parse_apfs_container(&plist, &needle)?;
+    if let Some(volumes) = container["volumes"].as_array_mut() {
+        lookup_volume_groups(volumes);
+        derive_volume_group_fields(volumes);
+    }
+    Ok(Some(container))
+}
+
+// Parst "diskutil apfs list -plist" fuer den Container, der needle (Container oder Physical Store) enthaelt.
+fn parse_apfs_container(plist: &PlistValue, needle: &str) -> Result<Value, String> {
     let dict = plist
         .as_dictionary()
         .ok_or_else(|| "Invalid plist".to_string())?;
@@ -383,12 +1260,12 @@ fn handle_apfs_list_volumes(payload: &Value) -> Result<Option<Value>, String> {
             None => continue,
         };
 
-        if !container_matches(container_dict, &needle) {
+        if !container_matches(container_dict, needle) {
             continue;
         }
 
         let container_identifier = plist_string(container_dict, &["ContainerReference", "DeviceIdentifier", "ContainerIdentifier"])
-            .unwrap_or_else(|| needle.clone());
+            .unwrap_or_else(|| needle.to_string());
         let container_uuid = plist_string(container_dict, &["APFSContainerUUID", "ContainerUUID"]);
         let capacity = plist_u64(container_dict, &["CapacityCeiling", "Capacity"]);
         let capacity_free = plist_u64(container_dict, &["CapacityFree"]);
@@ -401,136 +1278,446 @@ fn handle_apfs_list_volumes(payload: &Value) -> Result<Option<Value>, String> {
             volume_entries.extend(arr.iter());
         }
 
-        let mut volumes = Vec::new();
-        for volume in volume_entries {
-            let volume_dict = match volume.as_dictionary() {
-                Some(d) => d,
-                None => continue,
-            };
-
-            let identifier = plist_string(volume_dict, &["DeviceIdentifier", "DeviceReference"]).unwrap_or_default();
-            let name = plist_string(volume_dict, &["Name", "VolumeName"]).unwrap_or_default();
-            let roles = plist_string_array(volume_dict, &["Roles", "APFSVolumeRoles"]);
-            let volume_group_uuid = plist_string(volume_dict, &["VolumeGroupUUID", "APFSVolumeGroupUUID"]);
-            let volume_group_role = plist_string(volume_dict, &["VolumeGroupRole", "APFSVolumeGroupRole"]);
-            let volume_group_name = plist_string(volume_dict, &["VolumeGroupName", "APFSVolumeGroupName"]);
-            let sealed = volume_dict
-                .get("Sealed")
-                .and_then(|v| v.as_boolean())
-                .or_else(|| volume_dict.get("IsSealed").and_then(|v| v.as_boolean()));
-            let size = plist_u64(volume_dict, &["CapacityInUse", "CapacityInUseBytes", "CapacityUsed"]).unwrap_or(0);
-            let used = plist_u64(volume_dict, &["CapacityInUse", "CapacityInUseBytes", "CapacityUsed"]).unwrap_or(0);
-            let mount_point = plist_string(volume_dict, &["MountPoint"]);
-
-            volumes.push(json!({
-                "identifier": identifier,
-                "name": name,
-                "roles": roles,
-                "volumeGroupUuid": volume_group_uuid,
-                "volumeGroupRole": volume_group_role,
-                "volumeGroupName": volume_group_name,
-                "sealed": sealed,
-                "size": size,
-                "used": used,
-                "mountPoint": mount_point,
-            }));
-        }
+        let mut volumes: Vec<Value> = volume_entries
+            .into_iter()
+            .filter_map(|volume| volume.as_dictionary())
+            .map(parse_apfs_volume)
+            .collect();
+        derive_volume_group_fields(&mut volumes);
 
-        return Ok(Some(json!({
+        return Ok(json!({
             "containerIdentifier": container_identifier,
             "containerUuid": container_uuid,
             "capacity": capacity,
             "capacityFree": capacity_free,
             "capacityUsed": capacity_used,
             "volumes": volumes,
-        })));
+        }));
     }
 
     Err("APFS container not found".to_string())
 }
 
+fn parse_apfs_volume(volume_dict: &plist::Dictionary) -> Value {
+    let identifier = plist_string(volume_dict, &["DeviceIdentifier", "DeviceReference"]).unwrap_or_default();
+    let name = plist_string(volume_dict, &["Name", "VolumeName"]).unwrap_or_default();
+    let roles = plist_string_array(volume_dict, &["Roles", "APFSVolumeRoles"]);
+    let volume_group_uuid = plist_string(volume_dict, &["APFSVolumeGroupID", "VolumeGroupUUID", "APFSVolumeGroupUUID"]);
+    let volume_group_role = plist_string(volume_dict, &["VolumeGroupRole", "APFSVolumeGroupRole"]);
+    let volume_group_name = plist_string(volume_dict, &["VolumeGroupName", "APFSVolumeGroupName"]);
+    // Neuere diskutil-Versionen melden "Sealed" als String ("Yes", "No", "Broken")
+    let sealed = ["Sealed", "IsSealed"].iter().find_map(|key| match volume_dict.get(key) {
+        Some(PlistValue::Boolean(value)) => Some(*value),
+        Some(PlistValue::String(value)) => Some(value.eq_ignore_ascii_case("yes")),
+        _ => None,
+    });
+    let encrypted = volume_dict
+        .get("Encryption")
+        .and_then(|v| v.as_boolean())
+        .or_else(|| volume_dict.get("FileVault").and_then(|v| v.as_boolean()));
+    let locked = volume_dict.get("Locked").and_then(|v| v.as_boolean());
+    let reserve = volume_dict.get("CapacityReserve").and_then(|v| v.as_unsigned_integer()).filter(|v| *v > 0);
+    let quota = volume_dict.get("CapacityQuota").and_then(|v| v.as_unsigned_integer()).filter(|v| *v > 0);
+    let size = plist_u64(volume_dict, &["CapacityInUse", "CapacityInUseBytes", "CapacityUsed"]).unwrap_or(0);
+    let used = plist_u64(volume_dict, &["CapacityInUse", "CapacityInUseBytes", "CapacityUsed"]).unwrap_or(0);
+    let mount_point = plist_string(volume_dict, &["MountPoint"]);
+
+    json!({
+        "identifier": identifier,
+        "name": name,
+        "roles": roles,
+        "volumeGroupUuid": volume_group_uuid,
+        "volumeGroupRole": volume_group_role,
+        "volumeGroupName": volume_group_name,
+        "sealed": sealed,
+        "encrypted": encrypted,
+        "locked": locked,
+        "reserve": reserve,
+        "quota": quota,
+        "size": size,
+        "used": used,
+        "mountPoint": mount_point,
+    })
+}
+
+// "apfs list" enthaelt die Gruppenzuordnung nicht immer; dann aus diskutil info (APFSVolumeGroupID).
+fn lookup_volume_groups(volumes: &mut [Value]) {
+    for volume in volumes.iter_mut() {
+        if !volume["volumeGroupUuid"].is_null() {
+            continue;
+        }
+        let identifier = volume["identifier"].as_str().unwrap_or("").to_string();
+        if identifier.is_empty() {
+            continue;
+        }
+        if let Ok(info) = disk_info_dict(&normalize_device(&identifier)) {
+            if let Some(group) = info.get("APFSVolumeGroupID").and_then(|v| v.as_string()) {
+                volume["volumeGroupUuid"] = json!(group);
+            }
+        }
+    }
+}
+
+// Rolle in der Gruppe aus den Volume-Rollen, Gruppenname = Name des System-Volumes der Gruppe.
+fn derive_volume_group_fields(volumes: &mut [Value]) {
+    let has_role = |volume: &Value, role: &str| {
+        volume["roles"]
+            .as_array()
+            .map(|roles| roles.iter().any(|r| r.as_str() == Some(role)))
+            .unwrap_or(false)
+    };
+    let group_names: std::collections::HashMap<String, String> = volumes
+        .iter()
+        .filter(|volume| has_role(volume, "System"))
+        .filter_map(|volume| {
+            let group = volume["volumeGroupUuid"].as_str()?;
+            let name = volume["name"].as_str()?;
+            Some((group.to_string(), name.to_string()))
+        })
+        .collect();
+
+    for volume in volumes.iter_mut() {
+        let Some(group) = volume["volumeGroupUuid"].as_str().map(|g| g.to_string()) else {
+            continue;
+        };
+        if volume["volumeGroupRole"].is_null() {
+            let role = if has_role(volume, "System") {
+                Some("System")
+            } else if has_role(volume, "Data") {
+                Some("Data")
+            } else {
+                None
+            };
+            volume["volumeGroupRole"] = json!(role);
+        }
+        if volume["volumeGroupName"].is_null() {
+            volume["volumeGroupName"] = json!(group_names.get(&group));
+        }
+    }
+}
+
+// groupWith: bestehendes Volume, mit dem das neue eine Volume-Gruppe bildet
+// (z.B. Data-Volume "-role D" zu einem System-Volume).
 fn handle_apfs_add_volume(payload: &Value) -> Result<Option<Value>, String> {
-    let container_identifier = read_string(payload, "containerIdentifier")?;
+    let container_identifier = read_device_identifier(payload, "containerIdentifier")?;
     let name = read_string(payload, "name")?;
     let role = payload
         .get("role")
         .and_then(|v| v.as_str())
         .map(|s| s.to_string())
         .unwrap_or_default();
+    let group_with = payload
+        .get("groupWith")
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| validate_device_identifier(s, "groupWith").map(|_| normalize_device(s)))
+        .transpose()?;
 
     let container = normalize_device(&container_identifier);
-    if role.trim().is_empty() || role == "None" {
-        run_diskutil(["apfs", "addVolume", &container, "APFS", &name])?;
-    } else {
-        run_diskutil(["apfs", "addVolume", &container, "APFS", &name, "-role", &role])?;
-    }
-
-    Ok(Some(json!({ "container": container, "name": name, "role": role })))
+    let mut args = vec![
+        "apfs".to_string(),
+        "addVolume".to_string(),
+        container.clone(),
+        "APFS".to_string(),
+        name.clone(),
+    ];
+    if !(role.trim().is_empty() || role == "None") {
+        args.push("-role".to_string());
+        args.push(role.clone());
+    }
+    if let Some(group_with) = &group_with {
+        args.push("-groupWith".to_string());
+        args.push(group_with.clone());
+    }
+    run_diskutil(args)?;
+
+    Ok(Some(json!({ "container": container, "name": name, "role": role, "groupWith": group_with })))
 }
 
 fn handle_apfs_delete_volume(payload: &Value) -> Result<Option<Value>, String> {
-    let volume_identifier = read_string(payload, "volumeIdentifier")?;
+    let volume_identifier = read_device_identifier(payload, "volumeIdentifier")?;
     let volume = normalize_device(&volume_identifier);
+    require_confirmation(&volume, payload)?;
     run_diskutil(["apfs", "deleteVolume", &volume])?;
     Ok(Some(json!({ "volume": volume })))
 }
 
+// reserve/quota in Bytes; 0 entfernt den jeweiligen Wert, fehlende Felder bleiben unveraendert.
+fn handle_apfs_set_quota(payload: &Value) -> Result<Option<Value>, String> {
+    let volume_identifier = read_device_identifier(payload, "volumeIdentifier")?;
+    let volume = normalize_device(&volume_identifier);
+    let reserve = payload.get("reserve").and_then(|v| v.as_u64());
+    let quota = payload.get("quota").and_then(|v| v.as_u64());
+    if reserve.is_none() && quota.is_none() {
+        return Err("Nothing to change: reserve and quota are both missing".to_string());
+    }
+    if let (Some(reserve), Some(quota)) = (reserve, quota) {
+        if quota > 0 && reserve > quota {
+            return Err("Reserve must not exceed the quota".to_string());
+        }
+    }
+
+    if let Some(reserve) = reserve {
+        let size = if reserve == 0 { "0".to_string() } else { format!("{reserve}B") };
+        run_diskutil(["apfs", "setVolumeReserve", &volume, &size])?;
+    }
+    if let Some(quota) = quota {
+        let size = if quota == 0 { "0".to_string() } else { format!("{quota}B") };
+        run_diskutil(["apfs", "setVolumeQuota", &volume, &size])?;
+    }
+
+    Ok(Some(json!({ "volume": volume, "reserve": reserve, "quota": quota })))
+}
+
+// diskutil liefert keine Snapshot-Groesse; "created" wird aus dem Namen gelesen
+// (z.B. com.apple.TimeMachine.2024-05-01-101500.local).
+fn handle_apfs_list_snapshots(payload: &Value) -> Result<Option<Value>, String> {
+    let volume_identifier = read_device_identifier(payload, "volumeIdentifier")?;
+    let volume = normalize_device(&volume_identifier);
+
+    let output = Command::new("diskutil")
+        .args(["apfs", "listSnapshots", &volume, "-plist"])
+        .output()
+        .map_err(|e| format!("diskutil failed: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("diskutil error: {stderr}"));
+    }
+
+    let plist = PlistValue::from_reader_xml(&output.stdout[..]).map_err(|e| e.to_string())?;
+    let dict = plist
+        .as_dictionary()
+        .ok_or_else(|| "Invalid plist".to_string())?;
+    let entries = dict
+        .get("Snapshots")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    let created_pattern = Regex::new(r"(\d{4}-\d{2}-\d{2})-(\d{2})(\d{2})(\d{2})").map_err(|e| e.to_string())?;
+    let mut snapshots = Vec::new();
+    for entry in entries {
+        let snap = match entry.as_dictionary() {
+            Some(d) => d,
+            None => continue,
+        };
+        let name = snap
+            .get("SnapshotName")
+            .and_then(|v| v.as_string())
+            .unwrap_or("")
+            .to_string();
+        let created = created_pattern
+            .captures(&name)
+            .map(|caps| format!("{} {}:{}:{}", &caps[1], &caps[2], &caps[3], &caps[4]));
+        snapshots.push(json!({
+            "name": name,
+            "uuid": snap.get("SnapshotUUID").and_then(|v| v.as_string()),
+            "xid": snap.get("SnapshotXID").and_then(|v| v.as_unsigned_integer()),
+            "created": created,
+            "size": Value::Null,
+            "purgeable": snap.get("Purgeable").and_then(|v| v.as_boolean()),
+        }));
+    }
+
+    Ok(Some(json!({ "volume": volume, "snapshots": snapshots })))
+}
+
+fn handle_apfs_delete_snapshot(payload: &Value) -> Result<Option<Value>, String> {
+    let volume_identifier = read_device_identifier(payload, "volumeIdentifier")?;
+    let snapshot_name = read_string(payload, "snapshotName")?;
+    let volume = normalize_device(&volume_identifier);
+    if snapshot_name.trim().is_empty() {
+        return Err("Snapshot name must not be empty".to_string());
+    }
+    require_confirmation(&volume, payload)?;
+    run_diskutil(["apfs", "deleteSnapshot", &volume, "-name", &snapshot_name])?;
+    Ok(Some(json!({ "volume": volume, "snapshot": snapshot_name })))
+}
+
+// encryptVolume startet die Konvertierung im Hintergrund und kehrt sofort zurueck;
+// den Fortschritt lesen wir danach aus "diskutil apfs list". Die Passphrase kommt per stdin
+// und landet weder im Journal noch im Log.
+fn handle_encrypt_volume(payload: &Value) -> Result<Option<Value>, String> {
+    let volume_identifier = read_device_identifier(payload, "volumeIdentifier")?;
+    let passphrase = read_string(payload, "passphrase")?;
+    let volume = normalize_device(&volume_identifier);
+    if passphrase.is_empty() {
+        return Err("Passphrase must not be empty".to_string());
+    }
+
+    begin_journal("encrypt", &volume, &json!({ "volumeIdentifier": volume_identifier }))?;
+    emit_progress("encrypt", 0, 100, Some("Start encryption"));
+
+    let mut child = Command::new("diskutil")
+        .args(["apfs", "encryptVolume", &volume, "-user", "disk", "-stdinpassphrase"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("diskutil failed: {e}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(passphrase.as_bytes())
+            .and_then(|_| stdin.write_all(b"\n"))
+            .map_err(|e| format!("diskutil stdin failed: {e}"))?;
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("diskutil failed: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("diskutil error: {stderr}"));
+    }
+
+    let mut last_percent = 0u64;
+    loop {
+        ensure_not_cancelled()?;
+        match apfs_encryption_progress(&volume)? {
+            Some(percent) => {
+                if percent != last_percent {
+                    annotate_journal("percent", json!(percent))?;
+                    last_percent = percent;
+                }
+                emit_progress("encrypt", percent, 100, Some("Encrypting"));
+            }
+            None => break,
+        }
+        std::thread::sleep(std::time::Duration::from_secs(2));
+    }
+
+    emit_progress("encrypt", 100, 100, Some("Encryption complete"));
+    clear_journal();
+    Ok(Some(json!({ "volume": volume, "encrypted": true })))
+}
+
+// Prozent der laufenden Verschluesselung oder None, wenn keine Konvertierung (mehr) laeuft.
+// Der Volume-Block in "diskutil apfs list" endet am naechsten "+->"-Eintrag.
+fn apfs_encryption_progress(volume: &str) -> Result<Option<u64>, String> {
+    let output = Command::new("diskutil")
+        .args(["apfs", "list"])
+        .output()
+        .map_err(|e| format!("diskutil failed: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("diskutil error: {stderr}"));
+    }
+    let text = String::from_utf8_lossy(&output.stdout);
+    let disk = strip_device_prefix(volume);
+    let disk_pattern = Regex::new(&format!(r"Disk \(Role\):\s+{}\s", regex::escape(&disk)))
+        .map_err(|e| e.to_string())?;
+    let Some(start) = disk_pattern.find(&text).map(|m| m.start()) else {
+        return Err(format!("Volume {disk} not found in diskutil apfs list"));
+    };
+    let block = &text[start..];
+    let block = match block.find("+->") {
+        Some(end) => &block[..end],
+        None => block,
+    };
+
+    let progress = Regex::new(r"Encryption Progress:\s+(\d+)(?:\.\d+)?%").map_err(|e| e.to_string())?;
+    Ok(progress
+        .captures(block)
+        .and_then(|caps| caps.get(1))
+        .and_then(|m| m.as_str().parse::<u64>().ok())
+        .map(|percent| percent.min(100)))
+}
+
 fn handle_flash_image(payload: &Value) -> Result<Option<Value>, String> {
     let source_path = read_string(payload, "sourcePath")?;
-    let target_device = read_string(payload, "targetDevice")?;
+    let target_device = read_device_identifier(payload, "targetDevice")?;
     let verify = payload
         .get("verify")
         .and_then(|v| v.as_bool())
         .unwrap_or(true);
+    let write_checksum = read_write_checksum(payload);
 
     let device = normalize_device(&target_device);
+    require_confirmation(&device, payload)?;
     let raw_device = raw_device_path(&device);
 
-    let file_size = std::fs::metadata(&source_path)
-        .map_err(|e| format!("Image read failed: {e}"))?
-        .len();
+    let compression = detect_image_compression(&source_path)?;
+    let image_size = image_uncompressed_size(&source_path, compression)?;
 
     let disk_size = read_disk_size(&device).unwrap_or(0);
-    if disk_size > 0 && file_size > disk_size {
-        return Err("Image is larger than target device".to_string());
+    if let Some(size) = image_size {
+        if disk_size > 0 && size > disk_size {
+            return Err("Image is larger than target device".to_string());
+        }
     }
 
+    begin_journal("flash", &device, payload)?;
     emit_log("flash", "Unmounting target disk");
     force_unmount_disk(&device)?;
 
+    if compression != ImageCompression::None {
+        emit_log(
+            "flash",
+            &format!("Komprimiertes Image erkannt ({}), entpacke waehrend des Schreibens", compression.as_str()),
+        );
+    }
     emit_log("flash", "Writing image");
-    let source_hash = flash_write_with_hash(&source_path, &raw_device, file_size)?;
+    // Schreiben und Verifizieren bilden zusammen einen monotonen Gesamtbalken (0-50 / 50-100).
+    set_progress_span(0, if verify { 50 } else { 100 });
+    let (source_hash, file_size) = flash_write_with_hash(&source_path, &raw_device, compression, image_size)?;
 
     let mut verified_hash: Option<String> = None;
     if verify {
         emit_log("flash", "Verifying image");
+        set_progress_span(50, 50);
         let hash = flash_verify_with_hash(&raw_device, file_size)?;
         if hash != source_hash {
-            return Err("Verification failed: checksum mismatch".to_string());
+            return Err(coded("VERIFY_MISMATCH", "Verification failed: checksum mismatch"));
         }
         verified_hash = Some(hash);
     }
 
     sync_kernel_table(&device);
+    clear_journal();
 
-    Ok(Some(json!({
+    let mut result = json!({
         "target": device,
         "bytes": file_size,
         "sourceHash": source_hash,
         "verifiedHash": verified_hash,
         "verified": verify,
-    })))
+        "compression": compression.as_str(),
+    });
+    if write_checksum {
+        // Der Hash gilt fuer die geschriebenen (entpackten) Daten; als sha256sum-Zeile
+        // passt er nur zur Quelldatei, wenn diese unkomprimiert ist.
+        result["sha256"] = json!(source_hash);
+        if compression == ImageCompression::None {
+            result["checksumLine"] = json!(sha256sum_line(&source_hash, &source_path));
+        }
+    }
+    Ok(Some(result))
 }
 
 fn handle_inspect_image(payload: &Value) -> Result<Option<Value>, String> {
     let source_path = read_string(payload, "sourcePath")?;
-    let (is_windows, reason) = detect_windows_iso(&source_path)?;
-    let (brand, label) = detect_image_brand(&source_path, is_windows)?;
+    let compression = detect_image_compression(&source_path)?;
+    let uncompressed_size = image_uncompressed_size(&source_path, compression)?;
+    // Komprimierte Images enthalten kein lesbares ISO/Dateisystem am Dateianfang.
+    let (is_windows, reason, brand, label) = if compression == ImageCompression::None {
+        let (is_windows, reason) = detect_windows_iso(&source_path)?;
+        let (brand, label) = detect_image_brand(&source_path, is_windows)?;
+        (is_windows, reason, brand, label)
+    } else {
+        (false, Some("Compressed image".to_string()), None, None)
+    };
+    let layout = inspect_image_layout(&source_path, compression)?;
     Ok(Some(json!({
         "isWindows": is_windows,
         "reason": reason,
         "brand": brand,
         "label": label,
+        "compression": compression.as_str(),
+        "uncompressedSize": uncompressed_size,
+        "imageSize": uncompressed_size,
+        "scheme": layout.scheme,
+        "sectorSize": layout.sector_size,
+        "filesystem": layout.filesystem,
+        "partitions": layout.partitions,
     })))
 }
 
@@ -540,20 +1727,48 @@ fn handle_hash_image(payload: &Value) -> Result<Option<Value>, String> {
         .map_err(|e| format!("Image read failed: {e}"))?
         .len();
 
-    let hash = hash_file_with_progress(&source_path, file_size)?;
+    let mut with_md5 = false;
+    let mut with_sha1 = false;
+    if let Some(algorithms) = payload.get("algorithms").and_then(|v| v.as_array()) {
+        for algorithm in algorithms.iter().filter_map(|v| v.as_str()) {
+            match algorithm.to_lowercase().as_str() {
+                "sha256" => {}
+                "md5" => with_md5 = true,
+                "sha1" => with_sha1 = true,
+                other => return Err(format!("Unsupported hash algorithm: {other}")),
+            }
+        }
+    }
+
+    let digests = hash_file_digests(&source_path, file_size, with_md5, with_sha1)?;
     Ok(Some(json!({
         "bytes": file_size,
-        "sha256": hash,
+        "sha256": digests.sha256,
+        "md5": digests.md5,
+        "sha1": digests.sha1,
     })))
 }
 
 fn handle_backup_image(payload: &Value) -> Result<Option<Value>, String> {
-    let source_device = read_string(payload, "sourceDevice")?;
+    let source_device = read_device_identifier(payload, "sourceDevice")?;
     let target_path = read_string(payload, "targetPath")?;
     let compress = payload
         .get("compress")
         .and_then(|v| v.as_bool())
         .unwrap_or(false);
+    let force = payload
+        .get("force")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let write_checksum = read_write_checksum(payload);
+    let compression = match payload.get("compression").and_then(|v| v.as_str()) {
+        Some("gzip") => ImageCompression::Gzip,
+        Some("zstd") => ImageCompression::Zstd,
+        Some("none") => ImageCompression::None,
+        Some(other) => return Err(format!("Unsupported backup compression: {other}")),
+        None if compress => ImageCompression::Gzip,
+        None => ImageCompression::None,
+    };
 
     let device = normalize_device(&source_device);
     let raw_device = raw_device_path(&device);
@@ -562,36 +1777,83 @@ fn handle_backup_image(payload: &Value) -> Result<Option<Value>, String> {
         return Err("Unable to determine device size".to_string());
     }
 
+    if !force && (is_boot_volume(&device) || disk_hosts_system_volume(&device)) {
+        return Err(coded("PROTECTED_VOLUME", "Refusing to back up the mounted system volume without force"));
+    }
+
+    begin_journal("backup", &device, payload)?;
     emit_log("backup", "Unmounting source disk");
     force_unmount_disk(&device)?;
 
     emit_log("backup", "Reading image");
-    let (bytes_written, source_hash) = backup_read_to_file(&raw_device, &target_path, disk_size, compress)?;
+    let (bytes_written, source_hash) = backup_read_to_file(&raw_device, &target_path, disk_size, compression)?;
+    let _ = annotate_journal("sourceHash", json!(source_hash));
 
     emit_log("backup", "Verifying backup");
-    let target_hash = if compress {
-        hash_gzip_file_with_progress(&target_path, disk_size)?
-    } else {
+    let target_hash = if compression == ImageCompression::None {
         hash_file_with_progress(&target_path, disk_size)?
+    } else {
+        hash_compressed_file_with_progress(&target_path, compression, disk_size)?
     };
 
     if source_hash != target_hash {
-        return Err("Backup verification failed: checksum mismatch".to_string());
+        return Err(coded("VERIFY_MISMATCH", "Backup verification failed: checksum mismatch"));
+    }
+    clear_journal();
+
+    let mut checksum_path: Option<String> = None;
+    if write_checksum {
+        // sha256sum prueft die Datei selbst; bei Kompression ist das nicht der Device-Hash.
+        let file_hash = if compression == ImageCompression::None {
+            source_hash.clone()
+        } else {
+            let file_size = std::fs::metadata(&target_path)
+                .map_err(|e| format!("Image read failed: {e}"))?
+                .len();
+            hash_file_with_progress(&target_path, file_size)?
+        };
+        checksum_path = Some(write_checksum_file(&target_path, &file_hash)?);
     }
 
     Ok(Some(json!({
         "source": device,
         "target": target_path,
         "bytes": bytes_written,
-        "compressed": compress,
+        "compressed": compression != ImageCompression::None,
+        "compression": compression.as_str(),
         "verified": true,
         "sha256": source_hash,
+        "checksumPath": checksum_path,
     })))
 }
 
+fn read_write_checksum(payload: &Value) -> bool {
+    payload
+        .get("writeChecksum")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+// Format wie `sha256sum`: "<hash>  <dateiname>"
+fn sha256sum_line(hash: &str, path: &str) -> String {
+    let file_name = std::path::Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string());
+    format!("{hash}  {file_name}\n")
+}
+
+// Legt <image>.sha256 neben dem Image ab und liefert den Pfad.
+fn write_checksum_file(image_path: &str, hash: &str) -> Result<String, String> {
+    let checksum_path = format!("{image_path}.sha256");
+    std::fs::write(&checksum_path, sha256sum_line(hash, image_path))
+        .map_err(|e| format!("Checksum file write failed: {e}"))?;
+    Ok(checksum_path)
+}
+
 fn handle_windows_install(payload: &Value) -> Result<Option<Value>, String> {
     let source_path = read_string(payload, "sourcePath")?;
-    let target_device = read_string(payload, "targetDevice")?;
+    let target_device = read_device_identifier(payload, "targetDevice")?;
     let label = payload
         .get("label")
         .and_then(|v| v.as_str())
@@ -611,19 +1873,18 @@ fn handle_windows_install(payload: &Value) -> Result<Option<Value>, String> {
         .unwrap_or(false);
 
     let device = normalize_device(&target_device);
+    require_confirmation(&device, payload)?;
     let mount_point = "/tmp/oxidisk_win_iso";
     let mut iso_mounted = false;
+    // FAT32-Labels: max. 11 Zeichen, Grossbuchstaben.
+    let label: String = label.to_uppercase().chars().take(11).collect();
 
-    let result = (|| -> Result<Option<Value>, String> {
-        emit_log("win", "Erasing target disk (GPT + ExFAT)");
-        run_diskutil(["eraseDisk", "ExFAT", &label, "GPT", &device])?;
-
-        let volume_id = find_partition_by_label(&label)?
-            .ok_or_else(|| "Windows target volume not found".to_string())?;
-        let volume_device = normalize_device(&volume_id);
-        let volume_mount = read_mount_point(&volume_device)?
-            .ok_or_else(|| "Target volume not mounted".to_string())?;
+    let (is_windows, _) = detect_windows_iso(&source_path)?;
+    if !is_windows {
+        return Err("Source is not a Windows installer image".to_string());
+    }
 
+    let result = (|| -> Result<Option<Value>, String> {
         emit_log("win", "Mounting ISO");
         mount_iso_at(&source_path, mount_point)?;
         iso_mounted = true;
@@ -633,8 +1894,47 @@ fn handle_windows_install(payload: &Value) -> Result<Option<Value>, String> {
             return Err("ISO appears empty".to_string());
         }
 
+        // FAT32 erlaubt max. 4 GiB - 1 pro Datei; groessere install.wim wird mit wimlib gesplittet.
+        let wim_source = std::path::Path::new(mount_point).join("sources").join("install.wim");
+        let wim_size = std::fs::metadata(&wim_source).map(|m| m.len()).unwrap_or(0);
+        let split_wim = wim_size > FAT32_MAX_FILE_SIZE;
+        let wimlib = if split_wim {
+            Some(find_sidecar("wimlib-imagex").map_err(|_| {
+                "install.wim exceeds 4 GiB and wimlib-imagex is not available to split it".to_string()
+            })?)
+        } else {
+            None
+        };
+
+        emit_log("win", "Erasing target disk (GPT + FAT32)");
+        run_diskutil(["eraseDisk", "MS-DOS FAT32", &label, "GPT", &device])?;
+
+        let volume_id = find_partition_by_label(&label)?
+            .ok_or_else(|| "Windows target volume not found".to_string())?;
+        let volume_device = normalize_device(&volume_id);
+        let volume_mount = read_mount_point(&volume_device)?
+            .ok_or_else(|| "Target volume not mounted".to_string())?;
+
         emit_log("win", "Copying files");
-        copy_dir_with_progress(mount_point, &volume_mount, total_bytes)?;
+        let skip: &[&str] = if split_wim { &["sources/install.wim"] } else { &[] };
+        let copy_bytes = if split_wim { total_bytes - wim_size } else { total_bytes };
+        copy_dir_with_progress(mount_point, &volume_mount, copy_bytes, skip)?;
+
+        if let Some(wimlib) = wimlib {
+            emit_log("win", "Splitting install.wim for FAT32");
+            let target = std::path::Path::new(&volume_mount).join("sources").join("install.swm");
+            let output = Command::new(&wimlib)
+                .arg("split")
+                .arg(&wim_source)
+                .arg(&target)
+                .arg("3800")
+                .output()
+                .map_err(|e| format!("wimlib-imagex failed: {e}"))?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                return Err(format!("wimlib-imagex split failed: {}", stderr.trim()));
+            }
+        }
 
         if tpm_bypass || local_account || privacy_defaults {
             emit_log("win", "Writing autounattend.xml");
@@ -648,6 +1948,8 @@ fn handle_windows_install(payload: &Value) -> Result<Option<Value>, String> {
             "source": source_path,
             "target": device,
             "mountPoint": volume_mount,
+            "filesystem": "fat32",
+            "splitWim": split_wim,
         })))
     })();
 
@@ -669,6 +1971,7 @@ fn handle_preflight_check(payload: &Value) -> Result<Option<Value>, String> {
         .and_then(|value| value.as_str())
         .or_else(|| payload.get("deviceIdentifier").and_then(|value| value.as_str()))
         .ok_or_else(|| "Missing device identifier".to_string())?;
+    validate_device_identifier(device_identifier, "deviceIdentifier")?;
     let format_type = payload
         .get("formatType")
         .and_then(|value| value.as_str())
@@ -677,6 +1980,10 @@ fn handle_preflight_check(payload: &Value) -> Result<Option<Value>, String> {
         .get("newSize")
         .and_then(|value| value.as_str())
         .map(|value| value.to_string());
+    let source_path = payload
+        .get("sourcePath")
+        .and_then(|value| value.as_str())
+        .map(|value| value.to_string());
 
     let device = normalize_device(device_identifier);
     let fs_type = match &format_type {
@@ -704,6 +2011,12 @@ fn handle_preflight_check(payload: &Value) -> Result<Option<Value>, String> {
             blockers.push(format!("Sidecar fehlt: {}", sidecar.name));
         }
     }
+    if operation == "resize" && !online_resize_available(&fs_type) {
+        blockers.push(format!("{fs_type} kann hier nicht eingehaengt und daher nicht in der Groesse geaendert werden."));
+    }
+    if fs_type == "ntfs" && matches!(operation.as_str(), "create" | "format") && find_sidecar("ntfs-3g").is_err() {
+        warnings.push("ntfs-3g fehlt: das neue NTFS-Volume wird nur lesend eingehaengt.".to_string());
+    }
 
     let mut busy_processes: Vec<Value> = Vec::new();
     if let Ok(Some(mount_point)) = read_mount_point(&device) {
@@ -749,6 +2062,86 @@ fn handle_preflight_check(payload: &Value) -> Result<Option<Value>, String> {
         warnings.push("Achtung: Partition gehoert zu einer macOS-Installation.".to_string());
     }
 
+    // 512e: Sektor-Grenze ist nicht automatisch eine physische 4K-Grenze (Read-Modify-Write)
+    if let Ok(info) = disk_info_dict(&device) {
+        let (block_size, physical_block_size) = device_block_sizes(&info);
+        if let Some(offset) = info.get("PartitionOffset").and_then(|v| v.as_unsigned_integer()) {
+            if physical_block_size > block_size
+                && offset % block_size == 0
+                && offset % physical_block_size != 0
+            {
+                warnings.push(format!(
+                    "Partition ist nicht an {physical_block_size}-Byte-Bloecken ausgerichtet (512e-Laufwerk). Leistungseinbussen moeglich."
+                ));
+            }
+        }
+    }
+
+    // Nur mit vorhandenem smartctl; Geraete ohne SMART-Werte liefern einfach keine Warnung
+    let smart_missing: Vec<String> = required_sidecars("smart", "")
+        .into_iter()
+        .filter(|sidecar| !sidecar.found)
+        .map(|sidecar| sidecar.name)
+        .collect();
+    let drive_state = if smart_missing.is_empty() {
+        read_smart_report(&preflight_whole_disk(&device)).ok()
+    } else {
+        warnings.push(format!("{} fehlt: Temperatur und Zustand des Laufwerks werden nicht geprueft.", smart_missing.join(", ")));
+        None
+    };
+    let temperature = drive_state
+        .as_ref()
+        .and_then(|report| report.get("temperatureCelsius"))
+        .and_then(|v| v.as_i64());
+    let power_mode = drive_state
+        .as_ref()
+        .and_then(|report| report.get("powerMode"))
+        .and_then(|v| v.as_str())
+        .map(|mode| mode.to_string());
+    let is_ssd = drive_state
+        .as_ref()
+        .and_then(|report| report.get("isSsd"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let temperature_limit = if is_ssd { SSD_TEMPERATURE_WARN_CELSIUS } else { HDD_TEMPERATURE_WARN_CELSIUS };
+    if let Some(celsius) = temperature.filter(|celsius| *celsius >= temperature_limit) {
+        warnings.push(format!("Laufwerk ist sehr warm ({celsius} Grad C). Erst abkuehlen lassen."));
+    }
+    if power_mode.as_deref() == Some("standby") {
+        warnings.push("Laufwerk ist im Standby und muss vor der Operation erst anlaufen.".to_string());
+    }
+
+    // FAT32 kann keine Datei >= 4 GiB aufnehmen; windows_install formatiert das Ziel immer FAT32.
+    let target_fs = if operation == "windows_install" { "fat32" } else { fs_type.as_str() };
+    let mut max_file_size_exceeded = false;
+    let checks_file_size = matches!(operation.as_str(), "create" | "format" | "copy" | "windows_install");
+    if let (true, Some(limit), Some(source)) = (checks_file_size, filesystem_max_file_size(target_fs), &source_path) {
+        let is_windows_iso = source.to_lowercase().ends_with(".iso")
+            && detect_windows_iso(source).map(|(is_windows, _)| is_windows).unwrap_or(false);
+        if operation == "windows_install" || is_windows_iso {
+            if let Some(wim_size) = windows_iso_wim_size(source) {
+                if wim_size > limit {
+                    max_file_size_exceeded = true;
+                    warnings.push(
+                        "install.wim ist groesser als 4 GiB und wird beim Schreiben in install.swm-Teile gesplittet."
+                            .to_string(),
+                    );
+                    if find_sidecar("wimlib-imagex").is_err() {
+                        blockers.push("Sidecar fehlt: wimlib-imagex (zum Splitten von install.wim)".to_string());
+                    }
+                }
+            }
+        } else if let Some((name, size)) = largest_file(std::path::Path::new(source)) {
+            if size > limit {
+                max_file_size_exceeded = true;
+                warnings.push(format!("Datei zu gross fuer {target_fs}: {name}"));
+            }
+        }
+    }
+
+    let (bus_protocol, throughput) = assumed_throughput(&device);
+    let estimated_seconds = estimate_operation_seconds(&operation, &device, source_path.as_deref(), throughput);
+
     let ok = blockers.is_empty();
     Ok(Some(json!({
         "ok": ok,
@@ -758,6 +2151,11 @@ fn handle_preflight_check(payload: &Value) -> Result<Option<Value>, String> {
         "blockers": blockers,
         "warnings": warnings,
         "busyProcesses": busy_processes,
+        "estimatedSeconds": estimated_seconds,
+        "maxFileSizeExceeded": max_file_size_exceeded,
+        "busProtocol": bus_protocol,
+        "temperatureCelsius": temperature,
+        "powerMode": power_mode,
         "battery": battery.map(|info| json!({
             "isLaptop": info.is_laptop,
             "onAc": info.on_ac,
@@ -775,12 +2173,25 @@ fn handle_preflight_check(payload: &Value) -> Result<Option<Value>, String> {
     })))
 }
 
+// Ab diesen Temperaturen warnt der Preflight (typische Herstellergrenzen: HDD 60 Grad C, SSD 70 Grad C)
+const HDD_TEMPERATURE_WARN_CELSIUS: i64 = 60;
+const SSD_TEMPERATURE_WARN_CELSIUS: i64 = 70;
+
+// SMART gilt fuer das ganze Laufwerk; fuer Partitionen daher die ParentWholeDisk abfragen
+fn preflight_whole_disk(device: &str) -> String {
+    disk_info_dict(device)
+        .ok()
+        .and_then(|info| info.get("ParentWholeDisk").and_then(|v| v.as_string()).map(normalize_device))
+        .unwrap_or_else(|| device.to_string())
+}
+
 fn handle_force_unmount(payload: &Value) -> Result<Option<Value>, String> {
     let device_identifier = payload
         .get("partitionIdentifier")
         .and_then(|value| value.as_str())
         .or_else(|| payload.get("deviceIdentifier").and_then(|value| value.as_str()))
         .ok_or_else(|| "Missing device identifier".to_string())?;
+    validate_device_identifier(device_identifier, "deviceIdentifier")?;
     let device = normalize_device(device_identifier);
 
     let mut killed: Vec<Value> = Vec::new();
@@ -804,9 +2215,264 @@ fn handle_force_unmount(payload: &Value) -> Result<Option<Value>, String> {
         }
     }
 
-    force_unmount_disk(&device)?;
+    let unmounted = force_unmount_disk(&device)?;
+
+    Ok(Some(json!({ "device": device, "killed": killed, "unmountedVolumes": unmounted })))
+}
+
+// Anders als force_unmount wird hier niemand beendet: belegte Volumes werden samt der
+// blockierenden Prozesse gemeldet und das Geraet nur ausgeworfen, wenn alles ausgehaengt ist.
+fn handle_safe_eject(payload: &Value) -> Result<Option<Value>, String> {
+    let device_identifier = read_device_identifier(payload, "deviceIdentifier")?;
+    let disk = base_disk_identifier(&device_identifier);
+    let device = normalize_device(&disk);
+
+    let plist = diskutil_plist(["list", "-plist"])?;
+    let volumes = parse_mounted_volumes(&plist, &disk);
+
+    let mut unmounted: Vec<Value> = Vec::new();
+    let mut blockers: Vec<Value> = Vec::new();
+    for (identifier, mount_point) in volumes {
+        let processes = list_open_processes(&mount_point).unwrap_or_default();
+        let result = if processes.is_empty() {
+            run_diskutil(["unmount", &normalize_device(&identifier)])
+        } else {
+            Err("Volume is in use".to_string())
+        };
+        match result {
+            Ok(()) => unmounted.push(json!({ "identifier": identifier, "mountPoint": mount_point })),
+            Err(err) => blockers.push(json!({
+                "identifier": identifier,
+                "mountPoint": mount_point,
+                "error": err,
+                "processes": processes.iter().map(|proc_info| json!({
+                    "pid": proc_info.pid,
+                    "command": proc_info.command,
+                })).collect::<Vec<Value>>(),
+            })),
+        }
+    }
+
+    let ejected = if blockers.is_empty() {
+        run_diskutil(["eject", &device])?;
+        true
+    } else {
+        false
+    };
+
+    Ok(Some(json!({
+        "device": device,
+        "ejected": ejected,
+        "unmounted": unmounted,
+        "blockers": blockers,
+    })))
+}
+
+const BENCHMARK_READ_BYTES: u64 = 256 * 1024 * 1024;
+const BENCHMARK_WRITE_BYTES: u64 = 64 * 1024 * 1024;
+const BENCHMARK_MIN_WRITE_BYTES: u64 = 8 * 1024 * 1024;
+// MBR/GPT am Anfang und Backup-GPT am Ende der Disk bleiben unangetastet
+const BENCHMARK_RESERVED_BYTES: u64 = 1024 * 1024;
+
+// Sequentieller Lese- (und optional Schreib-)Test auf dem Raw-Device. Der Schreibtest
+// schreibt nur in nicht zugeordneten Bereich ausserhalb aller Partitionen, es gibt also
+// nichts zu sichern; ohne passende Luecke entfaellt er. Zusaetzlich nur fuer nicht
+// gemountete, nicht geschuetzte Laufwerke erlaubt.
+fn handle_benchmark_device(payload: &Value) -> Result<Option<Value>, String> {
+    let device_identifier = read_device_identifier(payload, "deviceIdentifier")?;
+    let read_only = payload.get("readOnly").and_then(|v| v.as_bool()).unwrap_or(true);
+    let disk = base_disk_identifier(&device_identifier);
+    let device = normalize_device(&disk);
+    let raw_device = raw_device_path(&device);
+
+    let mib = 1024 * 1024;
+    let disk_size = read_disk_size(&device).ok_or_else(|| format!("Disk size unknown for {device}"))?;
+    let read_bytes = BENCHMARK_READ_BYTES.min(disk_size / mib * mib);
+    if read_bytes == 0 {
+        return Err("Device is too small to benchmark".to_string());
+    }
+
+    if !read_only {
+        if is_boot_volume(&device) || disk_hosts_system_volume(&device) {
+            return Err(coded("PROTECTED_VOLUME", "Write benchmark is not allowed on system disks"));
+        }
+        let plist = diskutil_plist(["list", "-plist"])?;
+        let mounted = parse_mounted_volumes(&plist, &disk);
+        if !mounted.is_empty() {
+            let names: Vec<String> = mounted.into_iter().map(|(_, mount_point)| mount_point).collect();
+            return Err(format!("Write benchmark requires an unmounted device (mounted: {})", names.join(", ")));
+        }
+        require_confirmation(&device, payload)?;
+    }
+
+    emit_log("benchmark", "Sequentieller Lesetest");
+    let read_seconds = benchmark_read(&raw_device, read_bytes)?;
+
+    let mut write_bytes: Option<u64> = None;
+    let mut write_seconds: Option<f64> = None;
+    let mut write_skipped: Option<String> = None;
+    if !read_only {
+        match benchmark_write_region(disk_size, disk_partition_ranges(&device)?.as_deref()) {
+            Some((offset, bytes)) => {
+                emit_log("benchmark", "Sequentieller Schreibtest");
+                write_seconds = Some(benchmark_write(&raw_device, offset, bytes)?);
+                write_bytes = Some(bytes);
+            }
+            None => {
+                write_skipped = Some("No unallocated space for the write test".to_string());
+            }
+        }
+    }
+
+    let mb_per_second = |bytes: u64, seconds: f64| {
+        if seconds > 0.0 {
+            ((bytes as f64 / seconds / 1_000_000.0) * 10.0).round() / 10.0
+        } else {
+            0.0
+        }
+    };
+    Ok(Some(json!({
+        "device": device,
+        "readBytes": read_bytes,
+        "readMBps": mb_per_second(read_bytes, read_seconds),
+        "writeBytes": write_bytes,
+        "writeMBps": write_bytes.zip(write_seconds).map(|(bytes, seconds)| mb_per_second(bytes, seconds)),
+        "writeSkipped": write_skipped,
+    })))
+}
+
+// Belegte Bereiche (Offset, Groesse) aller Partitionen. Ohne MBR/GPT (leere Disk oder
+// Dateisystem direkt auf der Disk) oder bei unlesbarer Partition ist kein freier Bereich
+// sicher bestimmbar: None.
+fn disk_partition_ranges(device: &str) -> Result<Option<Vec<(u64, u64)>>, String> {
+    let info = disk_info_dict(device)?;
+    if !matches!(
+        info.get("Content").and_then(|v| v.as_string()),
+        Some("GUID_partition_scheme") | Some("FDisk_partition_scheme")
+    ) {
+        return Ok(None);
+    }
+    let mut ranges = Vec::new();
+    for part_id in list_disk_partitions(device)? {
+        let plist = diskutil_plist(["info", "-plist", &format!("/dev/{part_id}")])?;
+        let Ok(part) = parse_partition_info(&plist) else {
+            return Ok(None);
+        };
+        ranges.push((part.partition_offset, part.partition_size));
+    }
+    Ok(Some(ranges))
+}
+
+// Groesste MiB-ausgerichtete Luecke zwischen den Partitionen, auf BENCHMARK_WRITE_BYTES begrenzt.
+fn benchmark_write_region(disk_size: u64, partitions: Option<&[(u64, u64)]>) -> Option<(u64, u64)> {
+    let mib = 1024 * 1024;
+    let usable_end = disk_size.checked_sub(BENCHMARK_RESERVED_BYTES)?;
+    let mut ranges = partitions?.to_vec();
+    ranges.sort();
+
+    let mut best: Option<(u64, u64)> = None;
+    let mut cursor = BENCHMARK_RESERVED_BYTES;
+    let bounds = ranges
+        .iter()
+        .map(|(offset, size)| (*offset, offset.saturating_add(*size)))
+        .chain(std::iter::once((usable_end, usable_end)));
+    for (start, end) in bounds {
+        let gap_start = cursor.div_ceil(mib) * mib;
+        let gap_end = start.min(usable_end) / mib * mib;
+        if gap_end > gap_start && best.map(|(_, len)| gap_end - gap_start > len).unwrap_or(true) {
+            best = Some((gap_start, gap_end - gap_start));
+        }
+        cursor = cursor.max(end);
+    }
+
+    best.filter(|(_, len)| *len >= BENCHMARK_MIN_WRITE_BYTES)
+        .map(|(offset, len)| (offset, len.min(BENCHMARK_WRITE_BYTES)))
+}
+
+fn benchmark_read(raw_device: &str, total_bytes: u64) -> Result<f64, String> {
+    let mut source = open_device_for_read(raw_device)?;
+    let buffer_size = 4 * 1024 * 1024;
+    let mut buffer = vec![0u8; buffer_size];
+    let mut done: u64 = 0;
+    let started = std::time::Instant::now();
+    while done < total_bytes {
+        ensure_not_cancelled()?;
+        let chunk = std::cmp::min(buffer_size as u64, total_bytes - done) as usize;
+        source.read_exact(&mut buffer[..chunk]).map_err(|e| format!("Read failed: {e}"))?;
+        done += chunk as u64;
+        emit_progress_bytes("benchmark", done * 100 / total_bytes, 100, Some("Lesetest"), done, total_bytes);
+    }
+    Ok(started.elapsed().as_secs_f64())
+}
 
-    Ok(Some(json!({ "device": device, "killed": killed })))
+// Schreibt ein Testmuster (gemessen) in einen nicht zugeordneten Bereich, siehe benchmark_write_region.
+fn benchmark_write(raw_device: &str, offset: u64, total_bytes: u64) -> Result<f64, String> {
+    if is_dry_run() {
+        log_dry_run("benchmark-write", [raw_device, &offset.to_string(), &total_bytes.to_string()]);
+        return Ok(0.0);
+    }
+
+    let mut target = open_device_for_write(raw_device)?;
+    let pattern: Vec<u8> = (0..4 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+    let started = std::time::Instant::now();
+    target.seek(SeekFrom::Start(offset)).map_err(|e| format!("Seek failed: {e}"))?;
+    let mut done: u64 = 0;
+    while done < total_bytes {
+        ensure_not_cancelled()?;
+        let chunk = std::cmp::min(pattern.len() as u64, total_bytes - done) as usize;
+        target.write_all(&pattern[..chunk]).map_err(|e| format!("Write failed: {e}"))?;
+        done += chunk as u64;
+        emit_progress_bytes("benchmark", done * 100 / total_bytes, 100, Some("Schreibtest"), done, total_bytes);
+    }
+    target.sync_all().map_err(|e| format!("Sync failed: {e}"))?;
+    Ok(started.elapsed().as_secs_f64())
+}
+
+// Gemountete Volumes einer Disk aus "diskutil list -plist": eigene Partitionen und die
+// Volumes von APFS-Containern, deren Physical Store auf dieser Disk liegt.
+fn parse_mounted_volumes(plist: &PlistValue, disk: &str) -> Vec<(String, String)> {
+    let mut volumes = Vec::new();
+    let Some(entries) = plist
+        .as_dictionary()
+        .and_then(|dict| dict.get("AllDisksAndPartitions"))
+        .and_then(|v| v.as_array())
+    else {
+        return volumes;
+    };
+
+    for entry in entries.iter().filter_map(|entry| entry.as_dictionary()) {
+        let identifier = plist_string(entry, &["DeviceIdentifier"]).unwrap_or_default();
+        let on_disk = identifier == disk
+            || entry
+                .get("APFSPhysicalStores")
+                .and_then(|v| v.as_array())
+                .map(|stores| {
+                    stores.iter().filter_map(|store| store.as_dictionary()).any(|store| {
+                        plist_string(store, &["DeviceIdentifier"])
+                            .map(|store_id| base_disk_identifier(&store_id) == disk)
+                            .unwrap_or(false)
+                    })
+                })
+                .unwrap_or(false);
+        if !on_disk {
+            continue;
+        }
+
+        let mut dicts = vec![entry];
+        for key in ["Partitions", "APFSVolumes"] {
+            if let Some(items) = entry.get(key).and_then(|v| v.as_array()) {
+                dicts.extend(items.iter().filter_map(|item| item.as_dictionary()));
+            }
+        }
+        for dict in dicts {
+            if let (Some(id), Some(mount_point)) =
+                (plist_string(dict, &["DeviceIdentifier"]), plist_string(dict, &["MountPoint"]))
+            {
+                volumes.push((id, mount_point));
+            }
+        }
+    }
+    volumes
 }
 
 fn handle_get_journal() -> Result<Option<Value>, String> {
@@ -824,8 +2490,35 @@ fn handle_clear_journal() -> Result<Option<Value>, String> {
     Ok(Some(json!({ "cleared": true })))
 }
 
+fn handle_get_audit_log(payload: &Value) -> Result<Option<Value>, String> {
+    let limit = payload.get("limit").and_then(|v| v.as_u64()).map(|limit| limit as usize);
+    let path = audit_log_path();
+    if !path.exists() {
+        return Ok(Some(json!({ "entries": [] })));
+    }
+    let data = std::fs::read_to_string(&path).map_err(|e| format!("Audit log read failed: {e}"))?;
+    // Defekte Zeilen (z.B. abgebrochener Schreibvorgang) ueberspringen statt alles zu verwerfen
+    let mut entries: Vec<Value> = data
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    if let Some(limit) = limit {
+        let skip = entries.len().saturating_sub(limit);
+        entries.drain(..skip);
+    }
+    Ok(Some(json!({ "entries": entries })))
+}
+
+fn handle_clear_audit_log() -> Result<Option<Value>, String> {
+    let path = audit_log_path();
+    if path.exists() {
+        std::fs::remove_file(&path).map_err(|e| format!("Audit log delete failed: {e}"))?;
+    }
+    Ok(Some(json!({ "cleared": true })))
+}
+
 fn handle_check_partition(payload: &Value) -> Result<Option<Value>, String> {
-    let partition_identifier = read_string(payload, "partitionIdentifier")?;
+    let partition_identifier = read_device_identifier(payload, "partitionIdentifier")?;
     let repair = payload
         .get("repair")
         .and_then(|value| value.as_bool())
@@ -849,41 +2542,313 @@ fn handle_check_partition(payload: &Value) -> Result<Option<Value>, String> {
     Ok(Some(json!({ "device": device, "fs": fs_type, "output": output })))
 }
 
+fn handle_smart_info(payload: &Value) -> Result<Option<Value>, String> {
+    let device_identifier = read_device_identifier(payload, "deviceIdentifier")?;
+    let device = normalize_device(&device_identifier);
+    if let Some(missing) = required_sidecars("smart", "").into_iter().find(|sidecar| !sidecar.found) {
+        return Err(coded("SIDECAR_MISSING", format!("{} is required to read SMART data", missing.name)));
+    }
+    Ok(Some(read_smart_report(&device)?))
+}
+
+// Ergebnis von smartctl mit "-n standby": entweder Werte oder der Hinweis, dass die Platte
+// schlaeft und bewusst nicht geweckt wurde.
+enum SmartReading {
+    Standby,
+    Report(Value),
+}
+
+fn read_smart_report(device: &str) -> Result<Value, String> {
+    match run_smartctl(device)? {
+        SmartReading::Standby => {
+            let mut report = parse_smart_report(device, &json!({}));
+            report["powerMode"] = json!("standby");
+            report["isSsd"] = json!(false);
+            Ok(report)
+        }
+        SmartReading::Report(report) => Ok(parse_smart_report(device, &report)),
+    }
+}
+
+// "-n standby" liest keine Werte von einer Platte im Standby, statt sie dafuer anlaufen zu lassen.
+fn run_smartctl(device: &str) -> Result<SmartReading, String> {
+    let path = find_sidecar("smartctl")?;
+    let output = Command::new(&path)
+        .args(["-n", "standby", "-a", "-j", device])
+        .output()
+        .map_err(|e| format!("smartctl failed: {e}"))?;
+
+    let report: Option<Value> = serde_json::from_slice(&output.stdout).ok();
+    if report.as_ref().is_some_and(smart_reports_standby) {
+        return Ok(SmartReading::Standby);
+    }
+
+    // smartctl liefert eine Bitmaske als Exit-Code; nur Bit 0/1 heissen "nichts gelesen"
+    let status = output.status.code().unwrap_or(1);
+    if status & 0b11 != 0 {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let combined = format!("{stdout}\n{stderr}").trim().to_string();
+        return Err(format!("smartctl error: {combined}"));
+    }
+
+    report
+        .map(SmartReading::Report)
+        .ok_or_else(|| "smartctl parse failed: no JSON output".to_string())
+}
+
+// Im Standby bricht smartctl ab und meldet das nur als Text in smartctl.messages
+fn smart_reports_standby(report: &Value) -> bool {
+    report
+        .pointer("/smartctl/messages")
+        .and_then(|v| v.as_array())
+        .is_some_and(|messages| {
+            messages.iter().any(|message| {
+                message
+                    .get("string")
+                    .and_then(|v| v.as_str())
+                    .is_some_and(|text| text.to_ascii_uppercase().contains("STANDBY MODE"))
+            })
+        })
+}
+
+// macOS verwirft freie Bloecke von APFS/HFS+ beim Mounten, sofern TRIM aktiv ist.
+// Ein Unmount/Mount ist daher der sichere Weg; fuer andere Dateisysteme gibt es keinen.
+fn handle_trim_device(payload: &Value) -> Result<Option<Value>, String> {
+    let device_identifier = read_device_identifier(payload, "deviceIdentifier")?;
+    let device = normalize_device(&device_identifier);
+
+    let info = disk_info_dict(&device)?;
+    let solid_state = info.get("SolidState").and_then(|v| v.as_boolean()).unwrap_or(false);
+    let trim_supported = info.get("TRIMSupport").and_then(|v| v.as_boolean()).unwrap_or(false);
+    let whole_disk = info.get("WholeDisk").and_then(|v| v.as_boolean()).unwrap_or(false);
+
+    let skipped = |reason: &str| {
+        Ok(Some(json!({
+            "device": device,
+            "solidState": solid_state,
+            "trimSupported": trim_supported,
+            "performed": false,
+            "reason": reason,
+        })))
+    };
+
+    if !trim_supported {
+        return skipped("Device does not report TRIM support");
+    }
+
+    if whole_disk {
+        emit_log("trim", "Disk wird neu gemountet (TRIM beim Mount)");
+        run_diskutil(["unmountDisk", &device])?;
+        run_diskutil(["mountDisk", &device])?;
+    } else {
+        let fs_type = detect_fs_type(&device)?;
+        if !matches!(fs_type.as_str(), "apfs" | "hfs+") {
+            return skipped("macOS only trims APFS and HFS+ volumes");
+        }
+        emit_log("trim", "Volume wird neu gemountet (TRIM beim Mount)");
+        if read_mount_point(&device)?.is_some() {
+            run_diskutil(["unmount", &device])?;
+            run_diskutil(["mount", &device])?;
+        } else {
+            run_diskutil(["mount", &device])?;
+            run_diskutil(["unmount", &device])?;
+        }
+    }
+
+    Ok(Some(json!({
+        "device": device,
+        "solidState": solid_state,
+        "trimSupported": true,
+        "performed": true,
+        "method": "remount",
+    })))
+}
+
+fn parse_smart_report(device: &str, report: &Value) -> Value {
+    let health = report
+        .pointer("/smart_status/passed")
+        .and_then(|v| v.as_bool())
+        .map(|passed| if passed { "PASSED" } else { "FAILED" });
+    let attributes = report
+        .pointer("/ata_smart_attributes/table")
+        .and_then(|v| v.as_array());
+    let ata_attribute = |id: u64| {
+        attributes.and_then(|table| {
+            table
+                .iter()
+                .find(|attr| attr.get("id").and_then(|v| v.as_u64()) == Some(id))
+        })
+    };
+
+    let reallocated_sectors = ata_attribute(5)
+        .and_then(|attr| attr.pointer("/raw/value"))
+        .and_then(|v| v.as_u64());
+    // NVMe meldet percentage_used direkt, bei SATA-SSDs aus dem normierten Wear-Wert
+    // (Wear_Leveling_Count / Media_Wearout_Indicator / SSD_Life_Left, 100 = neu)
+    let percentage_used = report
+        .pointer("/nvme_smart_health_information_log/percentage_used")
+        .and_then(|v| v.as_u64())
+        .or_else(|| {
+            [177, 233, 231]
+                .iter()
+                .find_map(|id| ata_attribute(*id))
+                .and_then(|attr| attr.get("value"))
+                .and_then(|v| v.as_u64())
+                .map(|value| 100u64.saturating_sub(value))
+        });
+    let is_ssd = report
+        .get("rotation_rate")
+        .and_then(|v| v.as_u64())
+        .map(|rate| rate == 0)
+        .or_else(|| report.get("nvme_smart_health_information_log").map(|_| true));
+    // Aeltere smartctl-Versionen fuellen "temperature" nicht; dann Attribut 194 bzw. 190.
+    // Der Rohwert enthaelt oft zusaetzlich Min/Max, die aktuelle Temperatur steht im untersten Byte.
+    let temperature = report
+        .pointer("/temperature/current")
+        .and_then(|v| v.as_i64())
+        .or_else(|| {
+            [194, 190]
+                .iter()
+                .find_map(|id| ata_attribute(*id))
+                .and_then(|attr| attr.pointer("/raw/value"))
+                .and_then(|v| v.as_i64())
+                .map(|raw| raw & 0xFF)
+        });
+    // Mit "-n standby" antwortet smartctl nur, wenn die Platte nicht schlaeft;
+    // ACTIVE und IDLE lassen sich dabei nicht unterscheiden
+    let power_mode = match is_ssd {
+        Some(false) => Some("active_or_idle"),
+        _ => None,
+    };
+
+    json!({
+        "deviceIdentifier": device,
+        "model": report.get("model_name").and_then(|v| v.as_str()),
+        "serial": report.get("serial_number").and_then(|v| v.as_str()),
+        "health": health,
+        "reallocatedSectors": reallocated_sectors,
+        "powerOnHours": report.pointer("/power_on_time/hours").and_then(|v| v.as_u64()),
+        "temperatureCelsius": temperature,
+        "percentageUsed": percentage_used,
+        "isSsd": is_ssd,
+        "powerMode": power_mode,
+    })
+}
+
 fn handle_resize_partition(payload: &Value) -> Result<Option<Value>, String> {
-    let partition_identifier = read_string(payload, "partitionIdentifier")?;
+    let partition_identifier = read_device_identifier(payload, "partitionIdentifier")?;
     let new_size = read_string(payload, "newSize")?;
     let device = normalize_device(&partition_identifier);
+    require_confirmation(&device, payload)?;
+
+    let fs_type = detect_fs_type(&device)?;
+    parse_size_bytes(&new_size)?;
+    match fs_type.as_str() {
+        "apfs" | "hfs+" | "fat32" | "ext4" | "ntfs" | "btrfs" | "xfs" => {}
+        // fatresize (libparted) kennt nur FAT16/FAT32
+        "exfat" => return Err("Resize for exFAT is not supported (fatresize handles FAT16/FAT32 only)".to_string()),
+        _ => return Err("Unsupported filesystem for resize".to_string()),
+    }
+    if !online_resize_available(&fs_type) {
+        return Err(coded(
+            "RESIZE_UNSUPPORTED",
+            format!("{fs_type} cannot be mounted on this system and therefore cannot be resized"),
+        ));
+    }
+
+    // Erst nach der Pruefung journalen: ein abgelehnter Resize hinterlaesst keinen Eintrag
+    begin_journal("resize", &device, payload)?;
+
+    // Container-Resize laeuft online (typisch: macOS-Systemcontainer fuer Dual-Boot verkleinern),
+    // daher hier kein Unmount.
+    if fs_type == "apfs" {
+        if let Some(target) = apfs_container_resize_target(&device)? {
+            emit_progress("resize", 0, 100, Some("Start resize"));
+            run_diskutil(["apfs", "resizeContainer", &target, &new_size])?;
+            emit_progress("resize", 100, 100, Some("Resize complete"));
+            sync_kernel_table(&device);
+            clear_journal();
+            return Ok(Some(json!({
+                "device": device,
+                "fs": fs_type,
+                "size": new_size,
+                "path": "container",
+                "target": target,
+            })));
+        }
+    }
 
     maybe_swapoff(&device)?;
     force_unmount_disk(&device)?;
 
-    let fs_type = detect_fs_type(&device)?;
     emit_progress("resize", 0, 100, Some("Start resize"));
     let result = match fs_type.as_str() {
         "apfs" | "hfs+" => {
             run_diskutil(["resizeVolume", &device, &new_size])?;
             emit_progress("resize", 100, 100, Some("Resize complete"));
-            Ok(Some(json!({ "device": device, "fs": fs_type, "size": new_size })))
+            Ok(Some(json!({ "device": device, "fs": fs_type, "size": new_size, "path": "volume" })))
         }
-        "exfat" | "fat32" => Err("Resize for FAT/exFAT not supported yet".to_string()),
-        "ext4" => resize_linux_partition(&device, "ext4", &new_size),
-        "ntfs" => resize_linux_partition(&device, "ntfs", &new_size),
-        _ => Err("Unsupported filesystem for resize".to_string()),
+        linux_fs => resize_linux_partition(&device, linux_fs, &new_size),
     };
 
     if result.is_ok() {
         sync_kernel_table(&device);
+        clear_journal();
     }
     result
 }
 
+// Ab dieser Groesse lohnt sich eine neue Partition im freigewordenen Bereich
+const MIN_USEFUL_PARTITION_BYTES: u64 = 100 * 1024 * 1024;
+
+// Rechnet einen Resize nur durch (gleiche Ausrichtung/Grenzen wie resize_linux_partition),
+// damit die UI den entstehenden freien Bereich schon beim Ziehen anzeigen kann.
+fn handle_preview_resize(payload: &Value) -> Result<Option<Value>, String> {
+    let partition_identifier = read_device_identifier(payload, "partitionIdentifier")?;
+    let new_size = read_string(payload, "newSize")?;
+    let device = normalize_device(&partition_identifier);
+
+    let new_size_bytes = parse_size_bytes(&new_size)?;
+    let info = read_partition_info(&device)?;
+    resize_preview(&info, new_size_bytes).map(Some)
+}
+
+fn resize_preview(info: &PartitionInfo, new_size_bytes: u64) -> Result<Value, String> {
+    let block_size = info.physical_block_size.max(info.block_size);
+    let aligned_size = align_mib(new_size_bytes, block_size);
+    if aligned_size == 0 {
+        return Err("Invalid size".to_string());
+    }
+
+    let current_end = info.partition_offset + info.partition_size;
+    let new_end = info.partition_offset + aligned_size;
+    if new_end > info.max_end {
+        return Err("New size exceeds available space".to_string());
+    }
+
+    // Eine neue Partition muesste wieder ausgerichtet beginnen
+    let unit = alignment_unit(block_size);
+    let unallocated_offset = new_end.div_ceil(unit) * unit;
+    let unallocated_size = info.max_end.saturating_sub(unallocated_offset);
+
+    Ok(json!({
+        "device": info.device,
+        "newSize": aligned_size,
+        "newEnd": new_end,
+        "currentEnd": current_end,
+        "freedBytes": current_end.saturating_sub(new_end),
+        "unallocatedOffset": unallocated_offset,
+        "unallocatedSize": unallocated_size,
+        "canCreatePartition": unallocated_size >= MIN_USEFUL_PARTITION_BYTES,
+    }))
+}
+
 fn handle_move_partition(payload: &Value) -> Result<Option<Value>, String> {
-    let partition_identifier = read_string(payload, "partitionIdentifier")?;
+    let partition_identifier = read_device_identifier(payload, "partitionIdentifier")?;
     let new_start = read_string(payload, "newStart")?;
     let device = normalize_device(&partition_identifier);
-
-    maybe_swapoff(&device)?;
-    force_unmount_disk(&device)?;
+    require_confirmation(&device, payload)?;
 
     let target_start = parse_size_bytes(&new_start)?;
     emit_progress("move", 0, 100, Some("Start move"));
@@ -893,9 +2858,78 @@ fn handle_move_partition(payload: &Value) -> Result<Option<Value>, String> {
     Ok(result)
 }
 
+// Setzt einen unterbrochenen Move ab journal.lastCopied fort
+fn handle_resume_move() -> Result<Option<Value>, String> {
+    let path = journal_path();
+    if !path.exists() {
+        return Err("No interrupted move in the journal".to_string());
+    }
+    let data = std::fs::read_to_string(&path).map_err(|e| format!("Journal read failed: {e}"))?;
+    let journal: Value = serde_json::from_str(&data).map_err(|e| format!("Journal parse failed: {e}"))?;
+    if journal.get("operation").and_then(|v| v.as_str()) != Some("move") {
+        return Err("Journal does not contain a move operation".to_string());
+    }
+
+    let device = read_string(&journal, "device")?;
+    let disk = read_string(&journal, "disk")?;
+    let src_offset = read_u64(&journal, "srcOffset")?;
+    let dst_offset = read_u64(&journal, "dstOffset")?;
+    let size = read_u64(&journal, "size")?;
+    let block_size = read_u64(&journal, "blockSize")?;
+    let last_copied = journal
+        .get("lastCopied")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0)
+        .min(size);
+
+    maybe_swapoff(&device)?;
+    force_unmount_disk(&device)?;
+
+    // Die Partitionstabelle wird erst nach dem Kopieren umgeschrieben; die Partition
+    // muss also noch exakt am alten Offset mit gleicher Größe liegen.
+    let info = read_partition_info(&device)?;
+    if info.disk == disk && info.partition_offset == dst_offset && info.partition_size == size {
+        clear_journal();
+        return Ok(Some(json!({ "device": device, "newStart": dst_offset, "alreadyCompleted": true })));
+    }
+    if info.disk != disk
+        || info.partition_offset != src_offset
+        || info.partition_size != size
+        || info.block_size != block_size
+    {
+        return Err("Disk layout changed since the move was journaled; refusing to resume".to_string());
+    }
+    // Beim Resume wird hoechstens der letzte Block erneut gelesen. Liegen Quelle und Ziel
+    // naeher als ein Block beieinander, koennte dessen Quelle schon ueberschrieben sein.
+    if last_copied > 0 && src_offset.abs_diff(dst_offset) < MOVE_CHUNK_BYTES {
+        return Err(coded(
+            "RESUME_NOT_SAFE",
+            "Source and target overlap within one journal block; resuming could copy overwritten data",
+        ));
+    }
+
+    emit_progress("move", 0, 100, Some("Resume move"));
+    let move_log = copy_blocks(&disk, src_offset, dst_offset, size, true, last_copied)?;
+    let gpt_log = rewrite_moved_partition(&device, &disk, dst_offset, size, block_size)?;
+    clear_journal();
+    emit_progress("move", 100, 100, Some("Move complete"));
+    sync_kernel_table(&device);
+
+    Ok(Some(json!({
+        "device": device,
+        "newStart": dst_offset,
+        "resumedFrom": last_copied,
+        "output": format!("{move_log}\n{gpt_log}").trim(),
+    })))
+}
+
 fn handle_copy_partition(payload: &Value) -> Result<Option<Value>, String> {
-    let source_identifier = read_string(payload, "sourcePartition")?;
-    let target_device = read_string(payload, "targetDevice")?;
+    let source_identifier = read_device_identifier(payload, "sourcePartition")?;
+    let target_device = read_device_identifier(payload, "targetDevice")?;
+    let verify = payload
+        .get("verify")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
 
     let source_device = normalize_device(&source_identifier);
     let target_disk = normalize_device(&target_device);
@@ -925,7 +2959,30 @@ fn handle_copy_partition(payload: &Value) -> Result<Option<Value>, String> {
     run_diskutil(["unmount", "force", &target_partition])?;
 
     emit_progress("copy", 5, 100, Some("Copy blocks"));
-    let copy_log = copy_partition_blocks(&source_device, &target_partition, source_info.partition_size)?;
+    let (copy_method, copy_log) = match clone_used_blocks(&fs_type, &source_device, &target_partition)? {
+        Some((method, log)) => (method, log),
+        None => ("raw", copy_partition_blocks(&source_device, &target_partition, source_info.partition_size)?),
+    };
+
+    // Vor dem UUID-Refresh prüfen, danach unterscheiden sich Quelle und Ziel absichtlich.
+    // Nach einem Clone sind nur die belegten Bloecke gleich, daher dann das Tool-Abbild vergleichen.
+    let mut verified_hash: Option<String> = None;
+    if verify {
+        let (source_hash, target_hash) = match copy_method {
+            "raw" => (
+                hash_device_prefix(&source_device, source_info.partition_size, "Verifying source")?,
+                hash_device_prefix(&target_partition, source_info.partition_size, "Verifying target")?,
+            ),
+            method => (
+                hash_used_blocks(method, &source_device, "Verifying source")?,
+                hash_used_blocks(method, &target_partition, "Verifying target")?,
+            ),
+        };
+        if source_hash != target_hash {
+            return Err(coded("VERIFY_MISMATCH", "Verification failed: checksum mismatch"));
+        }
+        verified_hash = Some(target_hash);
+    }
 
     emit_progress("copy", 85, 100, Some("Update GPT type"));
     let type_warning = set_partition_typecode(&target_partition, &fs_type)?;
@@ -959,7 +3016,10 @@ fn handle_copy_partition(payload: &Value) -> Result<Option<Value>, String> {
         "source": source_device,
         "target": target_partition,
         "fs": fs_type,
+        "method": copy_method,
         "output": copy_log,
+        "verified": verify,
+        "verifiedHash": verified_hash,
         "warnings": warnings,
     })))
 }
@@ -972,6 +3032,40 @@ fn read_string(payload: &Value, key: &str) -> Result<String, String> {
         .ok_or_else(|| format!("Missing field: {key}"))
 }
 
+// Identifier landen unveraendert in diskutil/dd-Argumenten; nur diskN[sM...] (optional
+// mit /dev/ bzw. rdisk) zulassen, damit z.B. "disk0s1; rm -rf" oder "-force" nie durchkommen.
+fn validate_device_identifier(identifier: &str, field: &str) -> Result<(), String> {
+    let rest = identifier.strip_prefix("/dev/").unwrap_or(identifier);
+    let valid = rest
+        .strip_prefix("rdisk")
+        .or_else(|| rest.strip_prefix("disk"))
+        .map(|numbers| {
+            numbers
+                .split('s')
+                .all(|part| !part.is_empty() && part.chars().all(|ch| ch.is_ascii_digit()))
+        })
+        .unwrap_or(false);
+    if valid {
+        Ok(())
+    } else {
+        Err(coded("INVALID_IDENTIFIER", format!("Invalid device identifier in {field}")))
+    }
+}
+
+// diskN bzw. /dev/(r)diskN ohne Slice-Suffix
+fn is_whole_disk_identifier(identifier: &str) -> bool {
+    let rest = identifier.strip_prefix("/dev/").unwrap_or(identifier);
+    rest.strip_prefix("rdisk")
+        .or_else(|| rest.strip_prefix("disk"))
+        .is_some_and(|number| !number.is_empty() && number.chars().all(|ch| ch.is_ascii_digit()))
+}
+
+fn read_device_identifier(payload: &Value, key: &str) -> Result<String, String> {
+    let identifier = read_string(payload, key)?;
+    validate_device_identifier(&identifier, key)?;
+    Ok(identifier)
+}
+
 fn read_u64(payload: &Value, key: &str) -> Result<u64, String> {
     payload
         .get(key)
@@ -1061,10 +3155,29 @@ fn disk_info_dict(device: &str) -> Result<plist::Dictionary, String> {
         .ok_or_else(|| "Invalid plist".to_string())
 }
 
+// Physical Store (Content "Apple_APFS") oder synthetisierter Container (WholeDisk mit
+// APFSPhysicalStores) -> Ziel fuer "diskutil apfs resizeContainer". Bei einem Volume None.
+fn apfs_container_resize_target(device: &str) -> Result<Option<String>, String> {
+    let dict = disk_info_dict(device)?;
+    let content = dict.get("Content").and_then(|v| v.as_string()).unwrap_or("");
+    if content == "Apple_APFS" {
+        return Ok(Some(device.to_string()));
+    }
+
+    let whole_disk = dict.get("WholeDisk").and_then(|v| v.as_boolean()).unwrap_or(false);
+    let has_stores = matches!(dict.get("APFSPhysicalStores"), Some(PlistValue::Array(stores)) if !stores.is_empty());
+    if whole_disk && has_stores {
+        return Ok(Some(device.to_string()));
+    }
+
+    Ok(None)
+}
+
+// "disk4s2" -> "disk4"; das 's' in "disk" selbst darf dabei nicht zaehlen
 fn base_disk_identifier(device: &str) -> String {
     let needle = strip_device_prefix(device);
-    if let Some(pos) = needle.find('s') {
-        return needle[..pos].to_string();
+    if let Some(pos) = needle.get(4..).and_then(|rest| rest.find('s')) {
+        return needle[..pos + 4].to_string();
     }
     needle
 }
@@ -1163,17 +3276,21 @@ fn required_sidecars(operation: &str, fs_type: &str) -> Vec<SidecarCheck> {
         }
     }
     if matches!(operation, "resize") {
-        if fs_type == "ext4" {
+        if let Some(bin) = driver_for(fs_type).and_then(|driver| driver.resize_binary(false)) {
             names.push("sgdisk".to_string());
-            names.push("resize2fs".to_string());
-        } else if fs_type == "ntfs" {
-            names.push("sgdisk".to_string());
-            names.push("ntfsresize".to_string());
+            names.push(bin.to_string());
         }
     }
     if matches!(operation, "move") {
         names.push("sgdisk".to_string());
     }
+    if operation == "smart" {
+        names.push("smartctl".to_string());
+    }
+    // Fuer Create/Format ist ntfs-3g nur fuer den beschreibbaren Mount danach noetig (siehe preflight-Warnung)
+    if fs_type == "ntfs" && operation == "mount" {
+        names.push("ntfs-3g".to_string());
+    }
 
     names
         .into_iter()
@@ -1196,6 +3313,7 @@ fn mkfs_binary_for(fs_type: &str) -> Option<&'static str> {
         "xfs" => Some("mkfs.xfs"),
         "f2fs" => Some("mkfs.f2fs"),
         "swap" => Some("mkswap"),
+        "zfs" => Some("zpool"),
         _ => None,
     }
 }
@@ -1210,6 +3328,48 @@ fn run_quick_fs_check(device: &str, fs_type: &str) -> Result<FsCheckResult, Stri
     Ok(FsCheckResult { ok: true, output })
 }
 
+// Grobe Durchsatzannahme (Bytes/s) nach BusProtocol; diskutil unterscheidet USB2/USB3 nicht,
+// daher fuer USB-SSDs USB3-Niveau und fuer USB-HDDs/Sticks ein konservativer Wert.
+fn assumed_throughput(device: &str) -> (Option<String>, u64) {
+    const MB: u64 = 1024 * 1024;
+    let dict = match disk_info_dict(device) {
+        Ok(d) => d,
+        Err(_) => return (None, 50 * MB),
+    };
+    let protocol = dict.get("BusProtocol").and_then(|v| v.as_string()).map(|v| v.to_string());
+    let solid_state = dict.get("SolidState").and_then(|v| v.as_boolean()).unwrap_or(false);
+
+    let throughput = match protocol.as_deref().unwrap_or("").to_lowercase().as_str() {
+        "pci-express" | "pci" | "apple fabric" | "nvme" => 1500 * MB,
+        "thunderbolt" => 800 * MB,
+        "sata" if solid_state => 400 * MB,
+        "sata" => 120 * MB,
+        "usb" if solid_state => 300 * MB,
+        "usb" => 30 * MB,
+        "secure digital" => 40 * MB,
+        _ => 50 * MB,
+    };
+    (protocol, throughput)
+}
+
+fn estimate_operation_seconds(operation: &str, device: &str, source_path: Option<&str>, throughput: u64) -> u64 {
+    // Reine Metadaten-Operationen (Label, Tabelle, Loeschen, Formatieren)
+    const METADATA_SECONDS: u64 = 5;
+
+    let data_bytes = match operation {
+        "flash" | "windows_install" => source_path
+            .and_then(|path| std::fs::metadata(path).ok())
+            .map(|meta| meta.len()),
+        "copy" | "resize" | "move" | "backup" => volume_used_bytes(device),
+        _ => None,
+    };
+
+    match data_bytes {
+        Some(bytes) if throughput > 0 => METADATA_SECONDS + bytes / throughput,
+        _ => METADATA_SECONDS,
+    }
+}
+
 fn volume_used_bytes(device: &str) -> Option<u64> {
     let output = Command::new("diskutil")
         .args(["info", "-plist", device])
@@ -1254,17 +3414,147 @@ fn is_boot_volume(device: &str) -> bool {
     false
 }
 
-fn force_unmount_disk(device: &str) -> Result<(), String> {
-    let disk = parent_disk_identifier(device).unwrap_or_else(|| device.to_string());
-    let _ = run_diskutil(["unmount", "force", device]);
-    run_diskutil(["unmountDisk", "force", &disk])?;
+// Serverseitige Zweitpruefung: interne oder geschuetzte Geraete verlangen, dass die UI
+// den aktuellen Volume-Namen (bzw. den Disk-Identifier) als confirmationToken mitschickt.
+fn require_confirmation(device: &str, payload: &Value) -> Result<(), String> {
+    let info = disk_info_dict(device).unwrap_or_default();
+    let internal = info
+        .get("Internal")
+        .and_then(|v| v.as_boolean())
+        .unwrap_or(false);
+    let protected = is_boot_volume(device) || disk_hosts_system_volume(device);
+    if !internal && !protected {
+        return Ok(());
+    }
+
+    let expected = info
+        .get("VolumeName")
+        .and_then(|v| v.as_string())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| strip_device_prefix(device));
+    check_confirmation_token(device, &expected, payload)
+}
+
+// Der Token muss dem aktuellen Volume-Namen (bzw. dem Identifier ohne /dev/) entsprechen
+fn check_confirmation_token(device: &str, expected: &str, payload: &Value) -> Result<(), String> {
+    let token = payload
+        .get("confirmationToken")
+        .and_then(|v| v.as_str())
+        .map(|token| token.trim())
+        .unwrap_or("");
+
+    if token.is_empty() {
+        return Err(coded(
+            "CONFIRMATION_REQUIRED",
+            format!("{device} is an internal or protected device; confirmation token required"),
+        ));
+    }
+    if token != expected {
+        return Err(coded(
+            "CONFIRMATION_MISMATCH",
+            format!("confirmation token does not match the current name of {device}"),
+        ));
+    }
     Ok(())
 }
 
+// Prueft, ob die Disk (oder ein APFS Physical Store darauf) das laufende System traegt.
+fn disk_hosts_system_volume(device: &str) -> bool {
+    let output = match Command::new("diskutil").args(["info", "-plist", "/"]).output() {
+        Ok(o) if o.status.success() => o,
+        _ => return false,
+    };
+    let plist = match PlistValue::from_reader_xml(&output.stdout[..]) {
+        Ok(p) => p,
+        Err(_) => return false,
+    };
+    let dict = match plist.as_dictionary() {
+        Some(d) => d,
+        None => return false,
+    };
+
+    let mut system_disks = Vec::new();
+    if let Some(parent) = dict.get("ParentWholeDisk").and_then(|v| v.as_string()) {
+        system_disks.push(normalize_device(parent));
+    }
+    if let Some(PlistValue::Array(stores)) = dict.get("APFSPhysicalStores") {
+        for store in stores {
+            let name = store
+                .as_dictionary()
+                .and_then(|d| d.get("APFSPhysicalStore"))
+                .and_then(|v| v.as_string());
+            if let Some(name) = name {
+                let store_device = normalize_device(name);
+                system_disks.push(parent_disk_identifier(&store_device).unwrap_or(store_device));
+            }
+        }
+    }
+
+    let disk = parent_disk_identifier(device).unwrap_or_else(|| device.to_string());
+    system_disks.iter().any(|candidate| *candidate == disk || *candidate == device)
+}
+
+// Nichts gemountet gilt als Erfolg. Ein Fehler von unmountDisk zaehlt nur, wenn danach
+// tatsaechlich noch Volumes eingehaengt sind. Liefert die ausgehaengten Volumes
+// (zusaetzlich in details.unmountedVolumes der Antwort).
+fn force_unmount_disk(device: &str) -> Result<Vec<String>, String> {
+    let disk = parent_disk_identifier(device).unwrap_or_else(|| device.to_string());
+    let mounted = mounted_volumes_on(device)?;
+    if mounted.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    if read_mount_point(device).ok().flatten().is_some() {
+        let _ = run_diskutil(["unmount", "force", device]);
+    }
+    if let Err(err) = run_diskutil(["unmountDisk", "force", &disk]) {
+        let remaining = mounted_volumes_on(device)?;
+        if !remaining.is_empty() {
+            let still: Vec<String> = remaining.into_iter().map(|(_, mount_point)| mount_point).collect();
+            return Err(format!("{err} (still mounted: {})", still.join(", ")));
+        }
+    }
+
+    let unmounted: Vec<String> = mounted
+        .into_iter()
+        .map(|(identifier, mount_point)| format!("{identifier} ({mount_point})"))
+        .collect();
+    if let Ok(mut collected) = UNMOUNTED_VOLUMES.lock() {
+        collected.extend(unmounted.iter().cloned());
+    }
+    Ok(unmounted)
+}
+
+// Eingehaengte Volumes der Disk (inkl. APFS-Volumes auf ihr) plus das Device selbst
+fn mounted_volumes_on(device: &str) -> Result<Vec<(String, String)>, String> {
+    let plist = diskutil_plist(["list", "-plist"])?;
+    let mut volumes = parse_mounted_volumes(&plist, &base_disk_identifier(device));
+    let identifier = strip_device_prefix(device);
+    if !volumes.iter().any(|(id, _)| *id == identifier) {
+        if let Some(mount_point) = read_mount_point(device).ok().flatten() {
+            volumes.push((identifier, mount_point));
+        }
+    }
+    Ok(volumes)
+}
+
+// Fehler hier lassen die eigentliche Operation nicht scheitern, landen aber als Warnung im Ergebnis.
 fn sync_kernel_table(device: &str) {
     let disk = parent_disk_identifier(device).unwrap_or_else(|| device.to_string());
-    let _ = run_diskutil(["quiet", "repairDisk", &disk]);
-    let _ = run_diskutil(["updateDefaultPartitionOrder", &disk]);
+    if let Err(err) = run_diskutil(["quiet", "repairDisk", &disk]) {
+        push_tool_warning(format!("repairDisk {disk} failed: {}", err.trim()));
+    }
+    if let Err(err) = run_diskutil(["updateDefaultPartitionOrder", &disk]) {
+        push_tool_warning(format!("updateDefaultPartitionOrder {disk} failed: {}", err.trim()));
+    }
+}
+
+fn push_tool_warning(warning: String) {
+    emit_log("diskutil", &warning);
+    if let Ok(mut collected) = TOOL_WARNINGS.lock() {
+        collected.push(warning);
+    }
 }
 
 fn maybe_swapoff(device: &str) -> Result<(), String> {
@@ -1272,6 +3562,10 @@ fn maybe_swapoff(device: &str) -> Result<(), String> {
     if fs_type != "swap" {
         return Ok(());
     }
+    if is_dry_run() {
+        log_dry_run("swapoff", [device]);
+        return Ok(());
+    }
 
     if Command::new("swapoff").args(["-a"]).output().is_ok() {
         return Ok(());
@@ -1287,8 +3581,22 @@ fn maybe_swapoff(device: &str) -> Result<(), String> {
     Err("swapoff not available".to_string())
 }
 
+// Basisordner fuer Journal und Audit-Log. OXIDISK_STATE_DIR hat Vorrang (Tests, Linux-Port);
+// auf macOS bleibt es beim systemweiten Ordner, damit bestehende Journale gefunden werden.
+fn state_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("OXIDISK_STATE_DIR").filter(|dir| !dir.is_empty()) {
+        return PathBuf::from(dir);
+    }
+    if cfg!(target_os = "macos") {
+        return PathBuf::from("/Library/Application Support/com.oliverquick.oxidisk");
+    }
+    dirs::data_dir()
+        .map(|dir| dir.join("oxidisk"))
+        .unwrap_or_else(|| PathBuf::from("/var/lib/oxidisk"))
+}
+
 fn journal_path() -> PathBuf {
-    PathBuf::from("/Library/Application Support/com.oliverquick.oxidisk/operation_journal.json")
+    state_dir().join("operation_journal.json")
 }
 
 fn write_journal(value: &Value) -> Result<(), String> {
@@ -1297,27 +3605,148 @@ fn write_journal(value: &Value) -> Result<(), String> {
         std::fs::create_dir_all(dir).map_err(|e| format!("Journal mkdir failed: {e}"))?;
     }
     let data = serde_json::to_string_pretty(value).map_err(|e| format!("Journal encode failed: {e}"))?;
-    std::fs::write(&path, data).map_err(|e| format!("Journal write failed: {e}"))?;
+    // Temp-Datei + fsync + rename: nach Absturz/Stromausfall steht entweder der alte
+    // oder der neue Stand im Journal, nie ein halb geschriebener.
+    let temp = path.with_extension("json.tmp");
+    let mut file = std::fs::File::create(&temp).map_err(|e| format!("Journal write failed: {e}"))?;
+    file.write_all(data.as_bytes()).map_err(|e| format!("Journal write failed: {e}"))?;
+    file.sync_all().map_err(|e| format!("Journal sync failed: {e}"))?;
+    std::fs::rename(&temp, &path).map_err(|e| format!("Journal write failed: {e}"))?;
+    Ok(())
+}
+
+// Eintrag für eine destruktive Operation; bleibt bei Abbruch/Fehler stehen,
+// damit get_journal die unterbrochene Operation beim nächsten Start meldet.
+// Ein unterbrochener Move ist nur ueber sein Journal (lastCopied) fortsetzbar; eine neue
+// Operation wuerde es ueberschreiben und die Partition in halb kopiertem Zustand zuruecklassen.
+fn ensure_no_pending_move() -> Result<(), String> {
+    let pending = handle_get_journal()
+        .ok()
+        .flatten()
+        .is_some_and(|journal| journal.get("operation").and_then(|v| v.as_str()) == Some("move"));
+    if pending {
+        return Err(coded(
+            "MOVE_PENDING",
+            "An interrupted partition move must be resumed or cleared first",
+        ));
+    }
     Ok(())
 }
 
+fn begin_journal(operation: &str, device: &str, params: &Value) -> Result<(), String> {
+    if is_dry_run() {
+        return Ok(());
+    }
+    ensure_no_pending_move()?;
+    let now = current_timestamp();
+    write_journal(&json!({
+        "operation": operation,
+        "device": device,
+        "params": params,
+        "startedAt": now,
+        "updatedAt": now,
+    }))
+}
+
 fn update_journal_progress(copied: u64) -> Result<(), String> {
+    annotate_journal("lastCopied", json!(copied))
+}
+
+fn annotate_journal(key: &str, field: Value) -> Result<(), String> {
     let path = journal_path();
     if !path.exists() {
         return Ok(());
     }
     let data = std::fs::read_to_string(&path).map_err(|e| format!("Journal read failed: {e}"))?;
     let mut value: Value = serde_json::from_str(&data).map_err(|e| format!("Journal parse failed: {e}"))?;
-    value["lastCopied"] = json!(copied);
+    value[key] = field;
     value["updatedAt"] = json!(current_timestamp());
     write_journal(&value)
 }
 
 fn clear_journal() {
+    if is_dry_run() {
+        return;
+    }
     let path = journal_path();
     let _ = std::fs::remove_file(path);
 }
 
+fn audit_log_path() -> PathBuf {
+    state_dir().join("operation_audit.jsonl")
+}
+
+// Reine Abfragen erzeugen keinen Eintrag, sonst fuellt das Polling der UI das Protokoll
+const UNAUDITED_ACTIONS: &[&str] = &[
+    "version",
+    "get_journal",
+    "get_audit_log",
+    "preview_resize",
+    "fstab_entry",
+    "filesystem_constraints",
+    "supported_filesystems",
+];
+
+// Append-only Protokoll aller Helper-Aktionen (eine JSON-Zeile pro Aufruf). Anders als das
+// Journal wird es nie automatisch geleert; Fehler beim Schreiben brechen die Operation nicht ab.
+fn append_audit_entry(action: &str, payload: &Value, result: &Result<Option<Value>, String>) {
+    if UNAUDITED_ACTIONS.contains(&action) {
+        return;
+    }
+    let device = ["partitionIdentifier", "deviceIdentifier", "containerIdentifier", "volumeIdentifier", "targetIdentifier"]
+        .iter()
+        .find_map(|key| payload.get(*key).and_then(|v| v.as_str()));
+    let (ok, code, error) = match result {
+        Ok(_) => (true, None, None),
+        Err(message) => {
+            let (code, message) = split_error_code(message);
+            (false, code, Some(message))
+        }
+    };
+    let entry = json!({
+        "timestamp": current_timestamp(),
+        "action": action,
+        "device": device,
+        "params": redact_secrets(payload),
+        "ok": ok,
+        "code": code,
+        "error": error,
+        "dryRun": is_dry_run(),
+    });
+
+    let path = audit_log_path();
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    let Ok(mut line) = serde_json::to_string(&entry) else {
+        return;
+    };
+    line.push('\n');
+    if let Ok(mut file) = std::fs::OpenOptions::new().create(true).append(true).open(&path) {
+        let _ = file.write_all(line.as_bytes());
+    }
+}
+
+// Passwoerter/Passphrasen nie ins Protokoll schreiben
+fn redact_secrets(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, field)| {
+                    let lower = key.to_lowercase();
+                    let secret = ["password", "passphrase", "secret", "recoverykey"]
+                        .iter()
+                        .any(|needle| lower.contains(needle));
+                    let field = if secret { json!("<redacted>") } else { redact_secrets(field) };
+                    (key.clone(), field)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(redact_secrets).collect()),
+        other => other.clone(),
+    }
+}
+
 fn normalize_device(identifier: &str) -> String {
     if identifier.starts_with("/dev/") {
         identifier.to_string()
@@ -1336,7 +3765,19 @@ fn raw_device_path(device: &str) -> String {
     }
 }
 
+// Rohe Schreibzugriffe lassen sich nicht simulieren; im Dry-Run wird hart abgebrochen
+fn ensure_not_dry_run() -> Result<(), String> {
+    if is_dry_run() {
+        return Err(coded(
+            "DRY_RUN_UNSUPPORTED",
+            "Raw device writes are not available in dry-run",
+        ));
+    }
+    Ok(())
+}
+
 fn open_device_for_write(path: &str) -> Result<std::fs::File, String> {
+    ensure_not_dry_run()?;
     let mut options = std::fs::OpenOptions::new();
     options.write(true);
     #[cfg(target_os = "macos")]
@@ -1375,72 +3816,258 @@ fn read_disk_size(device: &str) -> Option<u64> {
         .or_else(|| dict.get("Size").and_then(|v| v.as_unsigned_integer()))
 }
 
-fn flash_write_with_hash(source_path: &str, target_device: &str, total_bytes: u64) -> Result<String, String> {
-    if total_bytes == 0 {
-        return Err("Image is empty".to_string());
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ImageCompression {
+    None,
+    Gzip,
+    Xz,
+    Zstd,
+    Zip,
+}
+
+impl ImageCompression {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ImageCompression::None => "none",
+            ImageCompression::Gzip => "gzip",
+            ImageCompression::Xz => "xz",
+            ImageCompression::Zstd => "zstd",
+            ImageCompression::Zip => "zip",
+        }
     }
+}
 
-    let mut source = std::fs::OpenOptions::new()
-        .read(true)
-        .open(source_path)
-        .map_err(|e| format!("Open image failed: {e}"))?;
-    let mut target = open_device_for_write(target_device)?;
+fn detect_image_compression(path: &str) -> Result<ImageCompression, String> {
+    let mut file = std::fs::File::open(path).map_err(|e| format!("Open image failed: {e}"))?;
+    let mut magic = [0u8; 6];
+    let mut filled = 0usize;
+    while filled < magic.len() {
+        let read = file.read(&mut magic[filled..]).map_err(|e| format!("Image read failed: {e}"))?;
+        if read == 0 {
+            break;
+        }
+        filled += read;
+    }
+    let magic = &magic[..filled];
 
-    let buffer_size = 4 * 1024 * 1024;
-    let mut buffer = vec![0u8; buffer_size];
-    let mut remaining = total_bytes;
-    let mut copied: u64 = 0;
-    let progress_step: u64 = 50 * 1024 * 1024;
-    let mut next_progress = progress_step;
-    let mut hasher = Sha256::new();
-    let mut last_progress_at = Instant::now();
-    let mut last_progress_bytes: u64 = 0;
-    let mut slow_streak = 0u32;
-    let mut warned = false;
+    if magic.starts_with(&[0x1f, 0x8b]) {
+        return Ok(ImageCompression::Gzip);
+    }
+    if magic.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+        return Ok(ImageCompression::Xz);
+    }
+    if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        return Ok(ImageCompression::Zstd);
+    }
+    if magic.starts_with(b"PK\x03\x04") {
+        return Ok(ImageCompression::Zip);
+    }
 
-    while remaining > 0 {
-        let chunk = std::cmp::min(buffer_size as u64, remaining) as usize;
-        source.read_exact(&mut buffer[..chunk]).map_err(|e| e.to_string())?;
-        target.write_all(&buffer[..chunk]).map_err(|e| e.to_string())?;
-        hasher.update(&buffer[..chunk]);
-        remaining -= chunk as u64;
-        copied += chunk as u64;
-        if copied >= next_progress || remaining == 0 {
-            let elapsed = last_progress_at.elapsed().as_secs_f64().max(0.001);
-            let delta = copied.saturating_sub(last_progress_bytes);
-            let speed = (delta as f64 / (1024.0 * 1024.0)) / elapsed;
-            let percent = ((copied as f64 / total_bytes as f64) * 100.0).round() as u64;
-            emit_progress_bytes("flash", percent, 100, Some("Writing image"), copied, total_bytes);
-            next_progress += progress_step;
-            last_progress_at = Instant::now();
-            last_progress_bytes = copied;
-            if speed < 1.0 && copied < (total_bytes * 9 / 10) {
-                slow_streak += 1;
-            } else {
-                slow_streak = 0;
+    // Fallback auf die Dateiendung, falls die Magic Bytes nicht eindeutig sind.
+    let lower = path.to_lowercase();
+    let compression = if lower.ends_with(".gz") {
+        ImageCompression::Gzip
+    } else if lower.ends_with(".xz") {
+        ImageCompression::Xz
+    } else if lower.ends_with(".zst") {
+        ImageCompression::Zstd
+    } else if lower.ends_with(".zip") {
+        ImageCompression::Zip
+    } else {
+        ImageCompression::None
+    };
+    Ok(compression)
+}
+
+// Liefert die entpackte Groesse, sofern sie ohne Dekompression ermittelbar ist.
+fn image_uncompressed_size(path: &str, compression: ImageCompression) -> Result<Option<u64>, String> {
+    let file_size = std::fs::metadata(path)
+        .map_err(|e| format!("Image read failed: {e}"))?
+        .len();
+    match compression {
+        ImageCompression::None => Ok(Some(file_size)),
+        ImageCompression::Gzip => {
+            if file_size < 18 {
+                return Ok(None);
             }
-            if slow_streak >= 3 && !warned {
-                emit_log(
+            let mut file = std::fs::File::open(path).map_err(|e| format!("Open image failed: {e}"))?;
+            file.seek(SeekFrom::End(-4)).map_err(|e| format!("Image read failed: {e}"))?;
+            let mut trailer = [0u8; 4];
+            file.read_exact(&mut trailer).map_err(|e| format!("Image read failed: {e}"))?;
+            Ok(gzip_trailer_size(file_size, u32::from_le_bytes(trailer)))
+        }
+        ImageCompression::Zip => {
+            let file = std::fs::File::open(path).map_err(|e| format!("Open image failed: {e}"))?;
+            let mut archive = zip::ZipArchive::new(file).map_err(|e| format!("Zip read failed: {e}"))?;
+            let index = zip_image_index(&mut archive)?;
+            let entry = archive.by_index(index).map_err(|e| format!("Zip read failed: {e}"))?;
+            Ok(Some(entry.size()))
+        }
+        ImageCompression::Xz | ImageCompression::Zstd => Ok(None),
+    }
+}
+
+// Deflate schafft hoechstens etwa 1032:1.
+const DEFLATE_MAX_RATIO: u64 = 1032;
+
+// ISIZE im Trailer ist die Groesse modulo 2^32; ein 6-GiB-Image meldet dort 2 GiB. Nur wenn
+// selbst bei maximaler Kompression keine 4 GiB entstehen koennen, ist der Wert eindeutig.
+fn gzip_trailer_size(file_size: u64, isize: u32) -> Option<u64> {
+    (file_size.saturating_mul(DEFLATE_MAX_RATIO) < 1 << 32).then_some(u64::from(isize))
+}
+
+// Oeffnet das Image als entpackten Datenstrom und uebergibt ihn an `consume`.
+// Zip-Eintraege leihen sich das Archiv, daher der Closure-Ansatz statt eines Rueckgabewerts.
+fn with_image_reader<T>(
+    path: &str,
+    compression: ImageCompression,
+    consume: impl FnOnce(&mut dyn Read) -> Result<T, String>,
+) -> Result<T, String> {
+    let file = std::fs::File::open(path).map_err(|e| format!("Open image failed: {e}"))?;
+    match compression {
+        ImageCompression::None => consume(&mut std::io::BufReader::new(file)),
+        ImageCompression::Gzip => consume(&mut flate2::read::MultiGzDecoder::new(std::io::BufReader::new(file))),
+        ImageCompression::Xz => consume(&mut xz2::read::XzDecoder::new_multi_decoder(std::io::BufReader::new(file))),
+        ImageCompression::Zstd => {
+            let mut decoder = zstd::stream::read::Decoder::new(file).map_err(|e| format!("Zstd init failed: {e}"))?;
+            consume(&mut decoder)
+        }
+        ImageCompression::Zip => {
+            let mut archive = zip::ZipArchive::new(file).map_err(|e| format!("Zip read failed: {e}"))?;
+            let index = zip_image_index(&mut archive)?;
+            let mut entry = archive.by_index(index).map_err(|e| format!("Zip read failed: {e}"))?;
+            consume(&mut entry)
+        }
+    }
+}
+
+// Index der einzigen Datei im Archiv; Ordner-Eintraege zaehlen nicht, mehrere Dateien sind ein Fehler.
+fn zip_image_index<R: Read + Seek>(archive: &mut zip::ZipArchive<R>) -> Result<usize, String> {
+    let files = (0..archive.len())
+        .filter(|idx| archive.by_index(*idx).map(|entry| entry.is_file()).unwrap_or(false))
+        .collect::<Vec<_>>();
+    match files.as_slice() {
+        [index] => Ok(*index),
+        _ => Err(format!(
+            "Zip archive must contain exactly one image file (found {})",
+            files.len()
+        )),
+    }
+}
+
+// Fuellt den Puffer so weit wie moeglich, damit Raw-Device-Writes blockweise bleiben.
+fn read_full(source: &mut dyn Read, buffer: &mut [u8]) -> Result<usize, String> {
+    let mut filled = 0usize;
+    while filled < buffer.len() {
+        match source.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(format!("Image read failed: {e}")),
+        }
+    }
+    Ok(filled)
+}
+
+// Schreibt das (ggf. entpackte) Image und liefert Hash und geschriebene Bytes.
+// Ist die Groesse unbekannt, wird Fortschritt mit totalBytes = 0 gemeldet (unbestimmt).
+fn flash_write_with_hash(
+    source_path: &str,
+    target_device: &str,
+    compression: ImageCompression,
+    expected_bytes: Option<u64>,
+) -> Result<(String, u64), String> {
+    if expected_bytes == Some(0) {
+        return Err("Image is empty".to_string());
+    }
+    let total_bytes = expected_bytes.unwrap_or(0);
+
+    with_image_reader(source_path, compression, |source| {
+        let mut target = open_device_for_write(target_device)?;
+
+        let buffer_size = 4 * 1024 * 1024;
+        let mut buffer = vec![0u8; buffer_size];
+        let mut copied: u64 = 0;
+        let progress_step: u64 = 50 * 1024 * 1024;
+        let mut next_progress = progress_step;
+        let mut hasher = Sha256::new();
+        let mut meter = ThroughputMeter::new();
+        let mut last_progress_at = Instant::now();
+        let mut last_progress_bytes: u64 = 0;
+        let mut slow_streak = 0u32;
+        let mut warned = false;
+
+        loop {
+            ensure_not_cancelled()?;
+            let chunk = read_full(source, &mut buffer)?;
+            if chunk > 0 {
+                target.write_all(&buffer[..chunk]).map_err(|e| e.to_string())?;
+                hasher.update(&buffer[..chunk]);
+                copied += chunk as u64;
+            }
+            let done = chunk < buffer_size;
+            if copied >= next_progress || done {
+                let elapsed = last_progress_at.elapsed().as_secs_f64().max(0.001);
+                let delta = copied.saturating_sub(last_progress_bytes);
+                let speed = (delta as f64 / (1024.0 * 1024.0)) / elapsed;
+                let percent = if total_bytes > 0 {
+                    ((copied as f64 / total_bytes as f64) * 100.0).round().min(100.0) as u64
+                } else {
+                    0
+                };
+                let (bytes_per_second, eta_seconds) = meter.sample(copied, total_bytes);
+                emit_progress_rate(
                     "flash",
-                    "Warnung: Sehr langsamer Schreibdurchsatz. Stick koennte defekt oder gefaelscht sein.",
+                    percent,
+                    100,
+                    Some("Writing image"),
+                    copied,
+                    total_bytes,
+                    Some((bytes_per_second, eta_seconds)),
                 );
-                warned = true;
+                next_progress = copied + progress_step;
+                last_progress_at = Instant::now();
+                last_progress_bytes = copied;
+                let near_end = total_bytes > 0 && copied >= total_bytes * 9 / 10;
+                if speed < 1.0 && !near_end {
+                    slow_streak += 1;
+                } else {
+                    slow_streak = 0;
+                }
+                if slow_streak >= 3 && !warned {
+                    emit_log(
+                        "flash",
+                        "Warnung: Sehr langsamer Schreibdurchsatz. Stick koennte defekt oder gefaelscht sein.",
+                    );
+                    warned = true;
+                }
+            }
+            if done {
+                break;
             }
         }
-    }
 
-    target.flush().map_err(|e| format!("Flush failed: {e}"))?;
+        if copied == 0 {
+            return Err("Image is empty".to_string());
+        }
+        target.flush().map_err(|e| format!("Flush failed: {e}"))?;
 
-    let hash = hasher.finalize();
-    Ok(format!("{:x}", hash))
+        let hash = hasher.finalize();
+        Ok((format!("{:x}", hash), copied))
+    })
 }
 
 fn flash_verify_with_hash(target_device: &str, total_bytes: u64) -> Result<String, String> {
     if total_bytes == 0 {
         return Err("Image is empty".to_string());
     }
+    hash_device_prefix(target_device, total_bytes, "Verifying image")
+}
 
-    let mut target = open_device_for_read(target_device)?;
+// SHA-256 über die ersten total_bytes eines Devices, Fortschritt als Phase "verify"
+fn hash_device_prefix(device: &str, total_bytes: u64, message: &str) -> Result<String, String> {
+    let mut target = open_device_for_read(device)?;
+    let mut meter = ThroughputMeter::new();
 
     let buffer_size = 4 * 1024 * 1024;
     let mut buffer = vec![0u8; buffer_size];
@@ -1451,6 +4078,7 @@ fn flash_verify_with_hash(target_device: &str, total_bytes: u64) -> Result<Strin
     let mut hasher = Sha256::new();
 
     while remaining > 0 {
+        ensure_not_cancelled()?;
         let chunk = std::cmp::min(buffer_size as u64, remaining) as usize;
         target.read_exact(&mut buffer[..chunk]).map_err(|e| e.to_string())?;
         hasher.update(&buffer[..chunk]);
@@ -1458,7 +4086,8 @@ fn flash_verify_with_hash(target_device: &str, total_bytes: u64) -> Result<Strin
         copied += chunk as u64;
         if copied >= next_progress || remaining == 0 {
             let percent = ((copied as f64 / total_bytes as f64) * 100.0).round() as u64;
-            emit_progress_bytes("verify", percent, 100, Some("Verifying image"), copied, total_bytes);
+            let rate = meter.sample(copied, total_bytes);
+            emit_progress_rate("verify", percent, 100, Some(message), copied, total_bytes, Some(rate));
             next_progress += progress_step;
         }
     }
@@ -1468,6 +4097,17 @@ fn flash_verify_with_hash(target_device: &str, total_bytes: u64) -> Result<Strin
 }
 
 fn hash_file_with_progress(path: &str, total_bytes: u64) -> Result<String, String> {
+    Ok(hash_file_digests(path, total_bytes, false, false)?.sha256)
+}
+
+struct FileDigests {
+    sha256: String,
+    md5: Option<String>,
+    sha1: Option<String>,
+}
+
+// SHA-256 wird immer berechnet; MD5/SHA-1 nur auf Wunsch, im selben Lesedurchlauf.
+fn hash_file_digests(path: &str, total_bytes: u64, with_md5: bool, with_sha1: bool) -> Result<FileDigests, String> {
     if total_bytes == 0 {
         return Err("Image is empty".to_string());
     }
@@ -1484,11 +4124,20 @@ fn hash_file_with_progress(path: &str, total_bytes: u64) -> Result<String, Strin
     let progress_step: u64 = 50 * 1024 * 1024;
     let mut next_progress = progress_step;
     let mut hasher = Sha256::new();
+    let mut md5_hasher = with_md5.then(Md5::new);
+    let mut sha1_hasher = with_sha1.then(Sha1::new);
 
     while remaining > 0 {
+        ensure_not_cancelled()?;
         let chunk = std::cmp::min(buffer_size as u64, remaining) as usize;
         file.read_exact(&mut buffer[..chunk]).map_err(|e| e.to_string())?;
         hasher.update(&buffer[..chunk]);
+        if let Some(md5_hasher) = md5_hasher.as_mut() {
+            md5_hasher.update(&buffer[..chunk]);
+        }
+        if let Some(sha1_hasher) = sha1_hasher.as_mut() {
+            sha1_hasher.update(&buffer[..chunk]);
+        }
         remaining -= chunk as u64;
         copied += chunk as u64;
         if copied >= next_progress || remaining == 0 {
@@ -1498,15 +4147,18 @@ fn hash_file_with_progress(path: &str, total_bytes: u64) -> Result<String, Strin
         }
     }
 
-    let hash = hasher.finalize();
-    Ok(format!("{:x}", hash))
+    Ok(FileDigests {
+        sha256: format!("{:x}", hasher.finalize()),
+        md5: md5_hasher.map(|h| format!("{:x}", h.finalize())),
+        sha1: sha1_hasher.map(|h| format!("{:x}", h.finalize())),
+    })
 }
 
 fn backup_read_to_file(
     source_device: &str,
     target_path: &str,
     total_bytes: u64,
-    compress: bool,
+    compression: ImageCompression,
 ) -> Result<(u64, String), String> {
     let mut source = open_device_for_read(source_device)?;
 
@@ -1517,10 +4169,14 @@ fn backup_read_to_file(
         .open(target_path)
         .map_err(|e| format!("Open target failed: {e}"))?;
 
-    let mut writer: Box<dyn Write> = if compress {
-        Box::new(GzEncoder::new(target_file, Compression::default()))
-    } else {
-        Box::new(target_file)
+    let mut writer: Box<dyn Write> = match compression {
+        ImageCompression::Gzip => Box::new(GzEncoder::new(target_file, Compression::default())),
+        ImageCompression::Zstd => Box::new(
+            zstd::stream::write::Encoder::new(target_file, 0)
+                .map_err(|e| format!("Zstd init failed: {e}"))?
+                .auto_finish(),
+        ),
+        _ => Box::new(target_file),
     };
 
     let buffer_size = 4 * 1024 * 1024;
@@ -1536,6 +4192,7 @@ fn backup_read_to_file(
     let mut warned = false;
 
     while remaining > 0 {
+        ensure_not_cancelled()?;
         let chunk = std::cmp::min(buffer_size as u64, remaining) as usize;
         source.read_exact(&mut buffer[..chunk]).map_err(|e| e.to_string())?;
         writer.write_all(&buffer[..chunk]).map_err(|e| e.to_string())?;
@@ -1572,43 +4229,44 @@ fn backup_read_to_file(
     Ok((copied, format!("{:x}", hash)))
 }
 
-fn hash_gzip_file_with_progress(path: &str, total_bytes: u64) -> Result<String, String> {
+fn hash_compressed_file_with_progress(
+    path: &str,
+    compression: ImageCompression,
+    total_bytes: u64,
+) -> Result<String, String> {
     if total_bytes == 0 {
         return Err("Image is empty".to_string());
     }
 
-    let file = std::fs::OpenOptions::new()
-        .read(true)
-        .open(path)
-        .map_err(|e| format!("Open image failed: {e}"))?;
-    let mut reader = GzDecoder::new(file);
-
-    let buffer_size = 4 * 1024 * 1024;
-    let mut buffer = vec![0u8; buffer_size];
-    let mut remaining = total_bytes;
-    let mut copied: u64 = 0;
-    let progress_step: u64 = 50 * 1024 * 1024;
-    let mut next_progress = progress_step;
-    let mut hasher = Sha256::new();
-
-    while remaining > 0 {
-        let chunk = std::cmp::min(buffer_size as u64, remaining) as usize;
-        let read = reader.read(&mut buffer[..chunk]).map_err(|e| e.to_string())?;
-        if read == 0 {
-            break;
-        }
-        hasher.update(&buffer[..read]);
-        remaining -= read as u64;
-        copied += read as u64;
-        if copied >= next_progress || remaining == 0 {
-            let percent = ((copied as f64 / total_bytes as f64) * 100.0).round() as u64;
-            emit_progress_bytes("backup-verify", percent, 100, Some("Verifying backup"), copied, total_bytes);
-            next_progress += progress_step;
+    with_image_reader(path, compression, |reader| {
+        let buffer_size = 4 * 1024 * 1024;
+        let mut buffer = vec![0u8; buffer_size];
+        let mut remaining = total_bytes;
+        let mut copied: u64 = 0;
+        let progress_step: u64 = 50 * 1024 * 1024;
+        let mut next_progress = progress_step;
+        let mut hasher = Sha256::new();
+
+        while remaining > 0 {
+            ensure_not_cancelled()?;
+            let chunk = std::cmp::min(buffer_size as u64, remaining) as usize;
+            let read = reader.read(&mut buffer[..chunk]).map_err(|e| e.to_string())?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+            remaining -= read as u64;
+            copied += read as u64;
+            if copied >= next_progress || remaining == 0 {
+                let percent = ((copied as f64 / total_bytes as f64) * 100.0).round() as u64;
+                emit_progress_bytes("backup-verify", percent, 100, Some("Verifying backup"), copied, total_bytes);
+                next_progress += progress_step;
+            }
         }
-    }
 
-    let hash = hasher.finalize();
-    Ok(format!("{:x}", hash))
+        let hash = hasher.finalize();
+        Ok(format!("{:x}", hash))
+    })
 }
 
 fn detect_windows_iso(path: &str) -> Result<(bool, Option<String>), String> {
@@ -1652,6 +4310,389 @@ fn contains_bytes(haystack: &[u8], needle: &[u8]) -> bool {
     haystack.windows(needle.len()).any(|window| window == needle)
 }
 
+const IMAGE_HEADER_BYTES: usize = 1024 * 1024;
+// Deckt alle Superblock-Offsets in detect_fs_magic ab (btrfs liegt bei 64 KiB).
+const IMAGE_PROBE_BYTES: usize = 72 * 1024;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ImagePartition {
+    index: u32,
+    offset_bytes: u64,
+    size_bytes: u64,
+    type_guid: Option<String>,
+    mbr_type: Option<String>,
+    name: Option<String>,
+    filesystem: Option<String>,
+}
+
+struct ImageLayout {
+    scheme: &'static str,
+    sector_size: Option<u64>,
+    filesystem: Option<String>,
+    partitions: Vec<ImagePartition>,
+}
+
+// Liest die Partitionstabelle eines Images (MBR/GPT) und erkennt Dateisysteme per Magic Bytes.
+fn inspect_image_layout(path: &str, compression: ImageCompression) -> Result<ImageLayout, String> {
+    let header = read_image_regions(path, compression, &[(0, IMAGE_HEADER_BYTES)])?
+        .pop()
+        .unwrap_or_default();
+
+    let mut layout = parse_gpt_layout(&header)?
+        .or_else(|| parse_mbr_layout(&header))
+        .unwrap_or(ImageLayout {
+            scheme: "none",
+            sector_size: None,
+            filesystem: None,
+            partitions: Vec::new(),
+        });
+
+    let mut regions = vec![(0u64, IMAGE_PROBE_BYTES)];
+    regions.extend(layout.partitions.iter().map(|part| (part.offset_bytes, IMAGE_PROBE_BYTES)));
+    let probes = read_image_regions(path, compression, &regions)?;
+
+    if layout.scheme == "none" {
+        layout.filesystem = probes.first().and_then(|buf| detect_fs_magic(buf)).map(|fs| fs.to_string());
+    }
+    for (part, probe) in layout.partitions.iter_mut().zip(probes.iter().skip(1)) {
+        part.filesystem = detect_fs_magic(probe).map(|fs| fs.to_string());
+    }
+
+    Ok(layout)
+}
+
+// Liest mehrere Bereiche aus dem (ggf. entpackten) Image. Rohe Images werden per Seek
+// gelesen, komprimierte Streams nur vorwaerts; Bereiche hinter dem Ende bleiben kurz.
+fn read_image_regions(
+    path: &str,
+    compression: ImageCompression,
+    regions: &[(u64, usize)],
+) -> Result<Vec<Vec<u8>>, String> {
+    let mut order = (0..regions.len()).collect::<Vec<_>>();
+    order.sort_by_key(|idx| regions[*idx].0);
+    let mut results = vec![Vec::new(); regions.len()];
+
+    if compression == ImageCompression::None {
+        let mut file = std::fs::File::open(path).map_err(|e| format!("Open image failed: {e}"))?;
+        for idx in order {
+            let (offset, len) = regions[idx];
+            file.seek(SeekFrom::Start(offset)).map_err(|e| format!("Image read failed: {e}"))?;
+            let mut buffer = vec![0u8; len];
+            let read = read_full(&mut file, &mut buffer)?;
+            buffer.truncate(read);
+            results[idx] = buffer;
+        }
+        return Ok(results);
+    }
+
+    with_image_reader(path, compression, |source| {
+        // Der Stream-Puffer haelt den letzten gelesenen Block, damit sich Bereiche ueberlappen duerfen.
+        let mut window: Vec<u8> = Vec::new();
+        let mut window_start: u64 = 0;
+        for idx in order {
+            let (offset, len) = regions[idx];
+            let window_end = window_start + window.len() as u64;
+            if offset < window_start || offset + len as u64 > window_end {
+                if offset > window_end {
+                    let skip = offset - window_end;
+                    let skipped = std::io::copy(&mut source.take(skip), &mut std::io::sink())
+                        .map_err(|e| format!("Image read failed: {e}"))?;
+                    if skipped < skip {
+                        break;
+                    }
+                    window.clear();
+                    window_start = offset;
+                } else if offset >= window_start {
+                    window.drain(..(offset - window_start) as usize);
+                    window_start = offset;
+                } else {
+                    return Err("Image regions out of order".to_string());
+                }
+                let missing = (offset + len as u64 - window_start) as usize - window.len();
+                let mut buffer = vec![0u8; missing];
+                let read = read_full(source, &mut buffer)?;
+                window.extend_from_slice(&buffer[..read]);
+            }
+            let start = (offset - window_start) as usize;
+            let end = std::cmp::min(start + len, window.len());
+            results[idx] = window.get(start..end).map(|s| s.to_vec()).unwrap_or_default();
+        }
+        Ok(results)
+    })
+}
+
+fn parse_mbr_layout(header: &[u8]) -> Option<ImageLayout> {
+    if header.len() < 512 || header[510] != 0x55 || header[511] != 0xAA {
+        return None;
+    }
+    let mut partitions = Vec::new();
+    for slot in 0..4u32 {
+        let entry = &header[446 + slot as usize * 16..446 + (slot as usize + 1) * 16];
+        let part_type = entry[4];
+        let start = u32::from_le_bytes([entry[8], entry[9], entry[10], entry[11]]) as u64;
+        let count = u32::from_le_bytes([entry[12], entry[13], entry[14], entry[15]]) as u64;
+        if part_type == 0 || count == 0 {
+            continue;
+        }
+        partitions.push(ImagePartition {
+            index: slot + 1,
+            offset_bytes: start * 512,
+            size_bytes: count * 512,
+            type_guid: None,
+            mbr_type: Some(format!("0x{:02X}", part_type)),
+            name: None,
+            filesystem: None,
+        });
+    }
+    // Ein leerer Bootsektor mit 55AA (z. B. FAT-Superfloppy) ist keine Partitionstabelle.
+    if partitions.is_empty() {
+        return None;
+    }
+    Some(ImageLayout {
+        scheme: "mbr",
+        sector_size: Some(512),
+        filesystem: None,
+        partitions,
+    })
+}
+
+// Header-Felder stammen aus einer beliebigen Image-Datei; alle Offsets werden geprueft
+// berechnet, damit ein manipuliertes Image weder ueberlaeuft noch riesige Bereiche anfordert.
+// Begrenzt wird die Tabellengroesse, nicht die Eintragszahl: gueltige GPTs duerfen mehr als
+// 128 Eintraege haben.
+const GPT_MAX_TABLE_BYTES: usize = 1024 * 1024;
+
+fn parse_gpt_layout(header: &[u8]) -> Result<Option<ImageLayout>, String> {
+    let Some(sector_size) = [512u64, 4096]
+        .into_iter()
+        .find(|size| header.get(*size as usize..*size as usize + 8) == Some(b"EFI PART".as_slice()))
+    else {
+        return Ok(None);
+    };
+    let gpt = &header[sector_size as usize..];
+    let read_u32 = |buf: &[u8], at: usize| u32::from_le_bytes(buf[at..at + 4].try_into().unwrap_or([0; 4]));
+    let read_u64 = |buf: &[u8], at: usize| u64::from_le_bytes(buf[at..at + 8].try_into().unwrap_or([0; 8]));
+    let overflow = || coded("INVALID_IMAGE", "GPT header values overflow");
+
+    let entries_lba = read_u64(gpt, 72);
+    let entry_count = read_u32(gpt, 80) as usize;
+    let entry_size = read_u32(gpt, 84) as usize;
+    // Laut Spezifikation 128 * 2^n Bytes
+    if entry_size < 128 || !entry_size.is_power_of_two() {
+        return Err(coded("INVALID_IMAGE", format!("Invalid GPT entry size: {entry_size}")));
+    }
+    let table_bytes = entry_count.checked_mul(entry_size).ok_or_else(overflow)?;
+    if table_bytes > GPT_MAX_TABLE_BYTES {
+        return Err(coded(
+            "INVALID_IMAGE",
+            format!("GPT partition table of {table_bytes} bytes exceeds {GPT_MAX_TABLE_BYTES}"),
+        ));
+    }
+    let table_start = entries_lba
+        .checked_mul(sector_size)
+        .and_then(|start| usize::try_from(start).ok())
+        .ok_or_else(overflow)?;
+
+    let mut partitions = Vec::new();
+    for idx in 0..entry_count {
+        let start = table_start.checked_add(idx * entry_size).ok_or_else(overflow)?;
+        let end = start.checked_add(entry_size).ok_or_else(overflow)?;
+        let Some(entry) = header.get(start..end) else {
+            break;
+        };
+        if entry[..16].iter().all(|b| *b == 0) {
+            continue;
+        }
+        let first_lba = read_u64(entry, 32);
+        let last_lba = read_u64(entry, 40);
+        let name_units = entry[56..128]
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .take_while(|unit| *unit != 0)
+            .collect::<Vec<_>>();
+        let name = String::from_utf16_lossy(&name_units);
+        let offset_bytes = first_lba.checked_mul(sector_size).ok_or_else(overflow)?;
+        let size_bytes = last_lba
+            .saturating_sub(first_lba)
+            .checked_add(1)
+            .and_then(|sectors| sectors.checked_mul(sector_size))
+            .ok_or_else(overflow)?;
+        partitions.push(ImagePartition {
+            index: idx as u32 + 1,
+            offset_bytes,
+            size_bytes,
+            type_guid: Some(format_mixed_endian_guid(&entry[..16])),
+            mbr_type: None,
+            name: if name.is_empty() { None } else { Some(name) },
+            filesystem: None,
+        });
+    }
+
+    Ok(Some(ImageLayout {
+        scheme: "gpt",
+        sector_size: Some(sector_size),
+        filesystem: None,
+        partitions,
+    }))
+}
+
+fn format_mixed_endian_guid(raw: &[u8]) -> String {
+    format!(
+        "{:02X}{:02X}{:02X}{:02X}-{:02X}{:02X}-{:02X}{:02X}-{:02X}{:02X}-{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}",
+        raw[3], raw[2], raw[1], raw[0], raw[5], raw[4], raw[7], raw[6],
+        raw[8], raw[9], raw[10], raw[11], raw[12], raw[13], raw[14], raw[15]
+    )
+}
+
+fn detect_fs_magic(buf: &[u8]) -> Option<&'static str> {
+    let at = |offset: usize, magic: &[u8]| buf.get(offset..offset + magic.len()) == Some(magic);
+
+    if at(0, b"LUKS\xba\xbe") {
+        return Some("luks");
+    }
+    if at(0, b"XFSB") {
+        return Some("xfs");
+    }
+    if at(0, b"hsqs") {
+        return Some("squashfs");
+    }
+    if at(32, b"NXSB") {
+        return Some("apfs");
+    }
+    if at(3, b"NTFS    ") {
+        return Some("ntfs");
+    }
+    if at(3, b"EXFAT   ") {
+        return Some("exfat");
+    }
+    if at(82, b"FAT32   ") {
+        return Some("fat32");
+    }
+    if at(54, b"FAT16   ") || at(54, b"FAT12   ") {
+        return Some("fat");
+    }
+    if at(1024, b"H+") || at(1024, b"HX") {
+        return Some("hfs+");
+    }
+    if at(1024, &[0x10, 0x20, 0xF5, 0xF2]) {
+        return Some("f2fs");
+    }
+    if at(0x438, &[0x53, 0xEF]) {
+        let read_u32 = |offset: usize| {
+            buf.get(offset..offset + 4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .unwrap_or(0)
+        };
+        // Extents (INCOMPAT 0x40) => ext4, Journal (COMPAT 0x4) => ext3, sonst ext2.
+        if read_u32(0x460) & 0x40 != 0 {
+            return Some("ext4");
+        }
+        if read_u32(0x45C) & 0x4 != 0 {
+            return Some("ext3");
+        }
+        return Some("ext2");
+    }
+    if at(0x8001, b"CD001") {
+        return Some("iso9660");
+    }
+    if at(4086, b"SWAPSPACE2") {
+        return Some("swap");
+    }
+    if at(0x10040, b"_BHRfS_M") {
+        return Some("btrfs");
+    }
+    None
+}
+
+// Vorschlag fuer /etc/fstab: Typ und UUID kommen aus dem Superblock (wie blkid), weil
+// diskutil Linux-Dateisysteme nicht kennt; Optionen liefert der Treiber.
+fn handle_fstab_entry(payload: &Value) -> Result<Option<Value>, String> {
+    let partition_identifier = read_device_identifier(payload, "partitionIdentifier")?;
+    let device = normalize_device(&partition_identifier);
+
+    let mut header = vec![0u8; 128 * 1024];
+    {
+        let mut source = open_device_for_read(&raw_device_path(&device))?;
+        let read = read_full(&mut source, &mut header)?;
+        header.truncate(read);
+    }
+    let magic = detect_fs_magic(&header);
+    let fs = match magic {
+        Some("fat") => "fat32".to_string(),
+        Some(fs) => fs.to_string(),
+        None => detect_fs_type(&device)?,
+    };
+    let driver = driver_for(&fs).ok_or_else(|| format!("Unsupported filesystem for fstab: {fs}"))?;
+    let spec = driver
+        .fstab_spec()
+        .ok_or_else(|| format!("{fs} is not mounted via /etc/fstab"))?;
+    // FAT12/16 laufen ueber den fat32-Treiber, die Seriennummer liegt aber an anderer Stelle
+    let uuid = fs_uuid_from_superblock(magic.unwrap_or(&fs), &header)
+        .ok_or_else(|| format!("Could not read filesystem UUID of {device}"))?;
+
+    let mount_point = if fs == "swap" {
+        "none".to_string()
+    } else {
+        let label = disk_info_dict(&device)
+            .ok()
+            .and_then(|info| info.get("VolumeName").and_then(|v| v.as_string()).map(|s| s.to_string()))
+            .map(|name| name.trim().replace('/', "_"))
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| uuid.chars().take(8).collect());
+        // Leerzeichen muessen in fstab oktal maskiert werden
+        format!("/mnt/{}", label.replace(' ', "\\040"))
+    };
+    let line = format!("UUID={uuid}  {mount_point}  {}  {}  0  {}", spec.fs_type, spec.options, spec.pass);
+
+    Ok(Some(json!({
+        "device": device,
+        "fs": fs,
+        "uuid": uuid,
+        "mountPoint": mount_point,
+        "fsType": spec.fs_type,
+        "options": spec.options,
+        "pass": spec.pass,
+        "line": line,
+    })))
+}
+
+// UUID im Format von blkid; buf muss die ersten 128 KiB des Dateisystems enthalten
+fn fs_uuid_from_superblock(fs: &str, buf: &[u8]) -> Option<String> {
+    let bytes = |offset: usize, len: usize| buf.get(offset..offset + len);
+    let uuid = |offset: usize| {
+        let b = bytes(offset, 16)?;
+        if b.iter().all(|byte| *byte == 0) {
+            return None;
+        }
+        let hex: String = b.iter().map(|byte| format!("{byte:02x}")).collect();
+        Some(format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32]))
+    };
+    // FAT/exFAT: 32-Bit-Seriennummer als XXXX-XXXX
+    let serial32 = |offset: usize| {
+        let b = bytes(offset, 4)?;
+        let value = u32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+        Some(format!("{:04X}-{:04X}", value >> 16, value & 0xFFFF))
+    };
+
+    match fs {
+        "ext4" | "ext3" | "ext2" => uuid(1024 + 0x68),
+        "btrfs" => uuid(0x10000 + 0x20),
+        "xfs" => uuid(32),
+        "f2fs" => uuid(1024 + 0x6C),
+        "swap" => uuid(1024 + 12),
+        "ntfs" => {
+            let b = bytes(0x48, 8)?;
+            let value = u64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]);
+            Some(format!("{value:016X}"))
+        }
+        "fat32" => serial32(0x43),
+        "fat" => serial32(0x27),
+        "exfat" => serial32(0x64),
+        _ => None,
+    }
+}
+
 fn detect_image_brand(path: &str, is_windows: bool) -> Result<(Option<String>, Option<String>), String> {
     if is_windows {
         return Ok((Some("windows".to_string()), Some("Windows".to_string())));
@@ -1897,7 +4938,10 @@ fn directory_size(path: &str) -> Result<u64, String> {
     Ok(total)
 }
 
-fn copy_dir_with_progress(source: &str, destination: &str, total_bytes: u64) -> Result<(), String> {
+const FAT32_MAX_FILE_SIZE: u64 = 4 * 1024 * 1024 * 1024 - 1;
+
+// skip: relative Pfade (ab source), die nicht kopiert werden sollen.
+fn copy_dir_with_progress(source: &str, destination: &str, total_bytes: u64, skip: &[&str]) -> Result<(), String> {
     let mut copied: u64 = 0;
     let progress_step: u64 = 50 * 1024 * 1024;
     let mut next_progress = progress_step;
@@ -1906,6 +4950,7 @@ fn copy_dir_with_progress(source: &str, destination: &str, total_bytes: u64) ->
         destination,
         source,
         total_bytes,
+        skip,
         &mut copied,
         &mut next_progress,
     )?;
@@ -1918,6 +4963,7 @@ fn copy_dir_inner(
     destination: &str,
     base_root: &str,
     total_bytes: u64,
+    skip: &[&str],
     copied: &mut u64,
     next_progress: &mut u64,
 ) -> Result<(), String> {
@@ -1939,6 +4985,7 @@ fn copy_dir_inner(
                 target_path.to_str().unwrap_or(""),
                 base_root,
                 total_bytes,
+                skip,
                 copied,
                 next_progress,
             )?;
@@ -1949,6 +4996,9 @@ fn copy_dir_inner(
                 .to_string_lossy()
                 .trim_start_matches('/')
                 .to_string();
+            if skip.iter().any(|path| relative.eq_ignore_ascii_case(path)) {
+                continue;
+            }
             copy_file_with_progress(
                 source_path.to_str().unwrap_or(""),
                 target_path.to_str().unwrap_or(""),
@@ -1988,6 +5038,7 @@ fn copy_file_with_progress(
     let start = Instant::now();
     let mut file_copied: u64 = 0;
     loop {
+        ensure_not_cancelled()?;
         let read = reader.read(&mut buffer).map_err(|e| e.to_string())?;
         if read == 0 {
             break;
@@ -2011,6 +5062,56 @@ fn copy_file_with_progress(
     Ok(())
 }
 
+fn filesystem_max_file_size(fs: &str) -> Option<u64> {
+    match fs {
+        "fat32" => Some(FAT32_MAX_FILE_SIZE),
+        _ => None,
+    }
+}
+
+// Groesste Datei unter path (oder path selbst, wenn es eine Datei ist).
+fn largest_file(path: &std::path::Path) -> Option<(String, u64)> {
+    let meta = std::fs::metadata(path).ok()?;
+    if meta.is_file() {
+        return Some((path.display().to_string(), meta.len()));
+    }
+    if !meta.is_dir() {
+        return None;
+    }
+
+    let mut largest: Option<(String, u64)> = None;
+    for entry in std::fs::read_dir(path).ok()?.flatten() {
+        let name = entry.file_name();
+        if should_skip_entry(&name.to_string_lossy()) {
+            continue;
+        }
+        let is_symlink = entry.file_type().map(|t| t.is_symlink()).unwrap_or(true);
+        if is_symlink {
+            continue;
+        }
+        if let Some(candidate) = largest_file(&entry.path()) {
+            if largest.as_ref().map(|(_, size)| candidate.1 > *size).unwrap_or(true) {
+                largest = Some(candidate);
+            }
+        }
+    }
+    largest
+}
+
+// Groesse von sources/install.wim; der ISO9660-Baum von Windows-ISOs enthaelt sie
+// meist nicht (nur UDF), daher kurz read-only mounten.
+fn windows_iso_wim_size(path: &str) -> Option<u64> {
+    let mount_point = "/tmp/oxidisk_preflight_iso";
+    if mount_iso_at(path, mount_point).is_err() {
+        return None;
+    }
+    let size = std::fs::metadata(std::path::Path::new(mount_point).join("sources").join("install.wim"))
+        .map(|meta| meta.len())
+        .ok();
+    let _ = run_hdiutil(["detach", mount_point]);
+    size
+}
+
 fn should_skip_entry(name: &str) -> bool {
     name == ".DS_Store"
 }
@@ -2077,7 +5178,13 @@ fn build_autounattend_xml(tpm_bypass: bool, local_account: bool, privacy_default
     )
 }
 
-fn create_linux_partition(device: &str, fs: &str, label: &str, size: &str) -> Result<Option<Value>, String> {
+fn create_linux_partition(
+    device: &str,
+    fs: &str,
+    label: &str,
+    size: &str,
+    opts: &MkfsOptions,
+) -> Result<Option<Value>, String> {
     let temp_label = format!("OXI_TMP_{}", current_timestamp());
     run_diskutil(["addPartition", device, "MS-DOS", &temp_label, size])?;
 
@@ -2088,7 +5195,7 @@ fn create_linux_partition(device: &str, fs: &str, label: &str, size: &str) -> Re
     run_diskutil(["unmount", &new_device])?;
 
     if let Some(driver) = driver_for(fs) {
-        if let Some((bin, args)) = driver.mkfs_command(&new_device, label) {
+        if let Some((bin, args)) = driver.mkfs_command_with_opts(&new_device, label, opts) {
             run_sidecar_stream(&bin, args)?;
         } else {
             return Err("Unsupported filesystem".to_string());
@@ -2102,13 +5209,156 @@ fn create_linux_partition(device: &str, fs: &str, label: &str, size: &str) -> Re
     Ok(Some(json!({ "device": device, "partition": new_device, "format": fs, "size": size, "warning": warning })))
 }
 
+// Legt die Partition per sgdisk an einem festen Offset an (statt diskutil addPartition,
+// das immer den ersten freien Bereich nimmt) und formatiert sie mit dem passenden Treiber.
+fn create_partition_at(
+    disk: &str,
+    fs: &str,
+    label: &str,
+    start: &str,
+    size: &str,
+    opts: &MkfsOptions,
+) -> Result<Option<Value>, String> {
+    if find_sidecar("sgdisk").is_err() {
+        return Err("sgdisk is required to create a partition at a specific start".to_string());
+    }
+    let driver = driver_for(fs).ok_or_else(|| format!("Unsupported format type for start offset: {fs}"))?;
+    // sgdisk muss auf die ganze Disk zeigen und wuerde eine MBR-Tabelle stillschweigend
+    // als GPT neu schreiben; die Endreserve unten setzt ebenfalls die GPT-Sicherung voraus.
+    if !is_whole_disk_identifier(disk) {
+        return Err(coded("INVALID_IDENTIFIER", "A start offset requires a whole-disk identifier"));
+    }
+    let info = disk_info_dict(disk)?;
+    if info.get("Content").and_then(|v| v.as_string()) != Some("GUID_partition_scheme") {
+        return Err(coded("UNSUPPORTED_SCHEME", "A start offset is only supported on GPT disks"));
+    }
+
+    let mib = 1024 * 1024;
+    let (disk_size, block_size) = disk_geometry(disk)?;
+    let start_bytes = align_mib(parse_size_bytes(start)?, block_size);
+    let size_bytes = align_mib(parse_size_bytes(size)?, block_size);
+    if size_bytes == 0 {
+        return Err("Invalid size".to_string());
+    }
+    if start_bytes < mib {
+        return Err("Start must be at least 1 MiB into the disk".to_string());
+    }
+    let end_bytes = start_bytes + size_bytes;
+
+    // Platz für die GPT-Sicherung am Ende der Disk lassen
+    if end_bytes > disk_size.saturating_sub(mib) {
+        return Err("Requested partition exceeds the end of the disk".to_string());
+    }
+
+    let mut used_numbers = Vec::new();
+    for (identifier, offset, part_size) in partition_extents(disk)? {
+        if let Some(number) = partition_number(&identifier) {
+            used_numbers.push(number);
+        }
+        if start_bytes < offset + part_size && end_bytes > offset {
+            return Err(format!(
+                "Requested range overlaps existing partition {identifier} ({offset}..{})",
+                offset + part_size
+            ));
+        }
+    }
+    let part_number = (1..=128)
+        .find(|number| !used_numbers.contains(number))
+        .ok_or_else(|| "No free partition slot".to_string())?;
+
+    let (start_sector, end_sector) = sector_range(start_bytes, end_bytes, block_size)?;
+    let table_log = run_sidecar_capture(
+        "sgdisk",
+        [&format!("--new={part_number}:{start_sector}:{end_sector}"), disk],
+    )?;
+
+    let new_device = format!("{disk}s{part_number}");
+    sync_kernel_table(&new_device);
+
+    let (bin, args) = driver
+        .mkfs_command_with_opts(&new_device, label, opts)
+        .ok_or_else(|| "Unsupported filesystem".to_string())?;
+    run_sidecar_stream(&bin, args)?;
+
+    let warning = set_partition_typecode(&new_device, fs)?;
+
+    Ok(Some(json!({
+        "device": disk,
+        "partition": new_device,
+        "format": fs,
+        "start": start_bytes,
+        "size": size,
+        "output": table_log,
+        "warning": warning,
+    })))
+}
+
+fn disk_geometry(disk: &str) -> Result<(u64, u64), String> {
+    let output = Command::new("diskutil")
+        .args(["info", "-plist", disk])
+        .output()
+        .map_err(|e| format!("diskutil failed: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("diskutil error: {stderr}"));
+    }
+
+    let plist = PlistValue::from_reader_xml(&output.stdout[..]).map_err(|e| e.to_string())?;
+    let dict = plist
+        .as_dictionary()
+        .ok_or_else(|| "Invalid plist".to_string())?;
+    let disk_size = dict
+        .get("TotalSize")
+        .and_then(|v| v.as_unsigned_integer())
+        .or_else(|| dict.get("DiskSize").and_then(|v| v.as_unsigned_integer()))
+        .ok_or_else(|| "Disk size missing".to_string())?;
+    let block_size = dict
+        .get("DeviceBlockSize")
+        .and_then(|v| v.as_unsigned_integer())
+        .unwrap_or(512);
+    Ok((disk_size, block_size))
+}
+
+// (Identifier, Offset, Größe) aller Partitionen einer Disk
+fn partition_extents(disk: &str) -> Result<Vec<(String, u64, u64)>, String> {
+    let mut extents = Vec::new();
+    for part_id in list_disk_partitions(disk)? {
+        let output = Command::new("diskutil")
+            .args(["info", "-plist", &format!("/dev/{part_id}")])
+            .output()
+            .map_err(|e| format!("diskutil failed: {e}"))?;
+        if !output.status.success() {
+            continue;
+        }
+        let plist = match PlistValue::from_reader_xml(&output.stdout[..]) {
+            Ok(p) => p,
+            Err(_) => continue,
+        };
+        let dict = match plist.as_dictionary() {
+            Some(d) => d,
+            None => continue,
+        };
+        let offset = dict.get("PartitionOffset").and_then(|v| v.as_unsigned_integer());
+        let size = dict.get("PartitionSize").and_then(|v| v.as_unsigned_integer());
+        if let (Some(offset), Some(size)) = (offset, size) {
+            extents.push((part_id, offset, size));
+        }
+    }
+    Ok(extents)
+}
+
 fn wipe_linux_device(device: &str, scheme: &str, fs: &str, label: &str) -> Result<Option<Value>, String> {
     let temp_label = format!("OXI_TMP_{}", current_timestamp());
     run_diskutil(["eraseDisk", "MS-DOS", &temp_label, scheme, device])?;
 
-    let new_partition = find_partition_by_label(&temp_label)?
-        .ok_or_else(|| "Failed to locate new partition".to_string())?;
-    let new_device = normalize_device(&new_partition);
+    // Im Dry-Run existiert die neue Partition nicht; eraseDisk legt sie als s1 bzw. s2 (GPT mit EFI) an.
+    let new_device = if is_dry_run() {
+        format!("{device}s{}", if scheme == "GPT" { 2 } else { 1 })
+    } else {
+        let new_partition = find_partition_by_label(&temp_label)?
+            .ok_or_else(|| "Failed to locate new partition".to_string())?;
+        normalize_device(&new_partition)
+    };
 
     run_diskutil(["unmount", &new_device])?;
 
@@ -2127,11 +5377,11 @@ fn wipe_linux_device(device: &str, scheme: &str, fs: &str, label: &str) -> Resul
     Ok(Some(json!({ "device": device, "partition": new_device, "format": fs, "scheme": scheme, "warning": warning })))
 }
 
-fn format_linux_partition(device: &str, fs: &str, label: &str) -> Result<Option<Value>, String> {
+fn format_linux_partition(device: &str, fs: &str, label: &str, opts: &MkfsOptions) -> Result<Option<Value>, String> {
     run_diskutil(["unmount", "force", device])?;
 
     if let Some(driver) = driver_for(fs) {
-        if let Some((bin, args)) = driver.mkfs_command(device, label) {
+        if let Some((bin, args)) = driver.mkfs_command_with_opts(device, label, opts) {
             run_sidecar_stream(&bin, args)?;
         } else {
             return Err("Unsupported filesystem".to_string());
@@ -2159,6 +5409,8 @@ fn set_partition_typecode(partition: &str, fs: &str) -> Result<Option<String>, S
         "ext4" | "btrfs" | "xfs" | "f2fs" => "8300",
         "ntfs" => "0700",
         "swap" => "8200",
+        "zfs" => "BF01",
+        "exfat" | "fat32" => "0700",
         _ => return Ok(None),
     };
 
@@ -2199,9 +5451,41 @@ fn parse_size_bytes(value: &str) -> Result<u64, String> {
     Ok((number * multiplier).floor() as u64)
 }
 
-fn align_mib(value: u64) -> u64 {
-    let mib = 1024 * 1024;
-    value / mib * mib
+// Auf 1 MiB abrunden; teilt die Blockgroesse 1 MiB nicht, auf das kgV beider
+fn align_mib(value: u64, block_size: u64) -> u64 {
+    let unit = alignment_unit(block_size);
+    value / unit * unit
+}
+
+// Ausrichtung fuer Create/Resize/Move: 1 MiB, bei Bloecken, die 1 MiB nicht teilen,
+// das kgV beider Groessen (sonst landet der Start bei 512e/4Kn mitten in einem Block)
+fn alignment_unit(block_size: u64) -> u64 {
+    let mib: u64 = 1024 * 1024;
+    if block_size == 0 {
+        return mib;
+    }
+    let (mut a, mut b) = (mib, block_size);
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    mib / a * block_size
+}
+
+// Erster und letzter Sektor fuer sgdisk --new. Der Start muss auf einer Blockgrenze liegen,
+// das Ende wird auf die letzte volle Blockgrenze abgerundet (kein halber Sektor am Ende).
+fn sector_range(start_bytes: u64, end_bytes: u64, block_size: u64) -> Result<(u64, u64), String> {
+    if block_size == 0 {
+        return Err("Invalid block size".to_string());
+    }
+    let start_sector = start_bytes / block_size;
+    if start_sector * block_size != start_bytes {
+        return Err(format!("Start {start_bytes} is not aligned to the {block_size}-byte block size"));
+    }
+    let end_blocks = end_bytes / block_size;
+    if end_blocks <= start_sector {
+        return Err("Partition end lies before its start".to_string());
+    }
+    Ok((start_sector, end_blocks - 1))
 }
 
 #[derive(Clone)]
@@ -2211,21 +5495,21 @@ struct PartitionInfo {
     partition_offset: u64,
     partition_size: u64,
     block_size: u64,
+    physical_block_size: u64,
     min_start: u64,
     max_end: u64,
 }
 
 fn read_partition_info(device: &str) -> Result<PartitionInfo, String> {
-    let output = Command::new("diskutil")
-        .args(["info", "-plist", device])
-        .output()
-        .map_err(|e| format!("diskutil failed: {e}"))?;
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("diskutil error: {stderr}"));
-    }
+    let plist = diskutil_plist(["info", "-plist", device])?;
+    let mut info = parse_partition_info(&plist)?;
+    info.max_end = disk_max_end(&info.disk, &info.device)?;
+    Ok(info)
+}
 
-    let plist = PlistValue::from_reader_xml(&output.stdout[..]).map_err(|e| e.to_string())?;
+// max_end ist hier nur das aktuelle Partitionsende; read_partition_info ersetzt es
+// durch den Start der naechsten Partition bzw. das Disk-Ende.
+fn parse_partition_info(plist: &PlistValue) -> Result<PartitionInfo, String> {
     let dict = plist
         .as_dictionary()
         .ok_or_else(|| "Invalid plist".to_string())?;
@@ -2238,10 +5522,7 @@ fn read_partition_info(device: &str) -> Result<PartitionInfo, String> {
         .get("PartitionSize")
         .and_then(|v| v.as_unsigned_integer())
         .ok_or_else(|| "PartitionSize missing".to_string())?;
-    let block_size = dict
-        .get("DeviceBlockSize")
-        .and_then(|v| v.as_unsigned_integer())
-        .unwrap_or(512);
+    let (block_size, physical_block_size) = device_block_sizes(dict);
     let disk = dict
         .get("ParentWholeDisk")
         .and_then(|v| v.as_string())
@@ -2254,18 +5535,34 @@ fn read_partition_info(device: &str) -> Result<PartitionInfo, String> {
         .map(|s| format!("/dev/{s}"))
         .ok_or_else(|| "DeviceIdentifier missing".to_string())?;
 
-    let max_end = disk_max_end(&disk, &device_id)?;
     Ok(PartitionInfo {
         device: device_id,
         disk,
         partition_offset,
         partition_size,
         block_size,
+        physical_block_size,
         min_start: partition_offset,
-        max_end,
+        max_end: partition_offset + partition_size,
     })
 }
 
+fn diskutil_plist<I, S>(args: I) -> Result<PlistValue, String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<std::ffi::OsStr>,
+{
+    let output = Command::new("diskutil")
+        .args(args)
+        .output()
+        .map_err(|e| format!("diskutil failed: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("diskutil error: {stderr}"));
+    }
+    PlistValue::from_reader_xml(&output.stdout[..]).map_err(|e| e.to_string())
+}
+
 fn disk_max_end(disk: &str, device: &str) -> Result<u64, String> {
     let output = Command::new("diskutil")
         .args(["info", "-plist", disk])
@@ -2322,47 +5619,52 @@ fn disk_max_end(disk: &str, device: &str) -> Result<u64, String> {
 }
 
 fn list_disk_partitions(disk: &str) -> Result<Vec<String>, String> {
-    let output = Command::new("diskutil")
-        .args(["list", "-plist", disk])
-        .output()
-        .map_err(|e| format!("diskutil failed: {e}"))?;
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("diskutil error: {stderr}"));
-    }
+    let plist = diskutil_plist(["list", "-plist", disk])?;
+    parse_partition_identifiers(&plist)
+}
 
-    let plist = PlistValue::from_reader_xml(&output.stdout[..]).map_err(|e| e.to_string())?;
+// "diskutil list -plist <disk>" liefert die Partitionen unter AllDisksAndPartitions;
+// aeltere Ausgaben bzw. Einzel-Disk-Abfragen hatten sie auf oberster Ebene.
+fn parse_partition_identifiers(plist: &PlistValue) -> Result<Vec<String>, String> {
     let dict = plist
         .as_dictionary()
         .ok_or_else(|| "Invalid plist".to_string())?;
-    let partitions = match dict.get("Partitions") {
-        Some(PlistValue::Array(parts)) => parts,
-        _ => return Ok(Vec::new()),
-    };
 
     let mut identifiers = Vec::new();
-    for part in partitions {
-        if let Some(part_dict) = part.as_dictionary() {
-            if let Some(id) = part_dict
-                .get("DeviceIdentifier")
-                .and_then(|v| v.as_string())
-            {
-                identifiers.push(id.to_string());
-            }
+    for part_dict in disk_partition_dicts(dict) {
+        if let Some(id) = part_dict
+            .get("DeviceIdentifier")
+            .and_then(|v| v.as_string())
+        {
+            identifiers.push(id.to_string());
         }
     }
 
     Ok(identifiers)
 }
 
+fn disk_partition_dicts(dict: &plist::Dictionary) -> Vec<&plist::Dictionary> {
+    let mut parts: Vec<&PlistValue> = Vec::new();
+    if let Some(PlistValue::Array(arr)) = dict.get("Partitions") {
+        parts.extend(arr.iter());
+    } else if let Some(PlistValue::Array(disks)) = dict.get("AllDisksAndPartitions") {
+        for disk in disks {
+            if let Some(PlistValue::Array(arr)) = disk.as_dictionary().and_then(|d| d.get("Partitions")) {
+                parts.extend(arr.iter());
+            }
+        }
+    }
+    parts.into_iter().filter_map(|part| part.as_dictionary()).collect()
+}
+
 fn resize_linux_partition(device: &str, fs: &str, new_size: &str) -> Result<Option<Value>, String> {
     if find_sidecar("sgdisk").is_err() {
-        return Err("sgdisk is required for ext4/ntfs resize".to_string());
+        return Err("sgdisk is required for ext4/ntfs/FAT/btrfs/xfs resize".to_string());
     }
 
     let new_size_bytes = parse_size_bytes(new_size)?;
     let info = read_partition_info(device)?;
-    let aligned_size = align_mib(new_size_bytes);
+    let aligned_size = align_mib(new_size_bytes, info.physical_block_size.max(info.block_size));
     if aligned_size == 0 {
         return Err("Invalid size".to_string());
     }
@@ -2374,19 +5676,33 @@ fn resize_linux_partition(device: &str, fs: &str, new_size: &str) -> Result<Opti
     if new_end > info.max_end {
         return Err("New size exceeds available space".to_string());
     }
+    if fs == "xfs" && new_end < current_end {
+        return Err("XFS cannot be shrunk; only growing is supported".to_string());
+    }
 
     let mut output_log = String::new();
+    let mut fs_log = String::new();
     if new_end < current_end {
         emit_progress("resize", 10, 100, Some("Shrink filesystem"));
         let size_mib = aligned_size / (1024 * 1024);
         let size_arg = format!("{size_mib}M");
+        // ntfsresize meldet Prozent; als overallPercent in den Bereich 10-60 der Gesamtoperation
+        set_progress_span(10, 50);
         let log = match fs {
             "ext4" => run_sidecar_capture("resize2fs", [device, &size_arg])?,
-            "ntfs" => run_sidecar_capture("ntfsresize", ["-s", &size_arg, device])?,
+            "ntfs" => run_sidecar_stream("ntfsresize", vec!["-s".to_string(), size_arg.clone(), device.to_string()])?,
+            "fat32" => run_sidecar_capture("fatresize", ["-s", &size_arg, device])?,
+            // btrfs resized nur gemountet
+            "btrfs" => with_temp_mount(device, "btrfs", |mount_point| {
+                run_sidecar_capture("btrfs", ["filesystem", "resize", &size_arg, mount_point])
+            })?,
             _ => return Err("Unsupported filesystem".to_string()),
         };
+        set_progress_span(0, 100);
         output_log.push_str(&log);
         output_log.push_str("\n");
+        fs_log = log;
+        ensure_not_cancelled()?;
         emit_progress("resize", 60, 100, Some("Update partition table"));
         let table_log = resize_partition_table(&info, new_end)?;
         output_log.push_str(&table_log);
@@ -2395,23 +5711,139 @@ fn resize_linux_partition(device: &str, fs: &str, new_size: &str) -> Result<Opti
         let table_log = resize_partition_table(&info, new_end)?;
         output_log.push_str(&table_log);
         output_log.push_str("\n");
+        ensure_not_cancelled()?;
         emit_progress("resize", 70, 100, Some("Grow filesystem"));
+        set_progress_span(70, 30);
         let log = match fs {
             "ext4" => run_sidecar_capture("resize2fs", [device])?,
-            "ntfs" => run_sidecar_capture("ntfsresize", [device])?,
+            "ntfs" => run_sidecar_stream("ntfsresize", vec![device.to_string()])?,
+            "fat32" => run_sidecar_capture("fatresize", ["-s", "max", device])?,
+            "btrfs" => with_temp_mount(device, "btrfs", |mount_point| {
+                run_sidecar_capture("btrfs", ["filesystem", "resize", "max", mount_point])
+            })?,
+            "xfs" => with_temp_mount(device, "xfs", |mount_point| {
+                run_sidecar_capture("xfs_growfs", [mount_point])
+            })?,
             _ => return Err("Unsupported filesystem".to_string()),
         };
+        set_progress_span(0, 100);
         output_log.push_str(&log);
+        fs_log = log;
     }
 
     emit_progress("resize", 100, 100, Some("Resize complete"));
 
-    Ok(Some(json!({ "device": device, "fs": fs, "size": new_size, "output": output_log.trim() })))
+    Ok(Some(json!({
+        "device": device,
+        "fs": fs,
+        "size": new_size,
+        "partitionSize": aligned_size,
+        "filesystemSize": parse_resize_final_size(fs, &fs_log),
+        "output": output_log.trim(),
+    })))
+}
+
+// Endgroesse aus der Tool-Ausgabe: resize2fs "... is now 262144 (4k) blocks long",
+// ntfsresize "New volume size    : 1073737728 bytes (1074 MB)"
+fn parse_resize_final_size(fs: &str, output: &str) -> Option<u64> {
+    match fs {
+        "ext4" => {
+            let pattern = Regex::new(r"is now (\d+) \((\d+)k\) blocks long").ok()?;
+            let captures = pattern.captures(output)?;
+            let blocks = captures.get(1)?.as_str().parse::<u64>().ok()?;
+            let block_kib = captures.get(2)?.as_str().parse::<u64>().ok()?;
+            Some(blocks * block_kib * 1024)
+        }
+        "ntfs" => {
+            let pattern = Regex::new(r"New volume size\s*:\s*(\d+) bytes").ok()?;
+            pattern.captures_iter(output).last()?.get(1)?.as_str().parse::<u64>().ok()
+        }
+        _ => None,
+    }
+}
+
+// btrfs/xfs resizen nur gemountet. macOS bringt fuer beide keinen Treiber mit; ohne
+// nachinstallierten mount-Helper (mount -t sucht /sbin/mount_<fs> bzw. /Library/Filesystems)
+// wuerde der Resize erst nach dem Aushaengen scheitern.
+fn online_resize_available(fs: &str) -> bool {
+    if !matches!(fs, "btrfs" | "xfs") {
+        return true;
+    }
+    [
+        PathBuf::from(format!("/sbin/mount_{fs}")),
+        PathBuf::from(format!("/Library/Filesystems/{fs}.fs")),
+    ]
+    .iter()
+    .any(|path| path.exists())
+}
+
+// Mountpunkte nur unter dem root-eigenen state_dir und per mkdtemp: ein fester Pfad unter
+// /tmp liesse sich vorab anlegen oder per Symlink umbiegen.
+fn create_temp_mount_dir() -> Result<PathBuf, String> {
+    let base = state_dir().join("mnt");
+    std::fs::create_dir_all(&base).map_err(|e| format!("Mount dir failed: {e}"))?;
+    let meta = std::fs::symlink_metadata(&base).map_err(|e| format!("Mount dir failed: {e}"))?;
+    if !meta.is_dir() || meta.uid() != unsafe { libc::geteuid() } {
+        return Err(format!("Mount dir {} is not owned by the helper", base.display()));
+    }
+    std::fs::set_permissions(&base, std::fs::Permissions::from_mode(0o700))
+        .map_err(|e| format!("Mount dir failed: {e}"))?;
+
+    let template = std::ffi::CString::new(base.join("resize.XXXXXX").as_os_str().as_bytes())
+        .map_err(|_| "Invalid mount dir".to_string())?;
+    let mut buffer = template.into_bytes_with_nul();
+    let created = unsafe { libc::mkdtemp(buffer.as_mut_ptr() as *mut libc::c_char) };
+    if created.is_null() {
+        return Err(format!("mkdtemp failed: {}", std::io::Error::last_os_error()));
+    }
+    buffer.pop();
+    Ok(PathBuf::from(std::ffi::OsString::from_vec(buffer)))
+}
+
+// Mountet das Dateisystem temporaer (btrfs/xfs resizen nur online) und haengt es
+// danach immer wieder aus, auch wenn der Resize fehlschlaegt.
+fn with_temp_mount<F>(device: &str, fs: &str, f: F) -> Result<String, String>
+where
+    F: FnOnce(&str) -> Result<String, String>,
+{
+    if is_dry_run() {
+        let mount_point = state_dir().join("mnt").join("resize.XXXXXX").display().to_string();
+        log_dry_run("mount", ["-t", fs, device, &mount_point]);
+        let result = f(&mount_point);
+        log_dry_run("umount", [&mount_point]);
+        return result;
+    }
+
+    let mount_dir = create_temp_mount_dir()?;
+    let mount_point = mount_dir.display().to_string();
+    let output = Command::new("mount")
+        .args(["-t", fs, device, &mount_point])
+        .output()
+        .map_err(|e| format!("mount failed: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let _ = std::fs::remove_dir(&mount_dir);
+        return Err(format!("mount error: {stderr}"));
+    }
+
+    let result = f(&mount_point);
+
+    let unmounted = Command::new("umount")
+        .arg(&mount_point)
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false);
+    if unmounted {
+        let _ = std::fs::remove_dir(&mount_dir);
+    } else {
+        emit_log("resize", &format!("Aushaengen von {mount_point} fehlgeschlagen"));
+    }
+
+    result
 }
 
 fn resize_partition_table(info: &PartitionInfo, new_end: u64) -> Result<String, String> {
-    let start_sector = info.partition_offset / info.block_size;
-    let end_sector = (new_end / info.block_size) - 1;
+    let (start_sector, end_sector) = sector_range(info.partition_offset, new_end, info.block_size)?;
     let part_number = partition_number(&info.device).ok_or_else(|| "Invalid partition".to_string())?;
 
     let output = run_sidecar_capture(
@@ -2433,7 +5865,7 @@ fn move_partition(device: &str, new_start: u64) -> Result<Option<Value>, String>
     }
 
     let info = read_partition_info(device)?;
-    let aligned_start = align_mib(new_start);
+    let aligned_start = align_mib(new_start, info.physical_block_size.max(info.block_size));
     if aligned_start < info.min_start || aligned_start >= info.max_end {
         return Err("Invalid target start".to_string());
     }
@@ -2448,6 +5880,10 @@ fn move_partition(device: &str, new_start: u64) -> Result<Option<Value>, String>
     if aligned_start < old_end && new_end > old_start {
         return Err("Move would overlap existing data".to_string());
     }
+    ensure_no_pending_move()?;
+
+    maybe_swapoff(device)?;
+    force_unmount_disk(device)?;
 
     let journal = json!({
         "operation": "move",
@@ -2462,27 +5898,49 @@ fn move_partition(device: &str, new_start: u64) -> Result<Option<Value>, String>
     });
     write_journal(&journal)?;
 
-    let move_log = copy_blocks(&info.disk, old_start, aligned_start, size, true)?;
+    let move_log = copy_blocks(&info.disk, old_start, aligned_start, size, true, 0)?;
+    let gpt_log = rewrite_moved_partition(device, &info.disk, aligned_start, size, info.block_size)?;
+
+    clear_journal();
+    Ok(Some(json!({ "device": device, "newStart": aligned_start, "output": format!("{move_log}\n{gpt_log}").trim() })))
+}
 
-    let start_sector = aligned_start / info.block_size;
-    let end_sector = (new_end / info.block_size) - 1;
+fn rewrite_moved_partition(
+    device: &str,
+    disk: &str,
+    new_start: u64,
+    size: u64,
+    block_size: u64,
+) -> Result<String, String> {
+    let (start_sector, end_sector) = sector_range(new_start, new_start + size, block_size)?;
     let part_number = partition_number(device).ok_or_else(|| "Invalid partition".to_string())?;
-    let gpt_log = run_sidecar_capture(
+    run_sidecar_capture(
         "sgdisk",
         [
             "--delete",
             &part_number.to_string(),
             "--new",
             &format!("{part_number}:{start_sector}:{end_sector}"),
-            &info.disk,
+            disk,
         ],
-    )?;
-
-    clear_journal();
-    Ok(Some(json!({ "device": device, "newStart": aligned_start, "output": format!("{move_log}\n{gpt_log}").trim() })))
+    )
 }
 
-fn copy_blocks(disk: &str, src_offset: u64, dst_offset: u64, size: u64, journal: bool) -> Result<String, String> {
+// Kopier- und Journal-Granularitaet von copy_blocks. Bei journal=true wird nach jedem Block
+// per sync_data auf das Geraet geschrieben und erst dann lastCopied fortgeschrieben, sodass
+// das Journal dem Geraet nie hinterherhinkt (hoechstens ein Block wird beim Resume wiederholt).
+const MOVE_CHUNK_BYTES: u64 = 4 * 1024 * 1024;
+
+// resume_from: bereits kopierte Bytes (aus journal.lastCopied), 0 für einen neuen Kopiervorgang
+fn copy_blocks(
+    disk: &str,
+    src_offset: u64,
+    dst_offset: u64,
+    size: u64,
+    journal: bool,
+    resume_from: u64,
+) -> Result<String, String> {
+    ensure_not_dry_run()?;
     let mut reader = std::fs::OpenOptions::new()
         .read(true)
         .open(disk)
@@ -2492,64 +5950,98 @@ fn copy_blocks(disk: &str, src_offset: u64, dst_offset: u64, size: u64, journal:
         .open(disk)
         .map_err(|e| format!("Open target failed: {e}"))?;
 
-    let buffer_size = 4 * 1024 * 1024;
+    let buffer_size = MOVE_CHUNK_BYTES as usize;
     let mut buffer = vec![0u8; buffer_size];
-    let mut remaining = size;
+    let resume_from = resume_from.min(size);
 
-    let mut copied: u64 = 0;
+    let mut copied: u64 = resume_from;
     let progress_step: u64 = 50 * 1024 * 1024;
-    let mut next_progress = progress_step;
-
-    if dst_offset > src_offset {
-        let mut position = size;
-        while position > 0 {
-            let chunk = std::cmp::min(buffer_size as u64, position) as usize;
-            position -= chunk as u64;
-            let read_pos = src_offset + position;
-            let write_pos = dst_offset + position;
-            reader.seek(SeekFrom::Start(read_pos)).map_err(|e| e.to_string())?;
-            reader.read_exact(&mut buffer[..chunk]).map_err(|e| e.to_string())?;
-            writer.seek(SeekFrom::Start(write_pos)).map_err(|e| e.to_string())?;
-            writer.write_all(&buffer[..chunk]).map_err(|e| e.to_string())?;
-            remaining -= chunk as u64;
-            copied += chunk as u64;
-            if copied >= next_progress {
-                let percent = ((copied as f64 / size as f64) * 100.0).round() as u64;
-                emit_progress_bytes("move", percent, 100, Some("Copying blocks"), copied, size);
-                if journal {
-                    let _ = update_journal_progress(copied);
-                }
-                next_progress += progress_step;
-            }
+    let mut next_progress = copied + progress_step;
+
+    // Rueckwaerts kopieren, wenn das Ziel hinter der Quelle liegt (Ueberlappung)
+    let backwards = dst_offset > src_offset;
+    while copied < size {
+        let chunk = std::cmp::min(buffer_size as u64, size - copied) as usize;
+        let position = if backwards { size - copied - chunk as u64 } else { copied };
+        reader.seek(SeekFrom::Start(src_offset + position)).map_err(|e| e.to_string())?;
+        reader.read_exact(&mut buffer[..chunk]).map_err(|e| e.to_string())?;
+        writer.seek(SeekFrom::Start(dst_offset + position)).map_err(|e| e.to_string())?;
+        writer.write_all(&buffer[..chunk]).map_err(|e| e.to_string())?;
+        copied += chunk as u64;
+        if journal {
+            writer.sync_data().map_err(|e| format!("Sync failed: {e}"))?;
+            update_journal_progress(copied)?;
         }
-    } else {
-        let mut position = 0u64;
-        while position < size {
-            let chunk = std::cmp::min(buffer_size as u64, size - position) as usize;
-            let read_pos = src_offset + position;
-            let write_pos = dst_offset + position;
-            reader.seek(SeekFrom::Start(read_pos)).map_err(|e| e.to_string())?;
-            reader.read_exact(&mut buffer[..chunk]).map_err(|e| e.to_string())?;
-            writer.seek(SeekFrom::Start(write_pos)).map_err(|e| e.to_string())?;
-            writer.write_all(&buffer[..chunk]).map_err(|e| e.to_string())?;
-            position += chunk as u64;
-            remaining -= chunk as u64;
-            copied += chunk as u64;
-            if copied >= next_progress {
-                let percent = ((copied as f64 / size as f64) * 100.0).round() as u64;
-                emit_progress_bytes("move", percent, 100, Some("Copying blocks"), copied, size);
-                if journal {
-                    let _ = update_journal_progress(copied);
-                }
-                next_progress += progress_step;
-            }
+        if copied >= next_progress || copied == size {
+            let percent = ((copied as f64 / size as f64) * 100.0).round() as u64;
+            emit_progress_bytes("move", percent, 100, Some("Copying blocks"), copied, size);
+            next_progress += progress_step;
         }
+        // Abbruch nur an Blockgrenzen, nachdem Geraet und Journal auf demselben Stand sind
+        ensure_not_cancelled()?;
     }
 
     Ok(format!("Smart copy completed. Bytes moved: {size}"))
 }
 
+// Schnellpfad fuer Partitionskopien: nur belegte Bloecke uebertragen. None, wenn das
+// Dateisystem keinen Schnellpfad hat oder das Tool fehlt; dann folgt die Rohkopie.
+fn clone_used_blocks(fs: &str, source: &str, target: &str) -> Result<Option<(&'static str, String)>, String> {
+    let (binary, args) = match fs {
+        "ext4" => ("e2image", vec!["-ra".to_string(), "-p".to_string(), source.to_string(), target.to_string()]),
+        "ntfs" => ("ntfsclone", vec!["--overwrite".to_string(), target.to_string(), source.to_string()]),
+        _ => return Ok(None),
+    };
+    if find_sidecar(binary).is_err() {
+        return Ok(None);
+    }
+    let log = run_sidecar_stream(binary, args)?;
+    Ok(Some((binary, log)))
+}
+
+// SHA-256 ueber das Abbild der belegten Bloecke, das das Clone-Tool auf stdout schreibt.
+// Unbelegte Bereiche erscheinen dort als Nullen bzw. gar nicht und beeinflussen den Hash nicht.
+fn hash_used_blocks(method: &str, device: &str, message: &str) -> Result<String, String> {
+    let args: Vec<&str> = match method {
+        "e2image" => vec!["-ra", device, "-"],
+        "ntfsclone" => vec!["--save-image", "--output", "-", device],
+        other => return Err(format!("No used-block verification for {other}")),
+    };
+    let path = find_sidecar(method)?;
+    if is_dry_run() {
+        log_dry_run(&path.display().to_string(), &args);
+        return Ok(String::new());
+    }
+
+    emit_progress("verify", 0, 100, Some(message));
+    let mut child = Command::new(&path)
+        .args(&args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Sidecar failed: {e}"))?;
+    let mut stdout = child.stdout.take().ok_or_else(|| "Sidecar stdout missing".to_string())?;
+
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 4 * 1024 * 1024];
+    loop {
+        ensure_not_cancelled()?;
+        let read = stdout.read(&mut buffer).map_err(|e| e.to_string())?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    let status = child.wait().map_err(|e| format!("Sidecar failed: {e}"))?;
+    if !status.success() {
+        return Err(format!("{method} failed while verifying {device}"));
+    }
+    emit_progress("verify", 100, 100, Some(message));
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
 fn copy_partition_blocks(source_device: &str, target_device: &str, size: u64) -> Result<String, String> {
+    ensure_not_dry_run()?;
     let source_info = read_partition_info(source_device)?;
     let target_info = read_partition_info(target_device)?;
 
@@ -2560,6 +6052,7 @@ fn copy_partition_blocks(source_device: &str, target_device: &str, size: u64) ->
             target_info.partition_offset,
             size,
             false,
+            0,
         );
     }
 
@@ -2580,6 +6073,7 @@ fn copy_partition_blocks(source_device: &str, target_device: &str, size: u64) ->
     let mut next_progress = progress_step;
 
     while remaining > 0 {
+        ensure_not_cancelled()?;
         let chunk = std::cmp::min(buffer_size as u64, remaining) as usize;
         reader.read_exact(&mut buffer[..chunk]).map_err(|e| e.to_string())?;
         writer.write_all(&buffer[..chunk]).map_err(|e| e.to_string())?;
@@ -2595,20 +6089,92 @@ fn copy_partition_blocks(source_device: &str, target_device: &str, size: u64) ->
     Ok(format!("Copy completed. Bytes copied: {size}"))
 }
 
+// Gleitender Durchsatz ueber die letzten Fortschritts-Samples
+const THROUGHPUT_WINDOW: usize = 5;
+
+struct ThroughputMeter {
+    samples: std::collections::VecDeque<(Instant, u64)>,
+}
+
+impl ThroughputMeter {
+    fn new() -> Self {
+        let mut samples = std::collections::VecDeque::new();
+        samples.push_back((Instant::now(), 0));
+        ThroughputMeter { samples }
+    }
+
+    fn sample(&mut self, bytes: u64, total_bytes: u64) -> (u64, Option<u64>) {
+        self.samples.push_back((Instant::now(), bytes));
+        while self.samples.len() > THROUGHPUT_WINDOW + 1 {
+            self.samples.pop_front();
+        }
+
+        let (first_at, first_bytes) = self.samples[0];
+        let (last_at, last_bytes) = self.samples[self.samples.len() - 1];
+        let elapsed = last_at.duration_since(first_at).as_secs_f64();
+        if elapsed <= 0.0 {
+            return (0, None);
+        }
+        let bytes_per_second = last_bytes.saturating_sub(first_bytes) as f64 / elapsed;
+        let eta_seconds = if bytes_per_second > 0.0 && total_bytes > 0 {
+            Some((total_bytes.saturating_sub(bytes) as f64 / bytes_per_second).round() as u64)
+        } else {
+            None
+        };
+        (bytes_per_second as u64, eta_seconds)
+    }
+}
+
 fn emit_progress(phase: &str, percent: u64, total: u64, message: Option<&str>) {
     emit_progress_bytes(phase, percent, total, message, 0, 0);
 }
 
 fn emit_progress_bytes(phase: &str, percent: u64, total: u64, message: Option<&str>, bytes: u64, total_bytes: u64) {
-    let payload = json!({
+    emit_progress_rate(phase, percent, total, message, bytes, total_bytes, None);
+}
+
+// rate: (Bytes pro Sekunde, geschaetzte Restsekunden) aus ThroughputMeter
+fn emit_progress_rate(
+    phase: &str,
+    percent: u64,
+    total: u64,
+    message: Option<&str>,
+    bytes: u64,
+    total_bytes: u64,
+    rate: Option<(u64, Option<u64>)>,
+) {
+    let span = PROGRESS_SPAN.lock().map(|span| *span).unwrap_or((0, 100));
+    let mut payload = json!({
         "type": "progress",
         "phase": phase,
         "percent": percent,
         "total": total,
+        "overallPercent": overall_percent(percent, total, span),
         "message": message,
         "bytes": bytes,
         "totalBytes": total_bytes,
     });
+    if let Some((bytes_per_second, eta_seconds)) = rate {
+        payload["bytesPerSecond"] = json!(bytes_per_second);
+        payload["etaSeconds"] = json!(eta_seconds);
+    }
+    if let Ok(line) = serde_json::to_string(&payload) {
+        println!("{line}");
+        let _ = std::io::stdout().flush();
+    }
+}
+
+// Rechnet den Phasenfortschritt (percent von total) in den Anteil der Gesamtoperation um.
+fn overall_percent(percent: u64, total: u64, (start, width): (u64, u64)) -> u64 {
+    let fraction = if total > 0 { percent.min(total) as f64 / total as f64 } else { 0.0 };
+    (start + (fraction * width as f64).round() as u64).min(100)
+}
+
+fn emit_heartbeat(tool: &str) {
+    let payload = json!({
+        "type": "heartbeat",
+        "tool": tool,
+    });
     if let Ok(line) = serde_json::to_string(&payload) {
         println!("{line}");
         let _ = std::io::stdout().flush();
@@ -2628,47 +6194,32 @@ fn emit_log(source: &str, line: &str) {
 }
 
 fn find_partition_by_label(label: &str) -> Result<Option<String>, String> {
-    let output = Command::new("diskutil")
-        .args(["list", "-plist"])
-        .output()
-        .map_err(|e| format!("diskutil failed: {e}"))?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("diskutil error: {stderr}"));
-    }
+    let plist = diskutil_plist(["list", "-plist"])?;
+    parse_partition_by_label(&plist, label)
+}
 
-    let plist = PlistValue::from_reader_xml(&output.stdout[..]).map_err(|e| e.to_string())?;
+fn parse_partition_by_label(plist: &PlistValue, label: &str) -> Result<Option<String>, String> {
     let dict = plist
         .as_dictionary()
         .ok_or_else(|| "Invalid plist".to_string())?;
 
-    let all_disks = match dict.get("AllDisksAndPartitions") {
-        Some(PlistValue::Array(arr)) => arr,
-        _ => return Err("Invalid plist structure".to_string()),
-    };
-
-    for entry in all_disks {
-        if let Some(disk_dict) = entry.as_dictionary() {
-            if let Some(PlistValue::Array(parts)) = disk_dict.get("Partitions") {
-                for part in parts {
-                    if let Some(part_dict) = part.as_dictionary() {
-                        let volume_name = part_dict
-                            .get("VolumeName")
-                            .and_then(|v| v.as_string())
-                            .unwrap_or("");
-                        if volume_name == label {
-                            let identifier = part_dict
-                                .get("DeviceIdentifier")
-                                .and_then(|v| v.as_string())
-                                .unwrap_or("")
-                                .to_string();
-                            if !identifier.is_empty() {
-                                return Ok(Some(identifier));
-                            }
-                        }
-                    }
-                }
+    if !matches!(dict.get("AllDisksAndPartitions"), Some(PlistValue::Array(_))) {
+        return Err("Invalid plist structure".to_string());
+    }
+
+    for part_dict in disk_partition_dicts(dict) {
+        let volume_name = part_dict
+            .get("VolumeName")
+            .and_then(|v| v.as_string())
+            .unwrap_or("");
+        if volume_name == label {
+            let identifier = part_dict
+                .get("DeviceIdentifier")
+                .and_then(|v| v.as_string())
+                .unwrap_or("")
+                .to_string();
+            if !identifier.is_empty() {
+                return Ok(Some(identifier));
             }
         }
     }
@@ -2716,6 +6267,9 @@ fn detect_fs_type(device: &str) -> Result<String, String> {
         if candidate.contains("ntfs") {
             return Ok("ntfs".to_string());
         }
+        if candidate.contains("zfs") {
+            return Ok("zfs".to_string());
+        }
         if candidate.contains("ext4") || candidate.contains("linux") {
             return Ok("ext4".to_string());
         }
@@ -2753,26 +6307,53 @@ fn validate_uuid(uuid: &str) -> Result<(), String> {
     Ok(())
 }
 
+// NTFS und FAT/exFAT haben statt einer UUID nur eine Seriennummer (16 bzw. 8 Hex-Zeichen)
+fn validate_volume_id(fs_type: &str, uuid: &str) -> Result<String, String> {
+    let hex_len = match fs_type {
+        "ntfs" => 16,
+        "exfat" | "fat32" => 8,
+        _ => {
+            validate_uuid(uuid)?;
+            return Ok(uuid.to_string());
+        }
+    };
+
+    if uuid == "random" {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        let serial = format!("{:016X}", nanos ^ u64::from(std::process::id()).rotate_left(32));
+        return Ok(serial[16 - hex_len..].to_string());
+    }
+
+    let serial: String = uuid.chars().filter(|c| *c != '-').collect();
+    if serial.len() != hex_len || !serial.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid volume serial: expected {hex_len} hex digits"));
+    }
+    Ok(serial.to_uppercase())
+}
+
 fn strip_device_prefix(identifier: &str) -> String {
     identifier.trim_start_matches("/dev/").to_string()
 }
 
-fn plist_string(dict: &std::collections::BTreeMap<String, PlistValue>, keys: &[&str]) -> Option<String> {
+fn plist_string(dict: &plist::Dictionary, keys: &[&str]) -> Option<String> {
     for key in keys {
-        if let Some(value) = dict.get(*key).and_then(|v| v.as_string()) {
+        if let Some(value) = dict.get(key).and_then(|v| v.as_string()) {
             return Some(value.to_string());
         }
     }
     None
 }
 
-fn plist_u64(dict: &std::collections::BTreeMap<String, PlistValue>, keys: &[&str]) -> Option<u64> {
+fn plist_u64(dict: &plist::Dictionary, keys: &[&str]) -> Option<u64> {
     for key in keys {
-        if let Some(value) = dict.get(*key) {
+        if let Some(value) = dict.get(key) {
             if let Some(u) = value.as_unsigned_integer() {
                 return Some(u);
             }
-            if let Some(i) = value.as_integer() {
+            if let Some(i) = value.as_signed_integer() {
                 if i >= 0 {
                     return Some(i as u64);
                 }
@@ -2782,9 +6363,9 @@ fn plist_u64(dict: &std::collections::BTreeMap<String, PlistValue>, keys: &[&str
     None
 }
 
-fn plist_string_array(dict: &std::collections::BTreeMap<String, PlistValue>, keys: &[&str]) -> Vec<String> {
+fn plist_string_array(dict: &plist::Dictionary, keys: &[&str]) -> Vec<String> {
     for key in keys {
-        if let Some(arr) = dict.get(*key).and_then(|v| v.as_array()) {
+        if let Some(arr) = dict.get(key).and_then(|v| v.as_array()) {
             return arr
                 .iter()
                 .filter_map(|v| v.as_string())
@@ -2795,7 +6376,7 @@ fn plist_string_array(dict: &std::collections::BTreeMap<String, PlistValue>, key
     Vec::new()
 }
 
-fn container_matches(container_dict: &std::collections::BTreeMap<String, PlistValue>, needle: &str) -> bool {
+fn container_matches(container_dict: &plist::Dictionary, needle: &str) -> bool {
     if let Some(reference) = plist_string(container_dict, &["ContainerReference", "DeviceIdentifier", "ContainerIdentifier"]) {
         if strip_device_prefix(&reference) == needle {
             return true;
@@ -2845,6 +6426,11 @@ where
     S: AsRef<std::ffi::OsStr>,
 {
     let path = find_sidecar(binary)?;
+    if is_dry_run() {
+        log_dry_run(&path.display().to_string(), args);
+        return Ok(());
+    }
+    let _watch = watch_tool(binary);
     let output = Command::new(&path)
         .args(args)
         .output()
@@ -2858,23 +6444,40 @@ where
     Ok(())
 }
 
+// Zeilen werden gemeldet, sobald das Tool sie schreibt; stderr laeuft in einem eigenen
+// Thread, damit keine der beiden Pipes volllaeuft.
 fn run_sidecar_stream(binary: &str, args: Vec<String>) -> Result<String, String> {
     let path = find_sidecar(binary)?;
-    let output = Command::new(&path)
+    if is_dry_run() {
+        log_dry_run(&path.display().to_string(), &args);
+        return Ok(String::new());
+    }
+    let _watch = watch_tool(binary);
+    let mut child = Command::new(&path)
         .args(args)
-        .output()
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
         .map_err(|e| format!("Sidecar failed: {e}"))?;
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    for line in stdout.lines() {
-        emit_log(binary, line);
-    }
-    let stderr = String::from_utf8_lossy(&output.stderr);
-    for line in stderr.lines() {
-        emit_log(binary, line);
+    let stderr_pipe = child.stderr.take();
+    let stderr_source = binary.to_string();
+    let stderr_thread = std::thread::spawn(move || {
+        let mut collected = String::new();
+        if let Some(pipe) = stderr_pipe {
+            stream_tool_output(pipe, &stderr_source, &mut collected);
+        }
+        collected
+    });
+
+    let mut stdout = String::new();
+    if let Some(pipe) = child.stdout.take() {
+        stream_tool_output(pipe, binary, &mut stdout);
     }
+    let stderr = stderr_thread.join().unwrap_or_default();
+    let status = child.wait().map_err(|e| format!("Sidecar failed: {e}"))?;
 
-    if !output.status.success() {
+    if !status.success() {
         let combined = format!("{stdout}\n{stderr}").trim().to_string();
         return Err(format!("Sidecar error: {combined}"));
     }
@@ -2882,6 +6485,169 @@ fn run_sidecar_stream(binary: &str, args: Vec<String>) -> Result<String, String>
     Ok(format!("{stdout}\n{stderr}").trim().to_string())
 }
 
+// ntfsresize/ntfsclone aktualisieren ihre Fortschrittszeile per '\r', daher wird auch daran
+// getrennt. Fortschrittszeilen gehen als progress-Event raus, alle anderen als log.
+fn stream_tool_output<R: Read>(pipe: R, source: &str, collected: &mut String) {
+    let mut reader = std::io::BufReader::new(pipe);
+    let mut line: Vec<u8> = Vec::new();
+    let mut byte = [0u8; 1];
+    loop {
+        let done = !matches!(reader.read(&mut byte), Ok(1));
+        if done || byte[0] == b'\n' || byte[0] == b'\r' {
+            let text = String::from_utf8_lossy(&line).trim_end().to_string();
+            line.clear();
+            if !text.trim().is_empty() {
+                match parse_tool_percent(&text) {
+                    Some(percent) => emit_progress(source, percent, 100, Some(text.trim())),
+                    None => {
+                        emit_log(source, &text);
+                        collected.push_str(&text);
+                        collected.push('\n');
+                    }
+                }
+            }
+            if done {
+                break;
+            }
+        } else {
+            line.push(byte[0]);
+        }
+    }
+}
+
+// "  45.67 percent completed" (ntfsresize, ntfsclone) bzw. "45%"
+fn parse_tool_percent(line: &str) -> Option<u64> {
+    static PERCENT: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    let pattern = PERCENT.get_or_init(|| Regex::new(r"(\d{1,3})(?:\.\d+)?\s*(?:%|percent)").expect("valid regex"));
+    let percent = pattern.captures(line)?.get(1)?.as_str().parse::<u64>().ok()?;
+    Some(percent.min(100))
+}
+
+// extraArgs nur mit den vom Treiber freigegebenen Schaltern; Dateisysteme ohne Treiber
+// (APFS, HFS+) nehmen keine Zusatzargumente an.
+fn read_mkfs_options(payload: &Value, fs: &str) -> Result<MkfsOptions, String> {
+    let opts: MkfsOptions = match payload.get("opts") {
+        Some(value) if !value.is_null() => {
+            serde_json::from_value(value.clone()).map_err(|e| format!("Invalid mkfs options: {e}"))?
+        }
+        _ => MkfsOptions::default(),
+    };
+    match driver_for(&fs.to_lowercase()) {
+        Some(driver) => driver.validate_mkfs_options(&opts)?,
+        None => check_mkfs_args(fs, &opts.extra_args, &[])?,
+    }
+    Ok(opts)
+}
+
+// Label vor mkfs/diskutil an die Grenzen des Dateisystems anpassen; Dateisysteme ohne
+// Treiber (APFS, HFS+) uebernimmt diskutil selbst.
+fn checked_label(fs: &str, label: &str) -> Result<(String, Option<String>), String> {
+    match driver_for(&fs.to_lowercase()) {
+        Some(driver) => {
+            let checked = driver.validate_label(label)?;
+            Ok((checked.label, checked.warning))
+        }
+        None => Ok((label.to_string(), None)),
+    }
+}
+
+fn attach_label_warning(
+    result: Result<Option<Value>, String>,
+    warning: Option<String>,
+) -> Result<Option<Value>, String> {
+    let Some(warning) = warning else {
+        return result;
+    };
+    result.map(|details| {
+        let mut details = details.unwrap_or_else(|| json!({}));
+        if let Some(map) = details.as_object_mut() {
+            map.insert("labelWarning".to_string(), json!(warning));
+        }
+        Some(details)
+    })
+}
+
+// Label-Grenzen aller Treiber, damit die UI Eingaben live pruefen kann
+fn handle_filesystem_constraints() -> Result<Option<Value>, String> {
+    let filesystems: Vec<Value> = default_drivers()
+        .iter()
+        .map(|driver| {
+            json!({
+                "fs": driver.id(),
+                "maxLabelLength": driver.max_label_length(),
+                "allowedLabelChars": driver.allowed_label_chars(),
+            })
+        })
+        .collect();
+    Ok(Some(json!({ "filesystems": filesystems })))
+}
+
+// Faehigkeiten je Dateisystem, abgeleitet aus den Treibern (Some/None) und den vorhandenen
+// Sidecars. APFS/HFS+ laufen ohne Treiber komplett ueber diskutil.
+fn handle_supported_filesystems() -> Result<Option<Value>, String> {
+    const EIB: u64 = 1024 * 1024 * 1024 * 1024 * 1024 * 1024;
+    let mut filesystems = vec![
+        json!({
+            "fs": "apfs",
+            "canCreate": true,
+            "canResizeGrow": true,
+            "canResizeShrink": true,
+            "canRelabel": true,
+            "canSetUuid": false,
+            "maxFileSize": 8 * EIB,
+            "sidecars": [],
+            "sidecarRequired": false,
+            "sidecarsPresent": true,
+        }),
+        json!({
+            "fs": "hfs+",
+            "canCreate": true,
+            "canResizeGrow": true,
+            "canResizeShrink": true,
+            "canRelabel": true,
+            "canSetUuid": false,
+            "maxFileSize": 8 * EIB,
+            "sidecars": [],
+            "sidecarRequired": false,
+            "sidecarsPresent": true,
+        }),
+    ];
+
+    let device = "/dev/null";
+    for driver in default_drivers() {
+        let fs = driver.id();
+        let label_binary = driver.label_command(device, "").map(|(bin, _)| bin);
+        let uuid_binary = driver.uuid_command(device, "").map(|(bin, _)| bin);
+        // ntfs-3g ist fuer Create nur optional (beschreibbarer Mount) und zaehlt hier nicht mit
+        let mut names: Vec<String> = required_sidecars("resize", fs).into_iter().map(|check| check.name).collect();
+        names.extend(mkfs_binary_for(fs).map(|bin| bin.to_string()));
+        names.extend(label_binary.iter().cloned());
+        names.extend(uuid_binary.iter().cloned());
+        names.sort();
+        names.dedup();
+
+        let sidecars: Vec<Value> = names
+            .iter()
+            .map(|name| json!({ "name": name, "found": find_sidecar(name).is_ok() }))
+            .collect();
+        let sidecars_present = sidecars.iter().all(|s| s["found"].as_bool().unwrap_or(false));
+
+        filesystems.push(json!({
+            "fs": fs,
+            "canCreate": driver.mkfs_command(device, "").is_some(),
+            "canResizeGrow": driver.resize_binary(false).is_some() && online_resize_available(fs),
+            "canResizeShrink": driver.resize_binary(true).is_some() && online_resize_available(fs),
+            "canRelabel": label_binary.is_some(),
+            "canSetUuid": uuid_binary.is_some(),
+            "maxFileSize": driver.max_file_size(),
+            "sidecarRequired": !sidecars.is_empty(),
+            "sidecarsPresent": sidecars_present,
+            "sidecars": sidecars,
+        }));
+    }
+    Ok(Some(json!({ "filesystems": filesystems })))
+}
+
 fn driver_for(fs: &str) -> Option<Box<dyn FileSystemDriver>> {
     for driver in default_drivers() {
         if driver.id() == fs {
@@ -2910,7 +6676,7 @@ fn find_sidecar(binary: &str) -> Result<PathBuf, String> {
         }
     }
 
-    Err(format!("Sidecar not found: {binary}"))
+    Err(coded("SIDECAR_MISSING", format!("Sidecar not found: {binary}")))
 }
 
 fn run_diskutil<I, S>(args: I) -> Result<(), String>
@@ -2918,16 +6684,37 @@ where
     I: IntoIterator<Item = S>,
     S: AsRef<std::ffi::OsStr>,
 {
+    if is_dry_run() {
+        log_dry_run("diskutil", args);
+        return Ok(());
+    }
+    let _watch = watch_tool("diskutil");
     let output = Command::new("diskutil")
         .args(args)
         .output()
         .map_err(|e| format!("diskutil failed: {e}"))?;
 
+    let stderr = String::from_utf8_lossy(&output.stderr);
     if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
         return Err(format!("diskutil error: {stderr}"));
     }
 
+    // diskutil meldet weiche Probleme (z.B. "Volume was not repaired because...") auch bei Exit 0
+    let warnings: Vec<String> = stderr
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| format!("diskutil: {line}"))
+        .collect();
+    if !warnings.is_empty() {
+        for warning in &warnings {
+            emit_log("diskutil", warning);
+        }
+        if let Ok(mut collected) = TOOL_WARNINGS.lock() {
+            collected.extend(warnings);
+        }
+    }
+
     Ok(())
 }
 
@@ -2936,6 +6723,11 @@ where
     I: IntoIterator<Item = S>,
     S: AsRef<std::ffi::OsStr>,
 {
+    if is_dry_run() {
+        log_dry_run("diskutil", args);
+        return Ok(String::new());
+    }
+    let _watch = watch_tool("diskutil");
     let output = Command::new("diskutil")
         .args(args)
         .output()
@@ -2958,6 +6750,11 @@ where
     S: AsRef<std::ffi::OsStr>,
 {
     let path = find_sidecar(binary)?;
+    if is_dry_run() {
+        log_dry_run(&path.display().to_string(), args);
+        return Ok(String::new());
+    }
+    let _watch = watch_tool(binary);
     let output = Command::new(&path)
         .args(args)
         .output()
@@ -2974,9 +6771,560 @@ where
     Ok(format!("{stdout}\n{stderr}").trim().to_string())
 }
 
-fn write_response(ok: bool, message: Option<String>, details: Option<Value>) {
-    let response = HelperResponse { ok, message, details };
+// Stabile, maschinenlesbare Fehlercodes; Handler stellen sie als "CODE: Text" voran.
+fn coded(code: &str, message: impl std::fmt::Display) -> String {
+    format!("{code}: {message}")
+}
+
+fn split_error_code(message: &str) -> (Option<String>, String) {
+    if let Some((code, rest)) = message.split_once(": ") {
+        if code.contains('_') && code.chars().all(|c| c.is_ascii_uppercase() || c == '_') {
+            return (Some(code.to_string()), rest.to_string());
+        }
+    }
+    // Fallback fuer diskutil-/Sidecar-Texte ohne expliziten Code.
+    let lower = message.to_lowercase();
+    let code = if lower.contains("resource busy")
+        || lower.contains("could not unmount")
+        || lower.contains("failed to unmount")
+    {
+        Some("DEVICE_BUSY".to_string())
+    } else {
+        None
+    };
+    (code, message.to_string())
+}
+
+fn write_response(ok: bool, code: Option<String>, message: Option<String>, details: Option<Value>) {
+    let response = HelperResponse { ok, code, message, details };
     if let Ok(json) = serde_json::to_string(&response) {
         let _ = std::io::stdout().write_all(json.as_bytes());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const APFS_LIST_APPLE_SILICON: &str = include_str!("../../tests/fixtures/apfs_list_apple_silicon.plist");
+    const LIST_INTEL_DISK0: &str = include_str!("../../tests/fixtures/diskutil_list_intel_disk0.plist");
+    const LIST_MBR_DISK4: &str = include_str!("../../tests/fixtures/diskutil_list_mbr_disk4.plist");
+    const LIST_APPLE_SILICON_ALL: &str = include_str!("../../tests/fixtures/diskutil_list_apple_silicon_all.plist");
+    const INFO_INTEL_BOOTCAMP: &str = include_str!("../../tests/fixtures/diskutil_info_intel_bootcamp.plist");
+    const INFO_MBR_FAT32: &str = include_str!("../../tests/fixtures/diskutil_info_mbr_fat32.plist");
+    const INFO_APFS_VOLUME: &str = include_str!("../../tests/fixtures/diskutil_info_apfs_volume.plist");
+    // Superblock-Dumps; erwartete Werte laut "blkid -p" auf den Quell-Images
+    const F2FS_SUPERBLOCK: &[u8] = include_bytes!("../../tests/fixtures/f2fs_superblock.bin");
+    const FAT16_BOOT_SECTOR: &[u8] = include_bytes!("../../tests/fixtures/fat16_boot_sector.bin");
+
+    fn fixture(xml: &str) -> PlistValue {
+        PlistValue::from_reader_xml(xml.as_bytes()).expect("fixture plist")
+    }
+
+    fn gpt_header(entries_lba: u64, entry_count: u32, entry_size: u32) -> Vec<u8> {
+        let mut header = vec![0u8; 4096];
+        header[512..520].copy_from_slice(b"EFI PART");
+        header[512 + 72..512 + 80].copy_from_slice(&entries_lba.to_le_bytes());
+        header[512 + 80..512 + 84].copy_from_slice(&entry_count.to_le_bytes());
+        header[512 + 84..512 + 88].copy_from_slice(&entry_size.to_le_bytes());
+        header
+    }
+
+    #[test]
+    fn gpt_layout_rejects_hostile_header_values() {
+        assert!(parse_gpt_layout(&gpt_header(u64::MAX, 4, 128)).is_err());
+        assert!(parse_gpt_layout(&gpt_header(2, 100_000, 128)).is_err());
+        assert!(parse_gpt_layout(&gpt_header(2, 4, 64)).is_err());
+        assert!(parse_gpt_layout(&gpt_header(2, 4, 200)).is_err());
+        assert!(parse_gpt_layout(&gpt_header(2, 4, u32::MAX)).is_err());
+        assert!(parse_gpt_layout(&gpt_header(2, 2, 1 << 20)).is_err());
+
+        let layout = parse_gpt_layout(&gpt_header(2, 128, 128)).expect("valid").expect("gpt");
+        assert_eq!(layout.sector_size, Some(512));
+        assert!(layout.partitions.is_empty());
+
+        // Mehr als 128 Eintraege sind gueltig; leere Eintraege werden uebersprungen
+        let mut header = gpt_header(2, 256, 128);
+        header.resize(1024 + 256 * 128, 0);
+        let entry = 1024 + 200 * 128;
+        header[entry] = 0xAF;
+        header[entry + 32..entry + 40].copy_from_slice(&2048u64.to_le_bytes());
+        header[entry + 40..entry + 48].copy_from_slice(&4095u64.to_le_bytes());
+        let layout = parse_gpt_layout(&header).expect("valid").expect("gpt");
+        assert_eq!(layout.partitions.len(), 1);
+        assert_eq!(layout.partitions[0].index, 201);
+        assert_eq!(layout.partitions[0].size_bytes, 2048 * 512);
+        assert!(parse_gpt_layout(&[0u8; 1024]).expect("no gpt").is_none());
+    }
+
+    #[test]
+    fn benchmark_write_region_only_uses_unallocated_space() {
+        let disk = 4096 * MIB;
+        // Ohne bekannte Partitionstabelle kein Schreibtest
+        assert_eq!(benchmark_write_region(disk, None), None);
+        // Leere Tabelle: erster MiB und letzter MiB bleiben frei
+        assert_eq!(benchmark_write_region(disk, Some(&[])), Some((MIB, BENCHMARK_WRITE_BYTES)));
+
+        // Groesste Luecke zwischen zwei Partitionen, unsortierte Eingabe
+        let parts = [(3000 * MIB, 1095 * MIB), (MIB, 2000 * MIB)];
+        assert_eq!(benchmark_write_region(disk, Some(&parts)), Some((2001 * MIB, BENCHMARK_WRITE_BYTES)));
+
+        // Voll belegt bzw. nur kleine Restluecken: kein Schreibtest
+        let full = [(MIB, 4094 * MIB)];
+        assert_eq!(benchmark_write_region(disk, Some(&full)), None);
+        let tight = [(MIB, 2000 * MIB), (2004 * MIB, 2091 * MIB)];
+        assert_eq!(benchmark_write_region(disk, Some(&tight)), None);
+    }
+
+    #[test]
+    fn smart_standby_is_detected_from_smartctl_messages() {
+        let standby = json!({
+            "smartctl": {
+                "exit_status": 2,
+                "messages": [{ "string": "Device is in STANDBY mode, exit(2)", "severity": "information" }]
+            }
+        });
+        assert!(smart_reports_standby(&standby));
+
+        let active = json!({ "smartctl": { "exit_status": 0 }, "power_on_time": { "hours": 1200 } });
+        assert!(!smart_reports_standby(&active));
+    }
+
+    fn zip_with(entries: &[&str]) -> zip::ZipArchive<std::io::Cursor<Vec<u8>>> {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default();
+        for name in entries {
+            if name.ends_with('/') {
+                writer.add_directory(*name, options).expect("dir");
+            } else {
+                writer.start_file(*name, options).expect("file");
+                writer.write_all(b"image").expect("write");
+            }
+        }
+        let cursor = writer.finish().expect("finish");
+        zip::ZipArchive::new(cursor).expect("archive")
+    }
+
+    #[test]
+    fn zip_image_skips_directories_and_rejects_multiple_files() {
+        assert_eq!(zip_image_index(&mut zip_with(&["raspios/", "raspios/image.img"])), Ok(1));
+        assert!(zip_image_index(&mut zip_with(&["a.img", "b.img"])).is_err());
+        assert!(zip_image_index(&mut zip_with(&["empty/"])).is_err());
+    }
+
+    #[test]
+    fn gzip_trailer_size_is_unknown_once_it_could_wrap() {
+        assert_eq!(gzip_trailer_size(1024 * 1024, 512 * 1024 * 1024), Some(512 * 1024 * 1024));
+        // 6 GiB entpackt steht als 2 GiB im Trailer
+        let wrapped = (6u64 << 30) as u32;
+        assert_eq!(gzip_trailer_size(1 << 30, wrapped), None);
+        assert_eq!(gzip_trailer_size(5 * 1024 * 1024, 0), None);
+    }
+
+    #[test]
+    fn secure_erase_levels_map_to_matching_diskutil_levels() {
+        assert_eq!(diskutil_secure_erase_level(0), Ok(0));
+        assert_eq!(diskutil_secure_erase_level(1), Ok(1));
+        assert_eq!(diskutil_secure_erase_level(2), Ok(2));
+        // diskutil 3 waere Gutmann mit 35 Durchgaengen, nicht unser 3-Pass-Level
+        assert_eq!(diskutil_secure_erase_level(3), Ok(4));
+        assert!(diskutil_secure_erase_level(5).is_err());
+    }
+
+    #[test]
+    fn apfs_list_volumes_round_trips_all_fields() {
+        let plist = fixture(APFS_LIST_APPLE_SILICON);
+        let container = parse_apfs_container(&plist, "disk3").expect("container");
+
+        assert_eq!(container["containerIdentifier"], "disk3");
+        assert_eq!(container["containerUuid"], "8D4E1E4C-6B0A-4B7A-9F6C-2C1F0D7E5A11");
+        assert_eq!(container["capacity"], 494384795648u64);
+        assert_eq!(container["capacityFree"], 263012085760u64);
+
+        let volumes = container["volumes"].as_array().expect("volumes");
+        assert_eq!(volumes.len(), 3);
+
+        let system = &volumes[0];
+        assert_eq!(system["identifier"], "disk3s1");
+        assert_eq!(system["name"], "Macintosh HD");
+        assert_eq!(system["roles"], json!(["System"]));
+        assert_eq!(system["volumeGroupUuid"], "5A6C4F0E-2D3B-4E1A-8C7D-9B0A1F2E3D4C");
+        assert_eq!(system["volumeGroupRole"], "System");
+        assert_eq!(system["volumeGroupName"], "Macintosh HD");
+        assert_eq!(system["sealed"], true);
+        assert_eq!(system["encrypted"], false);
+        assert_eq!(system["locked"], false);
+        assert_eq!(system["size"], 10825658368u64);
+        assert_eq!(system["mountPoint"], Value::Null);
+
+        let data = &volumes[1];
+        assert_eq!(data["identifier"], "disk3s5");
+        assert_eq!(data["roles"], json!(["Data"]));
+        assert_eq!(data["volumeGroupUuid"], system["volumeGroupUuid"]);
+        assert_eq!(data["volumeGroupRole"], "Data");
+        assert_eq!(data["volumeGroupName"], "Macintosh HD");
+        assert_eq!(data["sealed"], false);
+        assert_eq!(data["encrypted"], true);
+        assert_eq!(data["reserve"], 1073741824u64);
+        assert_eq!(data["quota"], 214748364800u64);
+        assert_eq!(data["mountPoint"], "/System/Volumes/Data");
+
+        let vm = &volumes[2];
+        assert_eq!(vm["roles"], json!(["VM"]));
+        assert_eq!(vm["volumeGroupUuid"], Value::Null);
+        assert_eq!(vm["volumeGroupRole"], Value::Null);
+        assert_eq!(vm["sealed"], Value::Null);
+    }
+
+    #[test]
+    fn apfs_container_matches_by_physical_store() {
+        let plist = fixture(APFS_LIST_APPLE_SILICON);
+        let container = parse_apfs_container(&plist, "disk0s2").expect("container");
+        assert_eq!(container["containerIdentifier"], "disk3");
+        assert!(parse_apfs_container(&plist, "disk9").is_err());
+    }
+
+    #[test]
+    fn partition_info_reads_offset_size_and_parent() {
+        let info = parse_partition_info(&fixture(INFO_INTEL_BOOTCAMP)).expect("info");
+        assert_eq!(info.device, "/dev/disk0s3");
+        assert_eq!(info.disk, "/dev/disk0");
+        assert_eq!(info.partition_offset, 400209735680);
+        assert_eq!(info.partition_size, 100000595968);
+        assert_eq!(info.block_size, 512);
+        assert_eq!(info.max_end, 400209735680 + 100000595968);
+
+        let info = parse_partition_info(&fixture(INFO_MBR_FAT32)).expect("info");
+        assert_eq!(info.device, "/dev/disk4s1");
+        assert_eq!(info.disk, "/dev/disk4");
+        assert_eq!(info.partition_offset, 1048576);
+        assert_eq!(info.block_size, 4096);
+    }
+
+    #[test]
+    fn partition_info_rejects_apfs_volume() {
+        let err = parse_partition_info(&fixture(INFO_APFS_VOLUME)).err().expect("error");
+        assert_eq!(err, "PartitionOffset missing");
+    }
+
+    #[test]
+    fn partition_identifiers_cover_gpt_and_mbr() {
+        let ids = parse_partition_identifiers(&fixture(LIST_INTEL_DISK0)).expect("ids");
+        assert_eq!(ids, vec!["disk0s1", "disk0s2", "disk0s3"]);
+
+        let ids = parse_partition_identifiers(&fixture(LIST_MBR_DISK4)).expect("ids");
+        assert_eq!(ids, vec!["disk4s1", "disk4s2"]);
+    }
+
+    #[test]
+    fn partition_by_label_searches_all_disks() {
+        let plist = fixture(LIST_APPLE_SILICON_ALL);
+        assert_eq!(parse_partition_by_label(&plist, "USBSTICK").expect("lookup"), Some("disk4s1".to_string()));
+        assert_eq!(parse_partition_by_label(&plist, "WININSTALL").expect("lookup"), None);
+
+        let plist = fixture(LIST_INTEL_DISK0);
+        assert_eq!(parse_partition_by_label(&plist, "BOOTCAMP").expect("lookup"), Some("disk0s3".to_string()));
+
+        let plist = fixture(INFO_MBR_FAT32);
+        assert!(parse_partition_by_label(&plist, "USBSTICK").is_err());
+    }
+
+    #[test]
+    fn container_matches_reference_store_and_volume() {
+        let plist = fixture(APFS_LIST_APPLE_SILICON);
+        let containers = plist
+            .as_dictionary()
+            .and_then(|d| d.get("Containers"))
+            .and_then(|v| v.as_array())
+            .expect("containers");
+        let container = containers[0].as_dictionary().expect("container");
+
+        assert!(container_matches(container, "disk3"));
+        assert!(container_matches(container, "disk0s2"));
+        assert!(container_matches(container, "disk3s5"));
+        assert!(!container_matches(container, "disk4"));
+    }
+
+    #[test]
+    fn device_identifier_accepts_disk_forms() {
+        for identifier in ["disk0", "disk0s1", "disk3s1s1", "/dev/disk4s2", "/dev/rdisk4", "rdisk12s3"] {
+            assert!(validate_device_identifier(identifier, "deviceIdentifier").is_ok(), "{identifier}");
+        }
+    }
+
+    #[test]
+    fn device_identifier_rejects_injection() {
+        for identifier in [
+            "disk0s1; rm -rf",
+            "disk0s1 -force",
+            "-force",
+            "disk",
+            "disk0s",
+            "disk0s1/../disk1",
+            "/dev/../etc/passwd",
+            "disk0$(reboot)",
+            "",
+        ] {
+            let err = validate_device_identifier(identifier, "deviceIdentifier").expect_err(identifier);
+            assert!(err.starts_with("INVALID_IDENTIFIER:"), "{identifier}");
+        }
+    }
+
+    #[test]
+    fn whole_disk_identifiers_exclude_slices() {
+        for identifier in ["disk2", "/dev/disk2", "/dev/rdisk12"] {
+            assert!(is_whole_disk_identifier(identifier), "{identifier}");
+        }
+        for identifier in ["disk2s1", "/dev/disk2s1", "disk", "disk2s"] {
+            assert!(!is_whole_disk_identifier(identifier), "{identifier}");
+        }
+    }
+
+    #[test]
+    fn handlers_reject_invalid_identifiers_before_running_commands() {
+        // Gueltiger Token, aber manipulierter Identifier: scheitert vor jeder Bestaetigung
+        let payload = json!({ "partitionIdentifier": "disk0s1; rm -rf /", "confirmationToken": "disk0s1" });
+        let err = handle_delete_partition(&payload).expect_err("rejected");
+        assert!(err.starts_with("INVALID_IDENTIFIER:"));
+
+        // Derselbe Payload mit sauberem Identifier passiert Identifier- und Tokenpruefung
+        let payload = json!({ "partitionIdentifier": "disk0s1", "confirmationToken": "disk0s1" });
+        let device = normalize_device(&read_device_identifier(&payload, "partitionIdentifier").expect("accepted"));
+        assert_eq!(check_confirmation_token(&device, "disk0s1", &payload), Ok(()));
+
+        let payload = json!({ "partitionIdentifier": "disk0s1", "confirmationToken": "disk0s2" });
+        let err = check_confirmation_token(&device, "disk0s1", &payload).expect_err("mismatch");
+        assert!(err.starts_with("CONFIRMATION_MISMATCH:"));
+        let payload = json!({ "partitionIdentifier": "disk0s1", "confirmToken": "disk0s1" });
+        let err = check_confirmation_token(&device, "disk0s1", &payload).expect_err("wrong key");
+        assert!(err.starts_with("CONFIRMATION_REQUIRED:"));
+
+        let payload = json!({ "deviceIdentifier": "disk2 -force" });
+        let err = handle_force_unmount(&payload).expect_err("rejected");
+        assert!(err.starts_with("INVALID_IDENTIFIER:"));
+
+        // Destruktive Handler mit Bestaetigungspflicht
+        let payload = json!({
+            "partitionIdentifier": "disk0s2 -force",
+            "volumeIdentifier": "disk1s1; rm -rf /",
+            "newSize": "10G",
+            "newStart": "1G",
+            "snapshotName": "snap",
+            "confirmationToken": "disk0s2",
+        });
+        for result in [
+            handle_resize_partition(&payload),
+            handle_move_partition(&payload),
+            handle_apfs_delete_volume(&payload),
+            handle_apfs_delete_snapshot(&payload),
+        ] {
+            let err = result.expect_err("rejected");
+            assert!(err.starts_with("INVALID_IDENTIFIER:"), "{err}");
+        }
+    }
+
+    const MIB: u64 = 1024 * 1024;
+
+    #[test]
+    fn align_mib_handles_4096_byte_blocks() {
+        assert_eq!(align_mib(5 * MIB + 123, 4096), 5 * MIB);
+        assert_eq!(align_mib(5 * MIB + 123, 512), 5 * MIB);
+        assert_eq!(alignment_unit(4096), MIB);
+        // Blockgroesse, die 1 MiB nicht teilt: Ergebnis muss auf beiden Grenzen liegen
+        let odd = align_mib(10 * MIB, 3 * 4096);
+        assert_eq!(odd % MIB, 0);
+        assert_eq!(odd % (3 * 4096), 0);
+    }
+
+    #[test]
+    fn resize_sectors_land_on_4096_byte_boundaries() {
+        let block_size = 4096;
+        let info = PartitionInfo {
+            device: "/dev/disk4s2".to_string(),
+            disk: "/dev/disk4".to_string(),
+            partition_offset: 210 * MIB,
+            partition_size: 1024 * MIB,
+            block_size,
+            physical_block_size: block_size,
+            min_start: 210 * MIB,
+            max_end: 4096 * MIB,
+        };
+        let aligned_size = align_mib(512 * MIB + 3000, info.physical_block_size.max(info.block_size));
+        let new_end = info.partition_offset + aligned_size;
+
+        let (start_sector, end_sector) = sector_range(info.partition_offset, new_end, info.block_size).expect("sectors");
+        assert_eq!(start_sector * block_size, info.partition_offset);
+        assert_eq!((end_sector + 1) * block_size, new_end);
+        assert_eq!(end_sector - start_sector + 1, 512 * MIB / block_size);
+    }
+
+    #[test]
+    fn move_sectors_land_on_4096_byte_boundaries() {
+        let block_size = 4096;
+        let size = 300 * MIB;
+        let aligned_start = align_mib(2048 * MIB + 777, block_size);
+        assert_eq!(aligned_start, 2048 * MIB);
+
+        let (start_sector, end_sector) = sector_range(aligned_start, aligned_start + size, block_size).expect("sectors");
+        assert_eq!(start_sector, aligned_start / block_size);
+        assert_eq!((end_sector + 1) * block_size, aligned_start + size);
+    }
+
+    #[test]
+    fn sector_range_rejects_unaligned_start_and_rounds_end_down() {
+        let err = sector_range(MIB + 512, 2 * MIB, 4096).expect_err("unaligned start");
+        assert!(err.contains("4096"));
+
+        // Ende mitten im Block: letzter Sektor ist der letzte vollstaendige
+        let (start_sector, end_sector) = sector_range(MIB, 2 * MIB + 100, 4096).expect("sectors");
+        assert_eq!(start_sector, 256);
+        assert_eq!(end_sector, 511);
+
+        assert!(sector_range(MIB, MIB + 100, 4096).is_err());
+    }
+
+    #[test]
+    fn resize_preview_reports_aligned_unallocated_region() {
+        let info = PartitionInfo {
+            device: "/dev/disk4s2".to_string(),
+            disk: "/dev/disk4".to_string(),
+            partition_offset: 210 * MIB,
+            partition_size: 1024 * MIB,
+            block_size: 512,
+            physical_block_size: 4096,
+            min_start: 210 * MIB,
+            max_end: 2048 * MIB,
+        };
+
+        let preview = resize_preview(&info, 512 * MIB + 3000).expect("preview");
+        assert_eq!(preview["newEnd"], json!(722 * MIB));
+        assert_eq!(preview["freedBytes"], json!(512 * MIB));
+        assert_eq!(preview["unallocatedOffset"], json!(722 * MIB));
+        assert_eq!(preview["unallocatedSize"], json!(1326 * MIB));
+        assert_eq!(preview["canCreatePartition"], json!(true));
+
+        let tight = resize_preview(&info, 1790 * MIB).expect("preview");
+        assert_eq!(tight["unallocatedSize"], json!(48 * MIB));
+        assert_eq!(tight["canCreatePartition"], json!(false));
+
+        assert!(resize_preview(&info, 4096 * MIB).is_err());
+    }
+
+    #[test]
+    fn resize_output_parsers_extract_percent_and_final_size() {
+        assert_eq!(parse_tool_percent("  45.67 percent completed"), Some(45));
+        assert_eq!(parse_tool_percent("Relocating: 100.00 percent completed"), Some(100));
+        assert_eq!(parse_tool_percent("Checking filesystem consistency ..."), None);
+
+        let resize2fs = "resize2fs 1.47.0 (5-Feb-2023)\nResizing the filesystem on /dev/disk4s2 to 262144 (4k) blocks.\nThe filesystem on /dev/disk4s2 is now 262144 (4k) blocks long.\n";
+        assert_eq!(parse_resize_final_size("ext4", resize2fs), Some(262144 * 4096));
+
+        let ntfsresize = "Current volume size: 2147479552 bytes (2148 MB)\nNew volume size    : 1073737728 bytes (1074 MB)\nSuccessfully resized NTFS on device '/dev/disk4s2'.\n";
+        assert_eq!(parse_resize_final_size("ntfs", ntfsresize), Some(1073737728));
+
+        assert_eq!(parse_resize_final_size("ntfs", "ntfsresize: nothing to do"), None);
+        assert_eq!(parse_resize_final_size("fat32", resize2fs), None);
+    }
+
+    #[test]
+    fn wipefs_output_lists_removed_signatures() {
+        let output = "/dev/disk4: 8 bytes were erased at offset 0x00000200 (gpt): 45 46 49 20 50 41 52 54\n\
+/dev/disk4: 8 bytes were erased at offset 0x3b9fffe00 (gpt): 45 46 49 20 50 41 52 54\n\
+/dev/disk4: 2 bytes were erased at offset 0x000001fe (PMBR): 55 aa\n\
+/dev/disk4: calling ioctl to re-read partition table: Success\n";
+        let removed = parse_wipefs_output(output);
+        assert_eq!(removed.len(), 3);
+        assert_eq!(removed[0], ("gpt".to_string(), 0x200, 8));
+        assert_eq!(removed[1].1, 0x3b9fffe00);
+        assert_eq!(removed[2], ("PMBR".to_string(), 0x1fe, 2));
+        assert!(parse_wipefs_output("").is_empty());
+    }
+
+    #[test]
+    fn superblock_uuids_match_blkid_format() {
+        let mut ext4 = vec![0u8; 128 * 1024];
+        ext4[0x438] = 0x53;
+        ext4[0x439] = 0xEF;
+        ext4[0x460] = 0x40;
+        let raw_uuid = [
+            0x3e, 0x6b, 0xe9, 0xde, 0x81, 0x39, 0x4b, 0x5a, 0x9f, 0x1d, 0x2c, 0x0a, 0x7e, 0x44, 0x10, 0x01,
+        ];
+        ext4[1024 + 0x68..1024 + 0x78].copy_from_slice(&raw_uuid);
+        assert_eq!(detect_fs_magic(&ext4), Some("ext4"));
+        assert_eq!(
+            fs_uuid_from_superblock("ext4", &ext4).as_deref(),
+            Some("3e6be9de-8139-4b5a-9f1d-2c0a7e441001")
+        );
+
+        let mut fat = vec![0u8; 512];
+        fat[0x43..0x47].copy_from_slice(&[0xEF, 0xBE, 0xAD, 0xDE]);
+        assert_eq!(fs_uuid_from_superblock("fat32", &fat).as_deref(), Some("DEAD-BEEF"));
+
+        let mut ntfs = vec![0u8; 512];
+        ntfs[0x48..0x50].copy_from_slice(&[0x90, 0xEF, 0x78, 0x56, 0xCD, 0xAB, 0x34, 0x12]);
+        assert_eq!(fs_uuid_from_superblock("ntfs", &ntfs).as_deref(), Some("1234ABCD5678EF90"));
+
+        assert_eq!(detect_fs_magic(F2FS_SUPERBLOCK), Some("f2fs"));
+        assert_eq!(
+            fs_uuid_from_superblock("f2fs", F2FS_SUPERBLOCK).as_deref(),
+            Some("3f1c2a7e-5b64-4d0e-9a21-7c8e5f4d3b12")
+        );
+        assert_eq!(detect_fs_magic(FAT16_BOOT_SECTOR), Some("fat"));
+        assert_eq!(fs_uuid_from_superblock("fat", FAT16_BOOT_SECTOR).as_deref(), Some("1A2B-3C4D"));
+
+        assert_eq!(fs_uuid_from_superblock("btrfs", &fat), None);
+        assert_eq!(fs_uuid_from_superblock("xfs", &vec![0u8; 512]), None);
+    }
+
+    #[test]
+    fn label_constraints_drive_validation() {
+        let expected = [("fat32", 11), ("exfat", 15), ("ntfs", 32), ("ext4", 16), ("btrfs", 255), ("xfs", 12)];
+        for (fs, max) in expected {
+            assert_eq!(driver_for(fs).expect("driver").max_label_length(), max, "{fs}");
+        }
+
+        let (label, warning) = checked_label("fat32", "backup disk 2024").expect("fat label");
+        assert_eq!(label, "BACKUP DISK");
+        assert!(warning.expect("warning").starts_with("Label converted to uppercase for fat32"));
+        assert!(checked_label("fat32", "Daten:1").is_err());
+
+        // exFAT zaehlt UTF-16-Einheiten, nicht Bytes
+        let (label, warning) = checked_label("exfat", "Übertragungen").expect("exfat label");
+        assert_eq!(label, "Übertragungen");
+        assert!(warning.is_none());
+
+        assert!(checked_label("zfs", "tank-01").is_ok());
+        assert!(checked_label("zfs", "tank/01").is_err());
+    }
+
+    #[test]
+    fn zpool_names_are_validated() {
+        for name in ["tank", "Backup_2024", "pool.a:b-c", "logs", "cache"] {
+            assert_eq!(checked_label("zfs", name).expect(name).0, name);
+        }
+        let too_long = "p".repeat(256);
+        let invalid = ["", "-f", "1tank", "_pool", "tank pool", "tank;rm", "log", "mirror1", "raidz", "draid2", "spare"];
+        for name in invalid.into_iter().chain(["c0", "c1t0d0", too_long.as_str()]) {
+            assert!(checked_label("zfs", name).is_err(), "{name}");
+        }
+    }
+
+    #[test]
+    fn mkfs_extra_args_are_allowlisted() {
+        let opts = |args: Value| json!({ "opts": { "extraArgs": args } });
+
+        let accepted = read_mkfs_options(&opts(json!(["-m", "1", "-O", "^has_journal"])), "ext4").expect("ext4 opts");
+        assert_eq!(accepted.extra_args, ["-m", "1", "-O", "^has_journal"]);
+        assert!(read_mkfs_options(&opts(json!(["-O", "compression=lz4"])), "zfs").is_ok());
+        assert!(read_mkfs_options(&json!({}), "apfs").is_ok());
+
+        // Unbekannte Schalter, fehlende Werte, Pfade und versteckte Schalter in Werten
+        assert!(read_mkfs_options(&opts(json!(["-J", "device=/dev/sdb1"])), "ext4").is_err());
+        assert!(read_mkfs_options(&opts(json!(["-m"])), "ext4").is_err());
+        assert!(read_mkfs_options(&opts(json!(["-O", "mountpoint=/etc"])), "zfs").is_err());
+        assert!(read_mkfs_options(&opts(json!(["-m", "-q"])), "xfs").is_err());
+        assert!(read_mkfs_options(&opts(json!(["-l", "logdev=sdb"])), "xfs").is_err());
+        assert!(read_mkfs_options(&opts(json!(["-v"])), "swap").is_err());
+        assert!(read_mkfs_options(&opts(json!(["-v"])), "apfs").is_err());
+    }
+}
@@ -1,14 +1,19 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use serde::Serialize;
-use std::collections::HashSet;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::io::Write;
+use std::io::{Read, Write};
 use std::os::unix::fs::MetadataExt;
 use std::path::Path;
 use std::process::{Command, Stdio};
-use std::time::UNIX_EPOCH;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant, UNIX_EPOCH};
 use sysinfo::Disks;
+use tauri::{Emitter, Manager};
 
 mod partitioning;
 
@@ -25,6 +30,18 @@ struct SystemDisk {
     device: Option<String>,
 }
 
+// Mountpoint mit zugehöriger Partition, damit die Scan-UI einen Pfad einer Disk zuordnen kann
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MountInfo {
+    mount_point: String,
+    device_identifier: Option<String>,
+    fs_type: String,
+    total: u64,
+    available: u64,
+    read_only: bool,
+}
+
 #[derive(Serialize)]
 struct FileNode {
     name: String,
@@ -33,6 +50,10 @@ struct FileNode {
     // Nivo braucht 'value' bei Blättern. Wir geben es auch bei Ordnern mit,
     // damit wir Tooltips korrekt anzeigen können.
     value: u64,
+    // Summe der st_size (logisch). Weicht auf APFS durch Clones, Kompression
+    // und Sparse-Dateien von der belegten Größe (value) ab.
+    #[serde(rename = "logicalSize")]
+    logical_size: u64,
     // Wir nutzen Box, um unendliche Rekursion im Typ zu vermeiden
     children: Option<Vec<Box<FileNode>>>,
 
@@ -43,6 +64,208 @@ struct FileNode {
     file_count: u64,
     #[serde(rename = "modifiedAt", skip_serializing_if = "Option::is_none")]
     modified_at: Option<u64>,
+    // Scan wurde abgebrochen, bevor dieser Ordner vollständig gelesen war
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    partial: bool,
+    // Ordner wurde über einen Symlink erreicht (nur mit follow_symlinks)
+    #[serde(rename = "viaSymlink", skip_serializing_if = "std::ops::Not::not")]
+    via_symlink: bool,
+    // Metadaten oder Ordnerinhalt nicht lesbar (z.B. EACCES) -> Größe unvollständig
+    #[serde(rename = "scannedOk")]
+    scanned_ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ScanResult {
+    scan_id: u64,
+    cancelled: bool,
+    root: FileNode,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DiskScanResult {
+    device_identifier: String,
+    mount_point: String,
+    // Von scan_disk selbst gemountet (sonst war das Volume schon eingehängt)
+    mounted: bool,
+    unmounted: bool,
+    root: FileNode,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ScanStarted {
+    scan_id: u64,
+    path: String,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ScanProgress {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scan_id: Option<u64>,
+    current_path: String,
+    files_seen: u64,
+    bytes_seen: u64,
+}
+
+// Logisch vs. belegt für einen Pfad. clone_shared_bytes ist nur eine Schätzung
+// (belegte Blöcke aller Dateien minus tatsächlich belegter Platz des Volumes)
+// und wird nur geliefert, wenn der Pfad ein Mountpoint ist.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AllocationSummary {
+    path: String,
+    logical_bytes: u64,
+    allocated_bytes: u64,
+    volume_used_bytes: Option<u64>,
+    clone_shared_bytes: Option<u64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DirSize {
+    total_bytes: u64,
+    file_count: u64,
+    display_size: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExtensionStat {
+    extension: String,
+    total_bytes: u64,
+    display_size: String,
+    file_count: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StaleFile {
+    path: String,
+    size: u64,
+    display_size: String,
+    modified_at: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DuplicateGroup {
+    sha256: String,
+    size: u64,
+    paths: Vec<String>,
+    // Platz, der frei wird, wenn nur eine Kopie bleibt
+    reclaimable_bytes: u64,
+    display_reclaimable: String,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct DuplicateProgress {
+    current_path: String,
+    files_hashed: u64,
+    files_total: u64,
+    bytes_hashed: u64,
+    bytes_total: u64,
+}
+
+// Kompakter Stand eines Scans (Pfad -> belegte Bytes) für diff_scans
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ScanSnapshot {
+    path: String,
+    created_at: u64,
+    sizes: HashMap<String, u64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SnapshotInfo {
+    id: String,
+    path: String,
+    created_at: u64,
+    entry_count: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ScanDiffEntry {
+    path: String,
+    // "added", "removed", "grown" oder "shrunk"
+    change: String,
+    old_size: u64,
+    new_size: u64,
+    delta: i64,
+    display_delta: String,
+}
+
+// Dateisystem, auf dem ein beliebiger Pfad liegt (statfs, ohne Helper)
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PathFilesystem {
+    path: String,
+    fs_type: String,
+    device: String,
+    mount_point: String,
+    // Allokationseinheit, erklärt die Differenz zwischen logicalSize und value
+    block_size: u64,
+    io_size: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TrashedItem {
+    original_path: String,
+    // Genauer Ort im Papierkorb, soweit ermittelbar; restore_from_trash braucht ihn auf macOS
+    trash_path: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TrashResult {
+    path: String,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    item: Option<TrashedItem>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+// Optionen für scan_directory / scan_directory_cancellable. Alle Felder
+// optional, damit ältere Frontends ohne options weiter funktionieren.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct ScanOptions {
+    // Glob-Muster gegen Datei-/Ordnernamen (z.B. "node_modules", ".git", "*.cache").
+    // Treffer werden nicht einzeln aufgeführt, sondern unter "Ignored" summiert.
+    ignore_globs: Vec<String>,
+    grouping: ScanGrouping,
+    // Symlinks auf Ordner auflösen und das Ziel mitscannen
+    follow_symlinks: bool,
+    // Wie `du -x`: nicht in andere Dateisysteme (externe Volumes, Netzlaufwerke) absteigen
+    stay_on_filesystem: bool,
+}
+
+// Wie kleine Einträge eines Ordners zu "Sonstiges" zusammengefasst werden.
+// JSON: {"percent": 1}, {"max_children": 50} oder "off".
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum ScanGrouping {
+    // Alles unter percent % der Ordnergröße (bisheriges Verhalten mit 1)
+    Percent(u64),
+    // Nur die größten N Einträge behalten, Rest gruppieren
+    MaxChildren(usize),
+    Off,
+}
+
+impl Default for ScanGrouping {
+    fn default() -> Self {
+        ScanGrouping::Percent(1)
+    }
 }
 
 // --- HILFS-STRUCTS FÜR ALGORITHMUS ---
@@ -54,6 +277,157 @@ struct FileID {
     ino: u64,
 }
 
+// Progress-Events höchstens alle 500ms bzw. alle 10.000 Einträge
+const SCAN_PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+const SCAN_PROGRESS_ENTRIES: u64 = 10_000;
+
+type ScanProgressFn<'a> = Box<dyn FnMut(&ScanProgress) + 'a>;
+
+// Zustand, der während eines Scans durch die Rekursion gereicht wird
+struct ScanContext<'a> {
+    seen: HashSet<FileID>,
+    cancel: Option<Arc<AtomicBool>>,
+    scan_id: Option<u64>,
+    on_progress: Option<ScanProgressFn<'a>>,
+    files_seen: u64,
+    bytes_seen: u64,
+    entries_since_emit: u64,
+    last_emit: Instant,
+    ignore: GlobSet,
+    grouping: ScanGrouping,
+    follow_symlinks: bool,
+    // Kanonische Ziele bereits verfolgter Symlinks (Schutz gegen Zyklen)
+    followed: HashSet<std::path::PathBuf>,
+    stay_on_filesystem: bool,
+    // dev des Scan-Startpunkts, gesetzt in scan_recursive bei depth 0
+    root_dev: Option<u64>,
+}
+
+impl<'a> ScanContext<'a> {
+    fn new(cancel: Option<Arc<AtomicBool>>) -> Self {
+        ScanContext {
+            seen: HashSet::new(),
+            cancel,
+            scan_id: None,
+            on_progress: None,
+            files_seen: 0,
+            bytes_seen: 0,
+            entries_since_emit: 0,
+            last_emit: Instant::now(),
+            ignore: GlobSet::empty(),
+            grouping: ScanGrouping::default(),
+            follow_symlinks: false,
+            followed: HashSet::new(),
+            stay_on_filesystem: false,
+            root_dev: None,
+        }
+    }
+
+    fn with_options(mut self, options: &ScanOptions) -> Self {
+        self.ignore = build_ignore_set(&options.ignore_globs);
+        self.grouping = options.grouping;
+        self.follow_symlinks = options.follow_symlinks;
+        self.stay_on_filesystem = options.stay_on_filesystem;
+        self
+    }
+
+    fn with_progress(mut self, scan_id: Option<u64>, on_progress: impl FnMut(&ScanProgress) + 'a) -> Self {
+        self.scan_id = scan_id;
+        self.on_progress = Some(Box::new(on_progress));
+        self
+    }
+
+    fn record_entry(&mut self, path: &str, is_dir: bool, size: u64) {
+        if !is_dir {
+            self.files_seen += 1;
+        }
+        self.bytes_seen += size;
+        self.entries_since_emit += 1;
+
+        if self.entries_since_emit < SCAN_PROGRESS_ENTRIES && self.last_emit.elapsed() < SCAN_PROGRESS_INTERVAL {
+            return;
+        }
+        if let Some(on_progress) = self.on_progress.as_mut() {
+            on_progress(&ScanProgress {
+                scan_id: self.scan_id,
+                current_path: path.to_string(),
+                files_seen: self.files_seen,
+                bytes_seen: self.bytes_seen,
+            });
+        }
+        self.entries_since_emit = 0;
+        self.last_emit = Instant::now();
+    }
+
+    // Metadaten des Ziels, wenn path ein Symlink auf einen noch nicht gescannten Ordner ist.
+    // Ordner landen mit follow_symlinks im seen-Set, ein Link auf einen Vorfahren wird so nicht verfolgt.
+    fn follow_dir_link(&mut self, path: &Path) -> Option<fs::Metadata> {
+        let target = fs::metadata(path).ok()?;
+        if !target.is_dir() {
+            return None;
+        }
+        let target_id = FileID {
+            dev: target.dev(),
+            ino: target.ino(),
+        };
+        if self.seen.contains(&target_id) || self.crosses_filesystem(target.dev()) {
+            return None;
+        }
+        let canonical = fs::canonicalize(path).ok()?;
+        if !self.followed.insert(canonical) {
+            return None;
+        }
+        Some(target)
+    }
+
+    fn crosses_filesystem(&self, dev: u64) -> bool {
+        self.stay_on_filesystem && self.root_dev.is_some_and(|root_dev| root_dev != dev)
+    }
+
+    fn is_ignored(&self, name: &std::ffi::OsStr) -> bool {
+        !self.ignore.is_empty() && self.ignore.is_match(name)
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .map(|flag| flag.load(Ordering::Relaxed))
+            .unwrap_or(false)
+    }
+}
+
+// Ungültige Muster werden übersprungen, damit ein Tippfehler nicht den ganzen Scan verhindert
+fn build_ignore_set(patterns: &[String]) -> GlobSet {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        if let Ok(glob) = Glob::new(pattern) {
+            builder.add(glob);
+        }
+    }
+    builder.build().unwrap_or_else(|_| GlobSet::empty())
+}
+
+// Laufende abbrechbare Scans (scan id -> Abbruch-Flag)
+static ACTIVE_SCANS: OnceLock<Mutex<HashMap<u64, Arc<AtomicBool>>>> = OnceLock::new();
+static NEXT_SCAN_ID: AtomicU64 = AtomicU64::new(1);
+
+fn register_scan() -> (u64, Arc<AtomicBool>) {
+    let scan_id = NEXT_SCAN_ID.fetch_add(1, Ordering::Relaxed);
+    let flag = Arc::new(AtomicBool::new(false));
+    let lock = ACTIVE_SCANS.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Ok(mut guard) = lock.lock() {
+        guard.insert(scan_id, flag.clone());
+    }
+    (scan_id, flag)
+}
+
+fn unregister_scan(scan_id: u64) {
+    let lock = ACTIVE_SCANS.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Ok(mut guard) = lock.lock() {
+        guard.remove(&scan_id);
+    }
+}
+
 // --- COMMANDS ---
 
 #[tauri::command]
@@ -98,6 +472,74 @@ fn get_disks(include_system: bool) -> Vec<SystemDisk> {
     disks_list
 }
 
+#[tauri::command(async)]
+fn list_mounts() -> Vec<MountInfo> {
+    let disks = Disks::new_with_refreshed_list();
+    let mut seen_mounts = HashSet::new();
+    let mut mounts = Vec::new();
+
+    for disk in disks.list() {
+        let mount_point = disk.mount_point().to_string_lossy().to_string();
+        if !seen_mounts.insert(mount_point.clone()) {
+            continue;
+        }
+        let name = disk.name().to_string_lossy().to_string();
+        let (device_identifier, fs_type) = match mount_device_info(&mount_point) {
+            Some((identifier, fs_type)) => (Some(identifier), fs_type),
+            None => (name.strip_prefix("/dev/").map(|id| id.to_string()), None),
+        };
+
+        mounts.push(MountInfo {
+            device_identifier,
+            fs_type: fs_type.unwrap_or_else(|| disk.file_system().to_string_lossy().to_string()),
+            total: disk.total_space(),
+            available: disk.available_space(),
+            read_only: mount_is_read_only(&mount_point),
+            mount_point,
+        });
+    }
+
+    mounts.sort_by(|a, b| a.mount_point.cmp(&b.mount_point));
+    mounts
+}
+
+// (DeviceIdentifier, FilesystemType) laut diskutil info; sysinfo kennt auf macOS nur den Volumenamen
+fn mount_device_info(mount_point: &str) -> Option<(String, Option<String>)> {
+    #[cfg(target_os = "macos")]
+    {
+        let output = Command::new("diskutil")
+            .args(["info", "-plist", mount_point])
+            .output()
+            .ok()
+            .filter(|o| o.status.success())?;
+        let plist = plist::Value::from_reader_xml(&output.stdout[..]).ok()?;
+        let dict = plist.as_dictionary()?;
+        let identifier = dict.get("DeviceIdentifier").and_then(|v| v.as_string())?.to_string();
+        let fs_type = dict
+            .get("FilesystemType")
+            .and_then(|v| v.as_string())
+            .map(|fs| fs.to_string());
+        return Some((identifier, fs_type));
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = mount_point;
+        None
+    }
+}
+
+fn mount_is_read_only(mount_point: &str) -> bool {
+    let Ok(c_path) = std::ffi::CString::new(mount_point) else {
+        return false;
+    };
+    let mut stats: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stats) } != 0 {
+        return false;
+    }
+    stats.f_flag & libc::ST_RDONLY != 0
+}
+
 fn get_unmounted_disks(mounted_points: &HashSet<String>, include_system: bool) -> Vec<SystemDisk> {
     #[cfg(target_os = "macos")]
     {
@@ -217,16 +659,299 @@ fn collect_unmounted_from_dict(
     });
 }
 
-#[tauri::command]
-fn scan_directory(path: String) -> FileNode {
-    // HashSet für Hardlink-Erkennung (Baobab Logik)
-    let mut seen_inodes = HashSet::new();
+#[tauri::command(async)]
+fn scan_directory(window: tauri::Window, path: String, options: Option<ScanOptions>) -> FileNode {
+    let options = options.unwrap_or_default();
+    // Kontext mit HashSet für Hardlink-Erkennung (Baobab Logik)
+    let mut ctx = ScanContext::new(None)
+        .with_options(&options)
+        .with_progress(None, |progress| {
+            let _ = window.emit("scan-progress", progress);
+        });
 
     // Starte Scan mit max Tiefe 5 (Performance)
-    scan_recursive(Path::new(&path), 0, 5, &mut seen_inodes)
+    scan_recursive(Path::new(&path), 0, 5, &mut ctx)
+}
+
+// Wie scan_directory, aber über cancel_scan abbrechbar. Die scan id wird per
+// "scan-started" Event sofort an das Frontend gemeldet.
+#[tauri::command(async)]
+fn scan_directory_cancellable(window: tauri::Window, path: String, options: Option<ScanOptions>) -> ScanResult {
+    let options = options.unwrap_or_default();
+    let (scan_id, flag) = register_scan();
+    let _ = window.emit(
+        "scan-started",
+        ScanStarted {
+            scan_id,
+            path: path.clone(),
+        },
+    );
+
+    let mut ctx = ScanContext::new(Some(flag))
+        .with_options(&options)
+        .with_progress(Some(scan_id), |progress| {
+            let _ = window.emit("scan-progress", progress);
+        });
+    let root = scan_recursive(Path::new(&path), 0, 5, &mut ctx);
+    let cancelled = ctx.is_cancelled();
+    unregister_scan(scan_id);
+
+    ScanResult {
+        scan_id,
+        cancelled,
+        root,
+    }
 }
 
-fn scan_recursive(path: &Path, depth: usize, max_depth: usize, seen: &mut HashSet<FileID>) -> FileNode {
+// Scan direkt aus der Disk-Liste: nicht eingehängte Volumes werden über mount_volume
+// gemountet und mit eject_after danach wieder ausgehängt (nur das Volume, nicht die Disk).
+#[tauri::command(async)]
+fn scan_disk(
+    window: tauri::Window,
+    device_identifier: String,
+    eject_after: Option<bool>,
+    options: Option<ScanOptions>,
+) -> Result<DiskScanResult, String> {
+    let options = options.unwrap_or_default();
+    let (mount_point, mounted) = match device_mount_point(&device_identifier) {
+        Some(mount_point) => (mount_point, false),
+        None => {
+            partitioning::mount_volume(device_identifier.clone())?;
+            let mount_point = device_mount_point(&device_identifier)
+                .ok_or_else(|| format!("{device_identifier} has no mount point after mounting"))?;
+            (mount_point, true)
+        }
+    };
+
+    let mut ctx = ScanContext::new(None)
+        .with_options(&options)
+        .with_progress(None, |progress| {
+            let _ = window.emit("scan-progress", progress);
+        });
+    let root = scan_recursive(Path::new(&mount_point), 0, 5, &mut ctx);
+    drop(ctx);
+
+    // Nur selbst gemountete Volumes wieder aushängen; ein Fehler dabei macht den Scan nicht ungültig
+    let unmounted =
+        mounted && eject_after.unwrap_or(false) && partitioning::unmount_volume(device_identifier.clone()).is_ok();
+
+    Ok(DiskScanResult {
+        device_identifier,
+        mount_point,
+        mounted,
+        unmounted,
+        root,
+    })
+}
+
+// MountPoint laut diskutil info; None, wenn das Volume nicht eingehängt ist
+fn device_mount_point(device_identifier: &str) -> Option<String> {
+    #[cfg(target_os = "macos")]
+    {
+        let device = if device_identifier.starts_with("/dev/") {
+            device_identifier.to_string()
+        } else {
+            format!("/dev/{device_identifier}")
+        };
+        let output = Command::new("diskutil")
+            .args(["info", "-plist", &device])
+            .output()
+            .ok()
+            .filter(|o| o.status.success())?;
+        let plist = plist::Value::from_reader_xml(&output.stdout[..]).ok()?;
+        return plist
+            .as_dictionary()?
+            .get("MountPoint")
+            .and_then(|v| v.as_string())
+            .filter(|mount_point| !mount_point.is_empty())
+            .map(|mount_point| mount_point.to_string());
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = device_identifier;
+        None
+    }
+}
+
+#[tauri::command]
+fn cancel_scan(scan_id: u64) -> Result<(), String> {
+    let lock = ACTIVE_SCANS.get_or_init(|| Mutex::new(HashMap::new()));
+    let guard = lock.lock().map_err(|_| "Scan registry unavailable".to_string())?;
+    match guard.get(&scan_id) {
+        Some(flag) => {
+            flag.store(true, Ordering::Relaxed);
+            Ok(())
+        }
+        None => Err("No active scan with this id".to_string()),
+    }
+}
+
+// Belegung pro Dateiendung (kleingeschrieben), Dateien ohne Endung unter "(none)"
+#[tauri::command(async)]
+fn scan_by_extension(path: String) -> Vec<ExtensionStat> {
+    let mut ctx = ScanContext::new(None);
+    let mut totals: HashMap<String, (u64, u64)> = HashMap::new();
+
+    walk_files(Path::new(&path), &mut ctx, &mut |file_path, meta, size| {
+        if meta.is_dir() {
+            return;
+        }
+        let extension = file_path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_else(|| "(none)".to_string());
+        let entry = totals.entry(extension).or_insert((0, 0));
+        entry.0 += size;
+        entry.1 += 1;
+    });
+
+    let mut stats: Vec<ExtensionStat> = totals
+        .into_iter()
+        .map(|(extension, (total_bytes, file_count))| ExtensionStat {
+            extension,
+            total_bytes,
+            display_size: format_bytes(total_bytes),
+            file_count,
+        })
+        .collect();
+    stats.sort_by_key(|stat| std::cmp::Reverse(stat.total_bytes));
+    stats
+}
+
+// Standardanzahl für scan_stale_files, wenn das Frontend kein limit mitgibt
+const STALE_FILES_DEFAULT_LIMIT: usize = 100;
+
+// Größte Dateien, die seit older_than_days nicht mehr geändert wurden (z.B. alte Downloads, VM-Images).
+// Hardlinks zählen über walk_files nur einmal.
+#[tauri::command(async)]
+fn scan_stale_files(path: String, older_than_days: u64, limit: Option<usize>) -> Vec<StaleFile> {
+    let now = std::time::SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let cutoff = now.saturating_sub(older_than_days.saturating_mul(24 * 60 * 60));
+    let mut ctx = ScanContext::new(None);
+    let mut stale: Vec<StaleFile> = Vec::new();
+
+    walk_files(Path::new(&path), &mut ctx, &mut |file_path, meta, size| {
+        if meta.is_dir() {
+            return;
+        }
+        let Some(modified_at) = meta
+            .modified()
+            .ok()
+            .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+        else {
+            return;
+        };
+        if modified_at > cutoff {
+            return;
+        }
+        stale.push(StaleFile {
+            path: file_path.to_string_lossy().to_string(),
+            size,
+            display_size: format_bytes(size),
+            modified_at,
+        });
+    });
+
+    stale.sort_by_key(|file| std::cmp::Reverse(file.size));
+    stale.truncate(limit.unwrap_or(STALE_FILES_DEFAULT_LIMIT));
+    stale
+}
+
+// Identische Dateien ab min_size finden. Erst nach Größe gruppieren, gehasht wird nur
+// innerhalb gleich großer Gruppen. Hardlinks meldet walk_files nur einmal, sie tauchen
+// daher nicht als verschwendete Duplikate auf.
+#[tauri::command(async)]
+fn find_duplicates(window: tauri::Window, path: String, min_size: u64) -> Vec<DuplicateGroup> {
+    let mut ctx = ScanContext::new(None).with_progress(None, |progress| {
+        let _ = window.emit("scan-progress", progress);
+    });
+    let mut by_size: HashMap<u64, Vec<std::path::PathBuf>> = HashMap::new();
+
+    walk_files(Path::new(&path), &mut ctx, &mut |file_path, meta, _| {
+        if !meta.is_file() || meta.size() == 0 || meta.size() < min_size {
+            return;
+        }
+        by_size.entry(meta.size()).or_default().push(file_path.to_path_buf());
+    });
+    drop(ctx);
+
+    let candidates: Vec<(u64, Vec<std::path::PathBuf>)> =
+        by_size.into_iter().filter(|(_, paths)| paths.len() > 1).collect();
+    let files_total: u64 = candidates.iter().map(|(_, paths)| paths.len() as u64).sum();
+    let bytes_total: u64 = candidates
+        .iter()
+        .map(|(size, paths)| size * paths.len() as u64)
+        .sum();
+
+    let mut files_hashed: u64 = 0;
+    let mut bytes_hashed: u64 = 0;
+    let mut last_emit = Instant::now();
+    let mut groups: Vec<DuplicateGroup> = Vec::new();
+
+    for (size, paths) in candidates {
+        let mut by_hash: HashMap<String, Vec<String>> = HashMap::new();
+        for file_path in paths {
+            let path_string = file_path.to_string_lossy().to_string();
+            if let Ok(digest) = sha256_file(&file_path) {
+                by_hash.entry(digest).or_default().push(path_string.clone());
+            }
+            files_hashed += 1;
+            bytes_hashed += size;
+            if last_emit.elapsed() >= SCAN_PROGRESS_INTERVAL || files_hashed == files_total {
+                let _ = window.emit(
+                    "duplicates-progress",
+                    DuplicateProgress {
+                        current_path: path_string,
+                        files_hashed,
+                        files_total,
+                        bytes_hashed,
+                        bytes_total,
+                    },
+                );
+                last_emit = Instant::now();
+            }
+        }
+
+        for (sha256, mut paths) in by_hash {
+            if paths.len() < 2 {
+                continue;
+            }
+            paths.sort();
+            let reclaimable_bytes = size * (paths.len() as u64 - 1);
+            groups.push(DuplicateGroup {
+                sha256,
+                size,
+                paths,
+                reclaimable_bytes,
+                display_reclaimable: format_bytes(reclaimable_bytes),
+            });
+        }
+    }
+
+    groups.sort_by_key(|group| std::cmp::Reverse(group.reclaimable_bytes));
+    groups
+}
+
+fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1024 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn scan_recursive(path: &Path, depth: usize, max_depth: usize, ctx: &mut ScanContext) -> FileNode {
     let name = path
         .file_name()
         .unwrap_or(path.as_os_str())
@@ -234,11 +959,33 @@ fn scan_recursive(path: &Path, depth: usize, max_depth: usize, seen: &mut HashSe
         .to_string();
     let path_string = path.to_string_lossy().to_string();
 
-    // 1. Metadaten holen (Fehler ignorieren -> Größe 0)
-    let meta = fs::symlink_metadata(path).ok();
+    // 1. Metadaten holen (Fehler merken, Größe bleibt 0)
+    let mut error: Option<String> = None;
+    let meta = match fs::symlink_metadata(path) {
+        Ok(m) => Some(m),
+        Err(e) => {
+            error = Some(e.to_string());
+            None
+        }
+    };
+    let mut via_symlink = false;
+    let meta = match meta {
+        Some(m) if ctx.follow_symlinks && m.file_type().is_symlink() => match ctx.follow_dir_link(path) {
+            Some(target) => {
+                via_symlink = true;
+                Some(target)
+            }
+            None => Some(m),
+        },
+        other => other,
+    };
+    if depth == 0 {
+        ctx.root_dev = meta.as_ref().map(|m| m.dev());
+    }
 
     // 2. Größe berechnen (Baobab Style: Allocated Blocks)
     let mut size = 0;
+    let mut logical_size = 0;
     let mut is_dir = false;
     let mut modified_at: Option<u64> = None;
 
@@ -257,24 +1004,93 @@ fn scan_recursive(path: &Path, depth: usize, max_depth: usize, seen: &mut HashSe
             ino: m.ino(),
         };
 
-        if is_dir || seen.insert(file_id) {
+        if is_dir && ctx.follow_symlinks {
+            ctx.seen.insert(file_id);
+        }
+
+        if is_dir || ctx.seen.insert(file_id) {
             size = m.blocks() * 512;
+            if !is_dir {
+                logical_size = m.size();
+            }
         } else {
             size = 0;
         }
     }
 
+    ctx.record_entry(&path_string, is_dir, size);
+
     // 3. Rekursion (nur wenn Ordner und Tiefe ok)
     let mut children = Vec::new();
     let mut file_count: u64 = if is_dir { 0 } else { 1 };
+    let mut partial = false;
+    // Ignorierte Einträge zählen weiter zur Größe, erscheinen aber nur als Summe
+    let mut ignored_sum: u64 = 0;
+    let mut ignored_logical: u64 = 0;
+    let mut ignored_count: u64 = 0;
+    // Mountpoints anderer Dateisysteme (stay_on_filesystem); ihre Größe wird nicht ermittelt und
+    // im Namen bzw. display_size ausdrücklich als nicht enthalten markiert
+    let mut other_filesystems: Vec<Box<FileNode>> = Vec::new();
 
     if is_dir && depth < max_depth {
-        if let Ok(entries) = fs::read_dir(path) {
-            for entry in entries.flatten() {
-                let child_node = scan_recursive(&entry.path(), depth + 1, max_depth, seen);
-                size += child_node.value;
-                file_count += child_node.file_count;
-                children.push(Box::new(child_node));
+        if ctx.is_cancelled() {
+            partial = true;
+        } else {
+            match fs::read_dir(path) {
+                Ok(entries) => {
+                    for entry in entries.flatten() {
+                        if ctx.is_cancelled() {
+                            partial = true;
+                            break;
+                        }
+                        // stat pro Eintrag nur, wenn die Dateisystemgrenze überhaupt geprüft wird
+                        let crosses = ctx.stay_on_filesystem
+                            && entry.metadata().is_ok_and(|entry_meta| ctx.crosses_filesystem(entry_meta.dev()));
+                        if crosses {
+                            other_filesystems.push(Box::new(FileNode {
+                                name: entry.file_name().to_string_lossy().to_string(),
+                                path: entry.path().to_string_lossy().to_string(),
+                                value: 0,
+                                logical_size: 0,
+                                children: None,
+                                display_size: "not included".to_string(),
+                                file_count: 0,
+                                modified_at: None,
+                                partial: false,
+                                via_symlink: false,
+                                scanned_ok: true,
+                                error: None,
+                            }));
+                            continue;
+                        }
+                        if ctx.is_ignored(&entry.file_name()) {
+                            // Kein Teilbaum für ignorierte Einträge, nur die Größe per walk_files
+                            let (mut entry_size, mut entry_logical, mut entry_count) = (0, 0, 0);
+                            walk_files(&entry.path(), ctx, &mut |_, meta, allocated| {
+                                entry_size += allocated;
+                                if !meta.is_dir() {
+                                    entry_logical += meta.size();
+                                    entry_count += 1;
+                                }
+                            });
+                            size += entry_size;
+                            logical_size += entry_logical;
+                            file_count += entry_count;
+                            partial |= ctx.is_cancelled();
+                            ignored_sum += entry_size;
+                            ignored_logical += entry_logical;
+                            ignored_count += entry_count;
+                            continue;
+                        }
+                        let child_node = scan_recursive(&entry.path(), depth + 1, max_depth, ctx);
+                        size += child_node.value;
+                        logical_size += child_node.logical_size;
+                        file_count += child_node.file_count;
+                        partial |= child_node.partial;
+                        children.push(Box::new(child_node));
+                    }
+                }
+                Err(e) => error = Some(e.to_string()),
             }
         }
     }
@@ -282,44 +1098,402 @@ fn scan_recursive(path: &Path, depth: usize, max_depth: usize, seen: &mut HashSe
     // 4. Sortieren & Gruppieren
     children.sort_by(|a, b| b.value.cmp(&a.value));
 
-    if size > 0 {
-        let threshold = size / 100;
+    let threshold = match ctx.grouping {
+        ScanGrouping::Percent(percent) if size > 0 => Some(size.saturating_mul(percent) / 100),
+        _ => None,
+    };
+    let cap = match ctx.grouping {
+        ScanGrouping::MaxChildren(max) => Some(max),
+        _ => None,
+    };
+
+    if threshold.is_some() || cap.is_some() {
         let mut keep = Vec::new();
         let mut other_sum: u64 = 0;
+        let mut other_logical: u64 = 0;
         let mut other_count: u64 = 0;
+        let mut other_entries: usize = 0;
 
         for child in children.into_iter() {
-            if child.value < threshold {
+            let grouped = match (threshold, cap) {
+                (Some(threshold), _) => child.value < threshold,
+                (_, Some(max)) => keep.len() >= max,
+                _ => false,
+            };
+            if grouped {
                 other_sum += child.value;
+                other_logical += child.logical_size;
                 other_count += child.file_count;
+                other_entries += 1;
             } else {
                 keep.push(child);
             }
         }
 
-        if other_sum > 0 {
+        // Beim Kappen nach Anzahl auch leere Dateien zusammenfassen, sonst fehlen sie im file_count
+        if other_sum > 0 || (cap.is_some() && other_entries > 0) {
             keep.push(Box::new(FileNode {
                 name: "Sonstiges".to_string(),
                 path: path_string.clone(),
                 value: other_sum,
+                logical_size: other_logical,
                 children: None,
                 display_size: format_bytes(other_sum),
                 file_count: other_count,
                 modified_at: None,
+                partial: false,
+                via_symlink: false,
+                scanned_ok: true,
+                error: None,
             }));
         }
 
         children = keep;
     }
 
+    // Nicht in "Sonstiges" einsortieren, damit der ignorierte Anteil sichtbar bleibt
+    if ignored_count > 0 || ignored_sum > 0 {
+        children.push(Box::new(FileNode {
+            name: "Ignored".to_string(),
+            path: path_string.clone(),
+            value: ignored_sum,
+            logical_size: ignored_logical,
+            children: None,
+            display_size: format_bytes(ignored_sum),
+            file_count: ignored_count,
+            modified_at: None,
+            partial: false,
+            via_symlink: false,
+            scanned_ok: true,
+            error: None,
+        }));
+    }
+
+    if !other_filesystems.is_empty() {
+        children.push(Box::new(FileNode {
+            name: "(other filesystem, size not included)".to_string(),
+            path: path_string.clone(),
+            value: 0,
+            logical_size: 0,
+            children: Some(other_filesystems),
+            display_size: "not included".to_string(),
+            file_count: 0,
+            modified_at: None,
+            partial: false,
+            via_symlink: false,
+            scanned_ok: true,
+            error: None,
+        }));
+    }
+
     FileNode {
         name,
         path: path_string,
         value: size,
+        logical_size,
         children: if children.is_empty() { None } else { Some(children) },
         display_size: format_bytes(size),
         file_count,
         modified_at,
+        partial,
+        via_symlink,
+        scanned_ok: error.is_none(),
+        error,
+    }
+}
+
+// Schnelle Gesamtgröße ohne Baum (z.B. als Vorab-Check vor scan_directory)
+#[tauri::command(async)]
+fn dir_size(path: String) -> DirSize {
+    let mut ctx = ScanContext::new(None);
+    let mut total_bytes: u64 = 0;
+    let mut file_count: u64 = 0;
+
+    walk_files(Path::new(&path), &mut ctx, &mut |_, meta, size| {
+        total_bytes += size;
+        if !meta.is_dir() {
+            file_count += 1;
+        }
+    });
+
+    DirSize {
+        total_bytes,
+        file_count,
+        display_size: format_bytes(total_bytes),
+    }
+}
+
+#[tauri::command(async)]
+fn get_allocation_summary(path: String) -> AllocationSummary {
+    let mut ctx = ScanContext::new(None);
+    let mut logical_bytes: u64 = 0;
+    let mut allocated_bytes: u64 = 0;
+
+    walk_files(Path::new(&path), &mut ctx, &mut |_, meta, size| {
+        allocated_bytes += size;
+        if !meta.is_dir() {
+            logical_bytes += meta.size();
+        }
+    });
+
+    let disks = Disks::new_with_refreshed_list();
+    let volume_used_bytes = disks
+        .list()
+        .iter()
+        .find(|d| d.mount_point() == Path::new(&path))
+        .map(|d| d.total_space().saturating_sub(d.available_space()));
+    let clone_shared_bytes = volume_used_bytes.map(|used| allocated_bytes.saturating_sub(used));
+
+    AllocationSummary {
+        path,
+        logical_bytes,
+        allocated_bytes,
+        volume_used_bytes,
+        clone_shared_bytes,
+    }
+}
+
+// Scan-Ergebnis speichern: "json" schreibt den FileNode-Baum wie scan_directory,
+// "csv" eine Zeile pro Datei (path,size,modified). Gibt den Zielpfad zurück.
+#[tauri::command(async)]
+fn export_scan(path: String, format: String, out_path: String) -> Result<String, String> {
+    let content = match format.as_str() {
+        "json" => {
+            let mut ctx = ScanContext::new(None);
+            let root = scan_recursive(Path::new(&path), 0, 5, &mut ctx);
+            serde_json::to_string_pretty(&root).map_err(|e| format!("JSON export failed: {e}"))?
+        }
+        "csv" => {
+            let mut ctx = ScanContext::new(None);
+            let mut csv = String::from("path,size,modified\n");
+            walk_files(Path::new(&path), &mut ctx, &mut |file_path, meta, size| {
+                if meta.is_dir() {
+                    return;
+                }
+                let modified = meta
+                    .modified()
+                    .ok()
+                    .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+                    .map(|d| d.as_secs().to_string())
+                    .unwrap_or_default();
+                csv.push_str(&format!(
+                    "{},{},{}\n",
+                    csv_field(&file_path.to_string_lossy()),
+                    size,
+                    modified
+                ));
+            });
+            csv
+        }
+        other => return Err(format!("Unsupported export format: {other}")),
+    };
+
+    write_atomically(Path::new(&out_path), content.as_bytes())?;
+    Ok(out_path)
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// Temp-Datei im Zielordner + rename, damit nie eine halb geschriebene Datei liegen bleibt
+fn write_atomically(target: &Path, data: &[u8]) -> Result<(), String> {
+    let file_name = target
+        .file_name()
+        .ok_or_else(|| format!("Invalid output path: {}", target.display()))?;
+    let tmp = target.with_file_name(format!(
+        ".{}.tmp-{}",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+
+    let write = || -> std::io::Result<()> {
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+        fs::rename(&tmp, target)
+    };
+    write().map_err(|e| {
+        let _ = fs::remove_file(&tmp);
+        format!("Writing {} failed: {e}", target.display())
+    })
+}
+
+// Speichert den ganzen Baum (ohne Tiefenlimit und Gruppierung) als flache Pfad→Größe-Tabelle
+// im App-Datenordner. Die id ist der Dateiname und wird an diff_scans übergeben.
+#[tauri::command(async)]
+fn save_scan_snapshot(app: tauri::AppHandle, path: String) -> Result<SnapshotInfo, String> {
+    let root = Path::new(&path);
+    let mut ctx = ScanContext::new(None);
+    let mut sizes: HashMap<String, u64> = HashMap::new();
+    walk_files(root, &mut ctx, &mut |entry_path, _, size| {
+        // Jeder Eintrag zählt für sich selbst und für alle Ordner bis zur Wurzel
+        for ancestor in entry_path.ancestors() {
+            *sizes.entry(ancestor.to_string_lossy().to_string()).or_insert(0) += size;
+            if ancestor == root {
+                break;
+            }
+        }
+    });
+
+    let created_at = std::time::SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let snapshot = ScanSnapshot {
+        path: path.clone(),
+        created_at,
+        sizes,
+    };
+
+    let dir = snapshot_dir(&app)?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Creating {} failed: {e}", dir.display()))?;
+    let slug: String = path
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let id = format!("{created_at}-{}.json", slug.trim_matches('_'));
+    let data = serde_json::to_vec(&snapshot).map_err(|e| format!("Snapshot encode failed: {e}"))?;
+    write_atomically(&dir.join(&id), &data)?;
+
+    Ok(SnapshotInfo {
+        id,
+        path,
+        created_at,
+        entry_count: snapshot.sizes.len(),
+    })
+}
+
+// Änderungen von snapshot_a (alt) nach snapshot_b (neu), nach absolutem Delta sortiert
+#[tauri::command(async)]
+fn diff_scans(app: tauri::AppHandle, snapshot_a: String, snapshot_b: String) -> Result<Vec<ScanDiffEntry>, String> {
+    let dir = snapshot_dir(&app)?;
+    let old = load_snapshot(&dir, &snapshot_a)?;
+    let new = load_snapshot(&dir, &snapshot_b)?;
+
+    let mut entries = Vec::new();
+    for (path, &old_size) in &old.sizes {
+        let new_size = new.sizes.get(path).copied();
+        let change = match new_size {
+            None => "removed",
+            Some(size) if size > old_size => "grown",
+            Some(size) if size < old_size => "shrunk",
+            Some(_) => continue,
+        };
+        entries.push(scan_diff_entry(path, change, old_size, new_size.unwrap_or(0)));
+    }
+    for (path, &new_size) in &new.sizes {
+        if !old.sizes.contains_key(path) {
+            entries.push(scan_diff_entry(path, "added", 0, new_size));
+        }
+    }
+
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.delta.unsigned_abs()));
+    Ok(entries)
+}
+
+fn scan_diff_entry(path: &str, change: &str, old_size: u64, new_size: u64) -> ScanDiffEntry {
+    let delta = new_size as i64 - old_size as i64;
+    let sign = if delta < 0 { "-" } else { "+" };
+    ScanDiffEntry {
+        path: path.to_string(),
+        change: change.to_string(),
+        old_size,
+        new_size,
+        delta,
+        display_delta: format!("{sign}{}", format_bytes(delta.unsigned_abs())),
+    }
+}
+
+fn snapshot_dir(app: &tauri::AppHandle) -> Result<std::path::PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join("snapshots"))
+        .map_err(|e| format!("App data dir unavailable: {e}"))
+}
+
+fn load_snapshot(dir: &Path, id: &str) -> Result<ScanSnapshot, String> {
+    if id.is_empty() || id.contains('/') || id.starts_with('.') {
+        return Err(format!("Invalid snapshot id: {id}"));
+    }
+    let data = fs::read(dir.join(id)).map_err(|e| format!("Snapshot {id} not readable: {e}"))?;
+    serde_json::from_slice(&data).map_err(|e| format!("Snapshot {id} invalid: {e}"))
+}
+
+// Läuft den ganzen Baum ohne Tiefenlimit ab, ohne FileNodes aufzubauen.
+// Hardlinks werden wie in scan_recursive nur beim ersten Auftreten gemeldet.
+fn walk_files(path: &Path, ctx: &mut ScanContext, visit: &mut dyn FnMut(&Path, &fs::Metadata, u64)) {
+    let meta = match fs::symlink_metadata(path) {
+        Ok(m) => m,
+        Err(_) => return,
+    };
+
+    let is_dir = meta.is_dir();
+    let file_id = FileID {
+        dev: meta.dev(),
+        ino: meta.ino(),
+    };
+    if !is_dir && !ctx.seen.insert(file_id) {
+        return;
+    }
+
+    let size = meta.blocks() * 512;
+    ctx.record_entry(&path.to_string_lossy(), is_dir, size);
+    visit(path, &meta, size);
+
+    if !is_dir || ctx.is_cancelled() {
+        return;
+    }
+    if let Ok(entries) = fs::read_dir(path) {
+        for entry in entries.flatten() {
+            if ctx.is_cancelled() {
+                break;
+            }
+            walk_files(&entry.path(), ctx, visit);
+        }
+    }
+}
+
+#[tauri::command]
+fn path_filesystem(path: String) -> Result<PathFilesystem, String> {
+    #[cfg(target_os = "macos")]
+    {
+        use std::ffi::{CStr, CString};
+
+        let c_path = CString::new(path.as_str()).map_err(|_| format!("Invalid path: {path}"))?;
+        let mut stats: libc::statfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statfs(c_path.as_ptr(), &mut stats) } != 0 {
+            return Err(format!("statfs failed for {path}: {}", std::io::Error::last_os_error()));
+        }
+        let field = |raw: &[libc::c_char]| unsafe { CStr::from_ptr(raw.as_ptr()) }.to_string_lossy().to_string();
+
+        return Ok(PathFilesystem {
+            fs_type: field(&stats.f_fstypename),
+            device: field(&stats.f_mntfromname),
+            mount_point: field(&stats.f_mntonname),
+            block_size: stats.f_bsize as u64,
+            io_size: stats.f_iosize as u64,
+            path,
+        });
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = path;
+        Err("path_filesystem is only supported on macOS".to_string())
+    }
+}
+
+#[tauri::command]
+fn format_size(bytes: u64, si: Option<bool>) -> String {
+    if si.unwrap_or(false) {
+        format_bytes_si(bytes)
+    } else {
+        format_bytes(bytes)
     }
 }
 
@@ -328,9 +1502,174 @@ fn open_in_finder(path: String) -> Result<(), String> {
     open::that(path).map_err(|e| e.to_string())
 }
 
+// Finder öffnet den Elternordner und markiert die Datei; andere Plattformen öffnen nur den Elternordner
+#[tauri::command]
+fn reveal_in_finder(path: String) -> Result<(), String> {
+    let target = Path::new(&path);
+    if fs::symlink_metadata(target).is_err() {
+        return Err(format!("Path does not exist: {path}"));
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let status = Command::new("open")
+            .args(["-R", &path])
+            .status()
+            .map_err(|e| format!("open failed: {e}"))?;
+        if !status.success() {
+            return Err(format!("open -R failed for {path}"));
+        }
+        return Ok(());
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        let parent = target.parent().unwrap_or(target);
+        open::that(parent).map_err(|e| e.to_string())
+    }
+}
+
+// Absoluter Pfad in die Zwischenablage; die UI bekommt den kopierten String zur Bestätigung zurück
+#[tauri::command]
+fn copy_path_to_clipboard(app: tauri::AppHandle, path: String) -> Result<String, String> {
+    use tauri_plugin_clipboard_manager::ClipboardExt;
+
+    let absolute = std::path::absolute(&path).map_err(|e| format!("{path}: {e}"))?;
+    let text = absolute.to_string_lossy().to_string();
+    app.clipboard().write_text(text.clone()).map_err(|e| e.to_string())?;
+    Ok(text)
+}
+
+#[tauri::command]
+fn move_to_trash(path: String) -> Result<TrashedItem, String> {
+    trash_one(&path)
+}
+
+// Einzelne Fehler (z.B. fehlende Rechte) brechen den Batch nicht ab
+#[tauri::command(async)]
+fn move_to_trash_many(paths: Vec<String>) -> Vec<TrashResult> {
+    paths
+        .into_iter()
+        .map(|path| match trash_one(&path) {
+            Ok(item) => TrashResult {
+                path,
+                ok: true,
+                item: Some(item),
+                error: None,
+            },
+            Err(err) => TrashResult {
+                path,
+                ok: false,
+                item: None,
+                error: Some(err),
+            },
+        })
+        .collect()
+}
+
+fn trash_one(path: &str) -> Result<TrashedItem, String> {
+    // Nicht canonicalize: ein Symlink soll selbst im Papierkorb landen, nicht sein Ziel
+    let original = std::path::absolute(path).map_err(|e| format!("{path}: {e}"))?;
+    #[cfg(target_os = "macos")]
+    let before = trash_entries().unwrap_or_default();
+    trash::delete(&original).map_err(|e| e.to_string())?;
+
+    #[cfg(target_os = "macos")]
+    let trash_path = locate_in_trash(&original, &before);
+    #[cfg(not(target_os = "macos"))]
+    let trash_path = locate_in_trash(&original);
+    Ok(TrashedItem {
+        original_path: original.to_string_lossy().to_string(),
+        trash_path: trash_path.map(|p| p.to_string_lossy().to_string()),
+    })
+}
+
+// Der trash-Crate bietet auf macOS weder list noch restore und meldet nicht, wohin Finder
+// das Objekt gelegt hat. Bei Namenskollision benennt Finder um („name 2“), daher zählt nur
+// der Eintrag, der durch das Löschen neu in ~/.Trash aufgetaucht ist. Objekte von anderen
+// Volumes (/Volumes/X/.Trashes) oder mehrdeutige Treffer ergeben None.
+#[cfg(target_os = "macos")]
+fn locate_in_trash(original: &Path, before: &HashSet<std::ffi::OsString>) -> Option<std::path::PathBuf> {
+    let stem = original.file_stem()?.to_string_lossy().to_string();
+    let mut added = trash_entries()?
+        .into_iter()
+        .filter(|name| !before.contains(name) && name.to_string_lossy().starts_with(&stem));
+    let name = added.next()?;
+    if added.next().is_some() {
+        return None;
+    }
+    Some(trash_dir()?.join(name))
+}
+
+#[cfg(target_os = "macos")]
+fn trash_dir() -> Option<std::path::PathBuf> {
+    Some(Path::new(&std::env::var_os("HOME")?).join(".Trash"))
+}
+
+#[cfg(target_os = "macos")]
+fn trash_entries() -> Option<HashSet<std::ffi::OsString>> {
+    let entries = fs::read_dir(trash_dir()?).ok()?;
+    Some(entries.filter_map(|entry| entry.ok().map(|entry| entry.file_name())).collect())
+}
+
+// Nur direkte Einträge des Papierkorbs; "..", "." oder verschachtelte Pfade werden abgelehnt
+#[cfg(any(target_os = "macos", test))]
+fn is_direct_trash_entry(trash_dir: &Path, candidate: &Path) -> bool {
+    candidate.parent() == Some(trash_dir)
+        && matches!(candidate.components().next_back(), Some(std::path::Component::Normal(_)))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn locate_in_trash(original: &Path) -> Option<std::path::PathBuf> {
+    newest_trash_item(original).map(|item| std::path::PathBuf::from(item.id.to_string_lossy().to_string()))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn newest_trash_item(original: &Path) -> Option<trash::TrashItem> {
+    trash::os_limited::list()
+        .ok()?
+        .into_iter()
+        .filter(|item| item.original_path() == original)
+        .max_by_key(|item| item.time_deleted)
+}
+
+// trash_path stammt aus dem TrashedItem von move_to_trash; geraten wird nicht.
 #[tauri::command]
-fn move_to_trash(path: String) -> Result<(), String> {
-    trash::delete(path).map_err(|e| e.to_string())
+fn restore_from_trash(original_path: String, trash_path: Option<String>) -> Result<(), String> {
+    let original = Path::new(&original_path);
+    if fs::symlink_metadata(original).is_ok() {
+        return Err(format!("{original_path} already exists"));
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let trash_path = trash_path.ok_or_else(|| format!("Trash location of {original_path} is unknown"))?;
+        let trashed = Path::new(&trash_path);
+        let trash_dir = trash_dir()
+            .and_then(|dir| fs::canonicalize(dir).ok())
+            .ok_or_else(|| "Trash not found".to_string())?;
+        let parent = trashed.parent().and_then(|dir| fs::canonicalize(dir).ok());
+        let inside = parent.is_some_and(|parent| {
+            trashed.file_name().is_some_and(|name| is_direct_trash_entry(&trash_dir, &parent.join(name)))
+        });
+        if !inside || fs::symlink_metadata(trashed).is_err() {
+            return Err(format!("{trash_path} is not an item in the Trash"));
+        }
+        return fs::rename(trashed, original).map_err(|e| format!("Restore failed: {e}"));
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        let item = match trash_path {
+            Some(trash_path) => trash::os_limited::list()
+                .map_err(|e| e.to_string())?
+                .into_iter()
+                .find(|item| item.id.to_string_lossy() == trash_path.as_str() && item.original_path() == original),
+            None => newest_trash_item(original),
+        };
+        let item = item.ok_or_else(|| format!("{original_path} not found in Trash"))?;
+        trash::os_limited::restore_all([item]).map_err(|e| e.to_string())
+    }
 }
 
 #[tauri::command]
@@ -369,15 +1708,29 @@ fn validate_admin_password(password: String) -> Result<bool, String> {
 }
 
 // Hilfsfunktion für schöne Strings direkt aus Rust
+// IEC (Basis 1024), Standard für alle bisherigen Ausgaben
 fn format_bytes(bytes: u64) -> String {
-    const UNIT: u64 = 1024;
-    if bytes < UNIT {
+    format_bytes_base(bytes, 1024, "KMGTPE")
+}
+
+// SI (Basis 1000) wie im Finder
+fn format_bytes_si(bytes: u64) -> String {
+    format_bytes_base(bytes, 1000, "kMGTPE")
+}
+
+// Exponent wird auf die größte bekannte Einheit begrenzt, darüber wird weiter skaliert
+fn format_bytes_base(bytes: u64, unit: u64, prefixes: &str) -> String {
+    if bytes < unit {
         return format!("{} B", bytes);
     }
-    let div = UNIT as f64;
-    let exp = (bytes as f64).log(div) as i32;
-    let pre = "KMGTPE".chars().nth((exp - 1) as usize).unwrap_or('?');
-    let val = (bytes as f64) / div.powi(exp);
+    let mut exp = 0;
+    let mut scaled = bytes;
+    while scaled >= unit && exp < prefixes.len() {
+        scaled /= unit;
+        exp += 1;
+    }
+    let pre = &prefixes[exp - 1..exp];
+    let val = (bytes as f64) / (unit as f64).powi(exp as i32);
     format!("{:.1} {}B", val, pre)
 }
 
@@ -385,44 +1738,136 @@ fn main() {
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
         .invoke_handler(tauri::generate_handler![
             get_disks,
+            list_mounts,
             scan_directory,
+            scan_directory_cancellable,
+            scan_disk,
+            cancel_scan,
+            scan_by_extension,
+            scan_stale_files,
+            find_duplicates,
+            export_scan,
+            save_scan_snapshot,
+            diff_scans,
+            path_filesystem,
+            get_allocation_summary,
+            dir_size,
+            format_size,
             open_in_finder,
+            reveal_in_finder,
+            copy_path_to_clipboard,
             move_to_trash,
+            move_to_trash_many,
+            restore_from_trash,
             validate_admin_password,
             partitioning::get_partition_devices,
+            partitioning::refresh_devices,
             partitioning::wipe_device,
             partitioning::secure_erase,
             partitioning::create_partition_table,
+            partitioning::convert_partition_table,
             partitioning::create_partition,
+            partitioning::apply_partition_layout,
             partitioning::delete_partition,
             partitioning::format_partition,
             partitioning::set_label_uuid,
+            partitioning::rename_volume,
+            partitioning::set_volume_icon,
+            partitioning::set_partition_name,
             partitioning::install_sudoers_helper,
             partitioning::mount_disk,
             partitioning::mount_volume,
+            partitioning::mount_volume_opts,
+            partitioning::unlock_volume,
             partitioning::check_partition,
             partitioning::resize_partition,
+            partitioning::preview_resize,
             partitioning::move_partition,
+            partitioning::resume_move,
             partitioning::copy_partition,
             partitioning::preflight_partition,
             partitioning::force_unmount_partition,
+            partitioning::safe_eject,
+            partitioning::fstab_entry,
+            partitioning::filesystem_constraints,
+            partitioning::supported_filesystems,
+            partitioning::wipe_signatures,
+            partitioning::mount_ntfs_rw,
+            partitioning::benchmark_device,
             partitioning::get_operation_journal,
             partitioning::clear_operation_journal,
+            partitioning::get_audit_log,
+            partitioning::clear_audit_log,
             partitioning::get_sidecar_status,
+            partitioning::helper_status,
+            partitioning::helper_version,
             partitioning::get_partition_bounds,
+            partitioning::check_alignment,
             partitioning::apfs_list_volumes,
             partitioning::apfs_add_volume,
             partitioning::apfs_delete_volume,
+            partitioning::encrypt_volume,
+            partitioning::apfs_set_quota,
+            partitioning::apfs_list_snapshots,
+            partitioning::apfs_delete_snapshot,
             partitioning::flash_image,
             partitioning::inspect_image,
             partitioning::hash_image,
             partitioning::backup_image,
             partitioning::windows_install,
             partitioning::cancel_helper_operation,
+            partitioning::set_helper_timeouts,
             partitioning::eject_disk,
+            partitioning::get_disk_io_stats,
+            partitioning::smart_info,
+            partitioning::trim_device,
+            partitioning::start_disk_watch,
+            partitioning::stop_disk_watch,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|_app, event| {
+            if let tauri::RunEvent::Exit = event {
+                partitioning::stop_disk_watch();
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_bytes_small_values() {
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(1023), "1023 B");
+    }
+
+    #[test]
+    fn format_bytes_exact_kib() {
+        assert_eq!(format_bytes(1024), "1.0 KB");
+    }
+
+    #[test]
+    fn format_bytes_u64_max() {
+        assert_eq!(format_bytes(u64::MAX), "16.0 EB");
+    }
+
+    #[test]
+    fn format_bytes_clamps_to_largest_unit() {
+        assert_eq!(format_bytes_base(10 * 1024 * 1024 * 1024, 1024, "KM"), "10240.0 MB");
+    }
+
+    #[test]
+    fn restore_only_accepts_direct_trash_entries() {
+        let trash = Path::new("/Users/oliver/.Trash");
+        assert!(is_direct_trash_entry(trash, Path::new("/Users/oliver/.Trash/report 2.pdf")));
+        assert!(!is_direct_trash_entry(trash, Path::new("/Users/oliver/.Trash/..")));
+        assert!(!is_direct_trash_entry(trash, Path::new("/Users/oliver/.Trash/dir/report.pdf")));
+        assert!(!is_direct_trash_entry(trash, Path::new("/Users/oliver/Documents/report.pdf")));
+        assert!(!is_direct_trash_entry(trash, Path::new("/Users/oliver/.Trash")));
+    }
 }
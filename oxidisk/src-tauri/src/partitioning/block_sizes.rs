@@ -0,0 +1,17 @@
+// Gemeinsam genutzt von partitioning (App) und oxidisk_helper, dort per #[path] eingebunden.
+
+// (logisch, physisch) aus diskutil info. 512e-Laufwerke melden 512/4096; fehlt
+// PhysicalBlockSize (aeltere macOS-Versionen), gilt die logische Groesse.
+pub fn device_block_sizes(dict: &plist::Dictionary) -> (u64, u64) {
+    let logical = dict
+        .get("DeviceBlockSize")
+        .and_then(|v| v.as_unsigned_integer())
+        .filter(|size| *size > 0)
+        .unwrap_or(512);
+    let physical = dict
+        .get("PhysicalBlockSize")
+        .and_then(|v| v.as_unsigned_integer())
+        .filter(|size| *size > 0)
+        .unwrap_or(logical);
+    (logical, physical)
+}
@@ -1,6 +1,53 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MkfsOptions {
+    pub block_size: Option<u64>,
+    pub inode_ratio: Option<u64>,
+    pub extra_args: Vec<String>,
+}
+
+// Ergebnis von validate_label: das tatsächlich verwendete Label und ggf. ein Hinweis,
+// falls es gekürzt oder umgewandelt wurde.
+pub struct LabelValidation {
+    pub label: String,
+    pub warning: Option<String>,
+}
+
+// Zulässige Zeichen für Labels. utf16: Länge zählt in UTF-16-Einheiten statt Bytes;
+// allowed_extra: nur ASCII-Buchstaben/-Ziffern plus diese Zeichen (z.B. ZFS-Poolnamen).
+#[derive(Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CharsetSpec {
+    pub forbidden: &'static str,
+    pub ascii_only: bool,
+    pub uppercase: bool,
+    pub utf16: bool,
+    pub allowed_extra: Option<&'static str>,
+}
+
+// Empfohlener /etc/fstab-Eintrag unter Linux (Typ, Optionen, fsck-Reihenfolge)
+pub struct FstabSpec {
+    pub fs_type: &'static str,
+    pub options: &'static str,
+    pub pass: u8,
+}
+
 pub trait FileSystemDriver {
     fn id(&self) -> &'static str;
     fn mkfs_command(&self, device: &str, label: &str) -> Option<(String, Vec<String>)>;
+    // block_size/inode_ratio werden nur von Treibern ausgewertet, die diese Methode
+    // überschreiben. Ohne Optionen entspricht das Ergebnis exakt mkfs_command.
+    fn mkfs_command_with_opts(
+        &self,
+        device: &str,
+        label: &str,
+        opts: &MkfsOptions,
+    ) -> Option<(String, Vec<String>)> {
+        let (bin, args) = self.mkfs_command(device, label)?;
+        Some((bin, insert_before_device(args, opts.extra_args.clone())))
+    }
     fn label_command(&self, device: &str, label: &str) -> Option<(String, Vec<String>)> {
         let _ = device;
         let _ = label;
@@ -11,6 +58,38 @@ pub trait FileSystemDriver {
         let _ = uuid;
         None
     }
+    // Maximale Labellänge in Bytes bzw. (bei CharsetSpec::utf16) UTF-16-Einheiten
+    fn max_label_length(&self) -> usize {
+        255
+    }
+    fn allowed_label_chars(&self) -> CharsetSpec {
+        CharsetSpec::default()
+    }
+    // Unzulässige Zeichen werden abgelehnt, zu lange Labels gekürzt.
+    fn validate_label(&self, label: &str) -> Result<LabelValidation, String> {
+        check_label(self.id(), label, self.max_label_length(), &self.allowed_label_chars())
+    }
+    // None: wird unter Linux nicht über fstab eingehängt (z.B. ZFS)
+    fn fstab_spec(&self) -> Option<FstabSpec> {
+        None
+    }
+    // Sidecar zum Vergrößern bzw. Verkleinern des Dateisystems; None: nicht unterstützt
+    fn resize_binary(&self, shrink: bool) -> Option<&'static str> {
+        let _ = shrink;
+        None
+    }
+    fn max_file_size(&self) -> Option<u64> {
+        None
+    }
+    // Schalter, die über MkfsOptions::extra_args an mkfs durchgereicht werden dürfen
+    // (Schalter, erwartet Wert). Leer: keine Zusatzargumente.
+    fn allowed_mkfs_flags(&self) -> &'static [(&'static str, bool)] {
+        &[]
+    }
+    // extra_args landen im privilegierten mkfs-Aufruf und werden deshalb vorab geprüft.
+    fn validate_mkfs_options(&self, opts: &MkfsOptions) -> Result<(), String> {
+        check_mkfs_args(self.id(), &opts.extra_args, self.allowed_mkfs_flags())
+    }
 }
 
 pub struct Ext4Driver;
@@ -27,6 +106,26 @@ impl FileSystemDriver for Ext4Driver {
         ))
     }
 
+    fn mkfs_command_with_opts(
+        &self,
+        device: &str,
+        label: &str,
+        opts: &MkfsOptions,
+    ) -> Option<(String, Vec<String>)> {
+        let (bin, args) = self.mkfs_command(device, label)?;
+        let mut extra = Vec::new();
+        if let Some(block_size) = opts.block_size {
+            extra.push("-b".to_string());
+            extra.push(block_size.to_string());
+        }
+        if let Some(inode_ratio) = opts.inode_ratio {
+            extra.push("-i".to_string());
+            extra.push(inode_ratio.to_string());
+        }
+        extra.extend(opts.extra_args.iter().cloned());
+        Some((bin, insert_before_device(args, extra)))
+    }
+
     fn label_command(&self, device: &str, label: &str) -> Option<(String, Vec<String>)> {
         Some((
             "e2label".to_string(),
@@ -40,6 +139,27 @@ impl FileSystemDriver for Ext4Driver {
             vec!["-U".to_string(), uuid.to_string(), device.to_string()],
         ))
     }
+
+    fn max_label_length(&self) -> usize {
+        16
+    }
+
+    fn fstab_spec(&self) -> Option<FstabSpec> {
+        Some(FstabSpec { fs_type: "ext4", options: "defaults,noatime", pass: 2 })
+    }
+
+    fn resize_binary(&self, _shrink: bool) -> Option<&'static str> {
+        Some("resize2fs")
+    }
+
+    fn max_file_size(&self) -> Option<u64> {
+        Some(16 * TIB)
+    }
+
+    // Reservierte Blöcke, Features, erweiterte Optionen, Nutzungsprofil
+    fn allowed_mkfs_flags(&self) -> &'static [(&'static str, bool)] {
+        &[("-m", true), ("-O", true), ("-E", true), ("-T", true)]
+    }
 }
 
 pub struct NtfsDriver;
@@ -56,12 +176,63 @@ impl FileSystemDriver for NtfsDriver {
         ))
     }
 
+    // Bei NTFS entspricht die Blockgröße der Clustergröße
+    fn mkfs_command_with_opts(
+        &self,
+        device: &str,
+        label: &str,
+        opts: &MkfsOptions,
+    ) -> Option<(String, Vec<String>)> {
+        let (bin, args) = self.mkfs_command(device, label)?;
+        let mut extra = Vec::new();
+        if let Some(block_size) = opts.block_size {
+            extra.push("-c".to_string());
+            extra.push(block_size.to_string());
+        }
+        extra.extend(opts.extra_args.iter().cloned());
+        Some((bin, insert_before_device(args, extra)))
+    }
+
     fn label_command(&self, device: &str, label: &str) -> Option<(String, Vec<String>)> {
         Some((
             "ntfslabel".to_string(),
             vec![device.to_string(), label.to_string()],
         ))
     }
+
+    fn uuid_command(&self, device: &str, uuid: &str) -> Option<(String, Vec<String>)> {
+        Some((
+            "ntfslabel".to_string(),
+            vec![format!("--new-serial={uuid}"), device.to_string()],
+        ))
+    }
+
+    fn max_label_length(&self) -> usize {
+        32
+    }
+
+    fn allowed_label_chars(&self) -> CharsetSpec {
+        CharsetSpec { forbidden: WINDOWS_ILLEGAL_LABEL_CHARS, utf16: true, ..CharsetSpec::default() }
+    }
+
+    // ntfs3 (Kernel ab 5.15); windows_names verhindert unter Windows ungültige Dateinamen
+    fn fstab_spec(&self) -> Option<FstabSpec> {
+        Some(FstabSpec { fs_type: "ntfs3", options: "defaults,uid=1000,gid=1000,windows_names", pass: 0 })
+    }
+
+    fn resize_binary(&self, _shrink: bool) -> Option<&'static str> {
+        Some("ntfsresize")
+    }
+
+    // Bei der Standard-Clustergröße von 4 KiB
+    fn max_file_size(&self) -> Option<u64> {
+        Some(16 * TIB)
+    }
+
+    // Kompression, Indexierung aus
+    fn allowed_mkfs_flags(&self) -> &'static [(&'static str, bool)] {
+        &[("-C", false), ("-I", false)]
+    }
 }
 
 pub struct BtrfsDriver;
@@ -89,6 +260,24 @@ impl FileSystemDriver for BtrfsDriver {
             ],
         ))
     }
+
+    // btrfs prüft sich selbst, fsck.btrfs ist ein No-op
+    fn fstab_spec(&self) -> Option<FstabSpec> {
+        Some(FstabSpec { fs_type: "btrfs", options: "defaults,noatime,compress=zstd", pass: 0 })
+    }
+
+    fn resize_binary(&self, _shrink: bool) -> Option<&'static str> {
+        Some("btrfs")
+    }
+
+    fn max_file_size(&self) -> Option<u64> {
+        Some(u64::MAX)
+    }
+
+    // Profile für Daten/Metadaten, Knotengröße, Features, Prüfsumme
+    fn allowed_mkfs_flags(&self) -> &'static [(&'static str, bool)] {
+        &[("-d", true), ("-m", true), ("-n", true), ("-O", true), ("--csum", true)]
+    }
 }
 
 pub struct XfsDriver;
@@ -105,12 +294,51 @@ impl FileSystemDriver for XfsDriver {
         ))
     }
 
+    fn mkfs_command_with_opts(
+        &self,
+        device: &str,
+        label: &str,
+        opts: &MkfsOptions,
+    ) -> Option<(String, Vec<String>)> {
+        let (bin, args) = self.mkfs_command(device, label)?;
+        let mut extra = Vec::new();
+        if let Some(block_size) = opts.block_size {
+            extra.push("-b".to_string());
+            extra.push(format!("size={block_size}"));
+        }
+        extra.extend(opts.extra_args.iter().cloned());
+        Some((bin, insert_before_device(args, extra)))
+    }
+
     fn label_command(&self, device: &str, label: &str) -> Option<(String, Vec<String>)> {
         Some((
             "xfs_admin".to_string(),
             vec!["-L".to_string(), label.to_string(), device.to_string()],
         ))
     }
+
+    fn max_label_length(&self) -> usize {
+        12
+    }
+
+    // xfs_repair läuft nicht beim Booten, daher kein fsck-Pass
+    fn fstab_spec(&self) -> Option<FstabSpec> {
+        Some(FstabSpec { fs_type: "xfs", options: "defaults,noatime", pass: 0 })
+    }
+
+    // XFS kann nur wachsen
+    fn resize_binary(&self, shrink: bool) -> Option<&'static str> {
+        (!shrink).then_some("xfs_growfs")
+    }
+
+    fn max_file_size(&self) -> Option<u64> {
+        Some(8 * EIB)
+    }
+
+    // Nur Metadaten- und Inode-Optionen; -d/-l/-r könnten auf fremde Geräte zeigen
+    fn allowed_mkfs_flags(&self) -> &'static [(&'static str, bool)] {
+        &[("-m", true), ("-i", true)]
+    }
 }
 
 pub struct F2fsDriver;
@@ -123,6 +351,29 @@ impl FileSystemDriver for F2fsDriver {
     fn mkfs_command(&self, device: &str, _label: &str) -> Option<(String, Vec<String>)> {
         Some(("mkfs.f2fs".to_string(), vec![device.to_string()]))
     }
+
+    // mkfs.f2fs wird ohne Label aufgerufen
+    fn max_label_length(&self) -> usize {
+        0
+    }
+
+    fn validate_label(&self, label: &str) -> Result<LabelValidation, String> {
+        let warning = (!label.is_empty()).then(|| "Label is ignored for f2fs".to_string());
+        Ok(LabelValidation { label: label.to_string(), warning })
+    }
+
+    fn fstab_spec(&self) -> Option<FstabSpec> {
+        Some(FstabSpec { fs_type: "f2fs", options: "defaults,noatime", pass: 0 })
+    }
+
+    fn max_file_size(&self) -> Option<u64> {
+        Some(3_940 * GIB)
+    }
+
+    // Features, Overprovisioning
+    fn allowed_mkfs_flags(&self) -> &'static [(&'static str, bool)] {
+        &[("-O", true), ("-o", true)]
+    }
 }
 
 pub struct SwapDriver;
@@ -152,6 +403,187 @@ impl FileSystemDriver for SwapDriver {
             vec!["-U".to_string(), uuid.to_string(), device.to_string()],
         ))
     }
+
+    fn max_label_length(&self) -> usize {
+        16
+    }
+
+    fn fstab_spec(&self) -> Option<FstabSpec> {
+        Some(FstabSpec { fs_type: "swap", options: "defaults", pass: 0 })
+    }
+}
+
+pub struct ExfatDriver;
+
+impl FileSystemDriver for ExfatDriver {
+    fn id(&self) -> &'static str {
+        "exfat"
+    }
+
+    fn mkfs_command(&self, device: &str, label: &str) -> Option<(String, Vec<String>)> {
+        Some((
+            "mkfs.exfat".to_string(),
+            vec!["-n".to_string(), label.to_string(), device.to_string()],
+        ))
+    }
+
+    fn label_command(&self, device: &str, label: &str) -> Option<(String, Vec<String>)> {
+        Some((
+            "exfatlabel".to_string(),
+            vec![device.to_string(), label.to_string()],
+        ))
+    }
+
+    fn uuid_command(&self, device: &str, uuid: &str) -> Option<(String, Vec<String>)> {
+        Some((
+            "exfatlabel".to_string(),
+            vec!["-i".to_string(), device.to_string(), format!("0x{uuid}")],
+        ))
+    }
+
+    fn max_label_length(&self) -> usize {
+        15
+    }
+
+    fn allowed_label_chars(&self) -> CharsetSpec {
+        CharsetSpec { forbidden: WINDOWS_ILLEGAL_LABEL_CHARS, utf16: true, ..CharsetSpec::default() }
+    }
+
+    fn fstab_spec(&self) -> Option<FstabSpec> {
+        Some(FstabSpec { fs_type: "exfat", options: "defaults,uid=1000,gid=1000,umask=022", pass: 0 })
+    }
+
+    fn max_file_size(&self) -> Option<u64> {
+        Some(u64::MAX)
+    }
+}
+
+pub struct Fat32Driver;
+
+impl FileSystemDriver for Fat32Driver {
+    fn id(&self) -> &'static str {
+        "fat32"
+    }
+
+    fn mkfs_command(&self, device: &str, label: &str) -> Option<(String, Vec<String>)> {
+        Some((
+            "mkfs.fat".to_string(),
+            vec![
+                "-F".to_string(),
+                "32".to_string(),
+                "-n".to_string(),
+                label.to_string(),
+                device.to_string(),
+            ],
+        ))
+    }
+
+    fn label_command(&self, device: &str, label: &str) -> Option<(String, Vec<String>)> {
+        Some((
+            "fatlabel".to_string(),
+            vec![device.to_string(), label.to_string()],
+        ))
+    }
+
+    fn uuid_command(&self, device: &str, uuid: &str) -> Option<(String, Vec<String>)> {
+        Some((
+            "fatlabel".to_string(),
+            vec!["-i".to_string(), device.to_string(), uuid.to_string()],
+        ))
+    }
+
+    // FAT speichert Labels als 11 Byte in Großbuchstaben (OEM-Zeichensatz)
+    fn max_label_length(&self) -> usize {
+        11
+    }
+
+    fn allowed_label_chars(&self) -> CharsetSpec {
+        CharsetSpec { forbidden: FAT_ILLEGAL_LABEL_CHARS, ascii_only: true, uppercase: true, ..CharsetSpec::default() }
+    }
+
+    fn fstab_spec(&self) -> Option<FstabSpec> {
+        Some(FstabSpec { fs_type: "vfat", options: "defaults,uid=1000,gid=1000,umask=022,utf8", pass: 2 })
+    }
+
+    fn resize_binary(&self, _shrink: bool) -> Option<&'static str> {
+        Some("fatresize")
+    }
+
+    fn max_file_size(&self) -> Option<u64> {
+        Some(4 * GIB - 1)
+    }
+}
+
+pub struct ZfsDriver;
+
+impl FileSystemDriver for ZfsDriver {
+    fn id(&self) -> &'static str {
+        "zfs"
+    }
+
+    // Das Label wird als Poolname verwendet
+    fn mkfs_command(&self, device: &str, label: &str) -> Option<(String, Vec<String>)> {
+        Some((
+            "zpool".to_string(),
+            vec![
+                "create".to_string(),
+                "-f".to_string(),
+                "-o".to_string(),
+                "ashift=12".to_string(),
+                label.to_string(),
+                device.to_string(),
+            ],
+        ))
+    }
+
+    // Optionen gehören vor den Poolnamen; block_size wird zu ashift
+    fn mkfs_command_with_opts(
+        &self,
+        device: &str,
+        label: &str,
+        opts: &MkfsOptions,
+    ) -> Option<(String, Vec<String>)> {
+        let (bin, mut args) = self.mkfs_command(device, label)?;
+        if let Some(block_size) = opts.block_size.filter(|size| size.is_power_of_two()) {
+            args[3] = format!("ashift={}", block_size.trailing_zeros());
+        }
+        let pool_index = args.len() - 2;
+        args.splice(pool_index..pool_index, opts.extra_args.iter().cloned());
+        Some((bin, args))
+    }
+
+    fn allowed_label_chars(&self) -> CharsetSpec {
+        CharsetSpec { ascii_only: true, allowed_extra: Some("_-.:"), ..CharsetSpec::default() }
+    }
+
+    // Der Poolname landet als Argument von zpool create und wird nie gekürzt
+    fn validate_label(&self, label: &str) -> Result<LabelValidation, String> {
+        validate_zpool_name(label)?;
+        Ok(LabelValidation { label: label.to_string(), warning: None })
+    }
+
+    fn max_file_size(&self) -> Option<u64> {
+        Some(u64::MAX)
+    }
+
+    // Pool- und Dataset-Eigenschaften (z.B. ashift=12, compression=lz4)
+    fn allowed_mkfs_flags(&self) -> &'static [(&'static str, bool)] {
+        &[("-o", true), ("-O", true)]
+    }
+}
+
+// Poolnamen: Buchstabe am Anfang (also kein führendes "-"), danach nur [A-Za-z0-9_.:-],
+// höchstens 255 Zeichen und keine von zpool reservierten Namen
+pub fn validate_zpool_name(name: &str) -> Result<(), String> {
+    let charset = ZfsDriver.allowed_label_chars();
+    let starts_with_letter = name.chars().next().is_some_and(|ch| ch.is_ascii_alphabetic());
+    let reserved = name == "log"
+        || ["mirror", "raidz", "draid", "spare"].iter().any(|prefix| name.starts_with(prefix))
+        || name.len() >= 2 && name.starts_with('c') && name.as_bytes()[1].is_ascii_digit();
+    if !starts_with_letter || name.len() > 255 || !label_chars_allowed(name, &charset) || reserved {
+        return Err(format!("Invalid ZFS pool name: {name}"));
+    }
+    Ok(())
 }
 
 pub fn default_drivers() -> Vec<Box<dyn FileSystemDriver>> {
@@ -162,5 +594,117 @@ pub fn default_drivers() -> Vec<Box<dyn FileSystemDriver>> {
         Box::new(XfsDriver),
         Box::new(F2fsDriver),
         Box::new(SwapDriver),
+        Box::new(ZfsDriver),
+        Box::new(ExfatDriver),
+        Box::new(Fat32Driver),
     ]
 }
+
+// Das Device ist bei allen mkfs-Aufrufen das letzte Argument
+fn insert_before_device(mut args: Vec<String>, extra: Vec<String>) -> Vec<String> {
+    let device_index = args.len().saturating_sub(1);
+    args.splice(device_index..device_index, extra);
+    args
+}
+
+const GIB: u64 = 1024 * 1024 * 1024;
+const TIB: u64 = 1024 * GIB;
+const EIB: u64 = 1024 * 1024 * TIB;
+
+// Von Windows/exFAT/NTFS nicht erlaubte Zeichen; FAT verbietet zusätzlich einige Satzzeichen
+const WINDOWS_ILLEGAL_LABEL_CHARS: &str = "\"*/:<>?\\|";
+const FAT_ILLEGAL_LABEL_CHARS: &str = "\"*+,./:;<=>?[\\]|";
+
+// Nur bekannte Schalter; Werte dürfen weder Pfade (z.B. Log-Geräte, Mountpoints)
+// noch weitere Schalter sein.
+pub fn check_mkfs_args(fs: &str, args: &[String], allowed: &[(&str, bool)]) -> Result<(), String> {
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let Some((_, takes_value)) = allowed.iter().find(|(flag, _)| flag == arg) else {
+            return Err(format!("mkfs option not allowed for {fs}: {arg}"));
+        };
+        if !takes_value {
+            continue;
+        }
+        let value = iter
+            .next()
+            .ok_or_else(|| format!("mkfs option {arg} for {fs} requires a value"))?;
+        let valid = !value.is_empty()
+            && !value.starts_with('-')
+            && value
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || "_.,:=^+-".contains(ch));
+        if !valid {
+            return Err(format!("Invalid value for mkfs option {arg}: {value}"));
+        }
+    }
+    Ok(())
+}
+
+// Gemeinsame Prüfung anhand von max_label_length/allowed_label_chars
+fn check_label(fs: &str, label: &str, max_length: usize, charset: &CharsetSpec) -> Result<LabelValidation, String> {
+    reject_label_chars(fs, label, charset.forbidden)?;
+    if charset.ascii_only && !label.is_ascii() {
+        return Err(format!("Label for {fs} may only contain ASCII characters"));
+    }
+    if !label_chars_allowed(label, charset) {
+        return Err(format!("Label contains characters not allowed for {fs}"));
+    }
+    let converted = if charset.uppercase { label.to_uppercase() } else { label.to_string() };
+    let mut result = if charset.utf16 {
+        truncate_label_utf16(fs, &converted, max_length)
+    } else {
+        truncate_label_bytes(fs, &converted, max_length)
+    };
+    if converted != label {
+        let note = format!("Label converted to uppercase for {fs}");
+        result.warning = Some(match result.warning {
+            Some(existing) => format!("{note}; {existing}"),
+            None => note,
+        });
+    }
+    Ok(result)
+}
+
+fn label_chars_allowed(label: &str, charset: &CharsetSpec) -> bool {
+    match charset.allowed_extra {
+        Some(extra) => label.chars().all(|ch| ch.is_ascii_alphanumeric() || extra.contains(ch)),
+        None => true,
+    }
+}
+
+fn reject_label_chars(fs: &str, label: &str, illegal: &str) -> Result<(), String> {
+    if let Some(ch) = label.chars().find(|ch| ch.is_control() || illegal.contains(*ch)) {
+        return Err(format!("Label contains a character not allowed for {fs}: {ch:?}"));
+    }
+    Ok(())
+}
+
+fn truncate_label_bytes(fs: &str, label: &str, max_bytes: usize) -> LabelValidation {
+    if label.len() <= max_bytes {
+        return LabelValidation { label: label.to_string(), warning: None };
+    }
+    let mut end = max_bytes;
+    while !label.is_char_boundary(end) {
+        end -= 1;
+    }
+    let truncated = label[..end].to_string();
+    let warning = Some(format!("Label truncated to {max_bytes} bytes for {fs}: {truncated}"));
+    LabelValidation { label: truncated, warning }
+}
+
+fn truncate_label_utf16(fs: &str, label: &str, max_units: usize) -> LabelValidation {
+    if label.encode_utf16().count() <= max_units {
+        return LabelValidation { label: label.to_string(), warning: None };
+    }
+    let mut units = 0;
+    let truncated: String = label
+        .chars()
+        .take_while(|ch| {
+            units += ch.len_utf16();
+            units <= max_units
+        })
+        .collect();
+    let warning = Some(format!("Label truncated to {max_units} characters for {fs}: {truncated}"));
+    LabelValidation { label: truncated, warning }
+}
@@ -3,10 +3,17 @@ use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::io::{BufRead, BufReader, Read, Write};
 use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Mutex, OnceLock};
 use tauri::path::BaseDirectory;
 use tauri::{Emitter, Manager};
 
+#[cfg(target_os = "macos")]
+mod block_sizes;
+
+#[cfg(target_os = "macos")]
+use block_sizes::device_block_sizes;
+
 #[derive(Serialize)]
 pub struct PartitionDevice {
     identifier: String,
@@ -15,12 +22,23 @@ pub struct PartitionDevice {
     is_solid_state: bool,
     bus_protocol: Option<String>,
     content: String,
+    scheme: String,
+    writable_scheme: bool,
     parent_device: Option<String>,
     partitions: Vec<PartitionEntry>,
+    free_regions: Vec<FreeRegion>,
+    block_size: Option<u64>,
+    physical_block_size: Option<u64>,
     is_protected: bool,
     protection_reason: Option<String>,
 }
 
+#[derive(Serialize)]
+pub struct FreeRegion {
+    offset: u64,
+    size: u64,
+}
+
 #[derive(Serialize)]
 pub struct PartitionEntry {
     identifier: String,
@@ -32,6 +50,11 @@ pub struct PartitionEntry {
     is_protected: bool,
     protection_reason: Option<String>,
     fs_type: Option<String>,
+    type_guid: Option<String>,
+    used: Option<u64>,
+    available: Option<u64>,
+    encrypted: Option<bool>,
+    locked: Option<bool>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -43,8 +66,14 @@ struct HelperRequest {
 #[derive(Serialize, Deserialize)]
 pub struct HelperResponse {
     ok: bool,
+    #[serde(default)]
+    code: Option<String>,
     message: Option<String>,
     details: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    warning: Option<String>,
+    #[serde(default, rename = "operationId", skip_serializing_if = "Option::is_none")]
+    operation_id: Option<u64>,
 }
 
 #[derive(Deserialize)]
@@ -53,18 +82,48 @@ pub struct WipeDeviceRequest {
     table_type: String,
     format_type: String,
     label: String,
+    dry_run: Option<bool>,
+    confirmation_token: Option<String>,
+    mount_after: Option<bool>,
 }
 
 #[derive(Deserialize)]
 pub struct SecureEraseRequest {
     device_identifier: String,
     level: u64,
+    fast_path: Option<bool>,
+    confirmation_token: Option<String>,
 }
 
 #[derive(Deserialize)]
 pub struct PartitionTableRequest {
     device_identifier: String,
     table_type: String,
+    dry_run: Option<bool>,
+    confirmation_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ConvertTableRequest {
+    device_identifier: String,
+    target: String,
+    dry_run: Option<bool>,
+    confirmation_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct PartitionLayoutEntry {
+    size: String,
+    format_type: String,
+    label: String,
+}
+
+#[derive(Deserialize)]
+pub struct PartitionLayoutRequest {
+    device_identifier: String,
+    table_type: String,
+    partitions: Vec<PartitionLayoutEntry>,
+    confirmation_token: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -73,11 +132,24 @@ pub struct CreatePartitionRequest {
     format_type: String,
     label: String,
     size: String,
+    start: Option<String>,
+    opts: Option<MkfsOptionsRequest>,
+    mount_after: Option<bool>,
+    confirmation_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct MkfsOptionsRequest {
+    block_size: Option<u64>,
+    inode_ratio: Option<u64>,
+    extra_args: Option<Vec<String>>,
 }
 
 #[derive(Deserialize)]
 pub struct DeletePartitionRequest {
     partition_identifier: String,
+    dry_run: Option<bool>,
+    confirmation_token: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -85,6 +157,10 @@ pub struct FormatPartitionRequest {
     partition_identifier: String,
     format_type: String,
     label: String,
+    opts: Option<MkfsOptionsRequest>,
+    dry_run: Option<bool>,
+    confirmation_token: Option<String>,
+    mount_after: Option<bool>,
 }
 
 #[derive(Deserialize)]
@@ -104,18 +180,21 @@ pub struct CheckPartitionRequest {
 pub struct ResizePartitionRequest {
     partition_identifier: String,
     new_size: String,
+    confirmation_token: Option<String>,
 }
 
 #[derive(Deserialize)]
 pub struct MovePartitionRequest {
     partition_identifier: String,
     new_start: String,
+    confirmation_token: Option<String>,
 }
 
 #[derive(Deserialize)]
 pub struct CopyPartitionRequest {
     source_partition: String,
     target_device: String,
+    verify: Option<bool>,
 }
 
 #[derive(Deserialize)]
@@ -123,6 +202,8 @@ pub struct FlashImageRequest {
     source_path: String,
     target_device: String,
     verify: Option<bool>,
+    write_checksum: Option<bool>,
+    confirmation_token: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -133,6 +214,7 @@ pub struct InspectImageRequest {
 #[derive(Deserialize)]
 pub struct HashImageRequest {
     source_path: String,
+    algorithms: Option<Vec<String>>,
 }
 
 #[derive(Deserialize)]
@@ -140,6 +222,9 @@ pub struct BackupImageRequest {
     source_device: String,
     target_path: String,
     compress: Option<bool>,
+    compression: Option<String>,
+    force: Option<bool>,
+    write_checksum: Option<bool>,
 }
 
 #[derive(Deserialize)]
@@ -150,6 +235,7 @@ pub struct WindowsInstallRequest {
     tpm_bypass: Option<bool>,
     local_account: Option<bool>,
     privacy_defaults: Option<bool>,
+    confirmation_token: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -159,6 +245,7 @@ pub struct PreflightRequest {
     operation: String,
     format_type: Option<String>,
     new_size: Option<String>,
+    source_path: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -172,11 +259,30 @@ pub struct ApfsAddVolumeRequest {
     container_identifier: String,
     name: String,
     role: Option<String>,
+    group_with: Option<String>,
 }
 
 #[derive(Deserialize)]
 pub struct ApfsDeleteVolumeRequest {
     volume_identifier: String,
+    confirmation_token: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApfsSnapshotInfo {
+    name: String,
+    uuid: Option<String>,
+    xid: Option<u64>,
+    created: Option<String>,
+    size: Option<u64>,
+    purgeable: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct ApfsEncryptVolumeRequest {
+    volume_identifier: String,
+    passphrase: String,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -189,6 +295,14 @@ pub struct ApfsVolumeInfo {
     volume_group_role: Option<String>,
     volume_group_name: Option<String>,
     sealed: Option<bool>,
+    #[serde(default)]
+    encrypted: Option<bool>,
+    #[serde(default)]
+    locked: Option<bool>,
+    #[serde(default)]
+    reserve: Option<u64>,
+    #[serde(default)]
+    quota: Option<u64>,
     size: u64,
     used: u64,
     mount_point: Option<String>,
@@ -213,6 +327,43 @@ pub struct SidecarStatus {
     version: Option<String>,
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HelperStatus {
+    // "missing" | "unauthorized" | "ready"
+    state: String,
+    code: Option<String>,
+    found: bool,
+    authorized: bool,
+    path: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SmartInfo {
+    device_identifier: String,
+    model: Option<String>,
+    serial: Option<String>,
+    health: Option<String>,
+    reallocated_sectors: Option<u64>,
+    power_on_hours: Option<u64>,
+    temperature_celsius: Option<i64>,
+    percentage_used: Option<u64>,
+    is_ssd: Option<bool>,
+    power_mode: Option<String>,
+    #[serde(skip_deserializing)]
+    smartctl: Option<SidecarStatus>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnlockResult {
+    device_identifier: String,
+    unlocked: bool,
+    mount_point: Option<String>,
+    error: Option<String>,
+}
+
 #[derive(Serialize)]
 pub struct PartitionBounds {
     offset: u64,
@@ -220,6 +371,41 @@ pub struct PartitionBounds {
     min_start: u64,
     max_start: u64,
     block_size: u64,
+    physical_block_size: u64,
+    min_size: Option<u64>,
+    max_size: u64,
+    shrink_supported: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartitionAlignment {
+    identifier: String,
+    offset: u64,
+    #[serde(rename = "aligned1MiB")]
+    aligned_1mib: bool,
+    aligned_physical: bool,
+    suggested_offset: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskAlignmentReport {
+    device_identifier: String,
+    physical_block_size: u64,
+    all_aligned: bool,
+    partitions: Vec<PartitionAlignment>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskIoStats {
+    device_identifier: String,
+    interval_ms: u64,
+    bytes_read: u64,
+    bytes_written: u64,
+    read_bytes_per_sec: u64,
+    write_bytes_per_sec: u64,
 }
 
 #[derive(Serialize)]
@@ -228,18 +414,66 @@ struct SudoersInstallResult {
     sudoers_path: String,
 }
 
-static ACTIVE_HELPER_PID: OnceLock<Mutex<Option<u32>>> = OnceLock::new();
+// Operation-ID -> PID des sudo-Prozesses fuer alle laufenden Helper-Aufrufe.
+static ACTIVE_HELPERS: OnceLock<Mutex<HashMap<u64, u32>>> = OnceLock::new();
+static NEXT_OPERATION_ID: AtomicU64 = AtomicU64::new(1);
 
-fn set_active_helper_pid(pid: Option<u32>) {
-    let lock = ACTIVE_HELPER_PID.get_or_init(|| Mutex::new(None));
-    if let Ok(mut guard) = lock.lock() {
-        *guard = pid;
+fn active_helpers() -> &'static Mutex<HashMap<u64, u32>> {
+    ACTIVE_HELPERS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// Meldet den Helper beim Drop wieder ab, damit Erfolg, Fehler und Timeout gleich behandelt werden.
+struct HelperRegistration(u64);
+
+impl HelperRegistration {
+    fn new(pid: u32) -> Self {
+        let id = NEXT_OPERATION_ID.fetch_add(1, Ordering::SeqCst);
+        if let Ok(mut guard) = active_helpers().lock() {
+            guard.insert(id, pid);
+        }
+        HelperRegistration(id)
+    }
+}
+
+impl Drop for HelperRegistration {
+    fn drop(&mut self) {
+        if let Ok(mut guard) = active_helpers().lock() {
+            guard.remove(&self.0);
+        }
     }
 }
 
-fn get_active_helper_pid() -> Option<u32> {
-    let lock = ACTIVE_HELPER_PID.get_or_init(|| Mutex::new(None));
-    lock.lock().ok().and_then(|guard| *guard)
+// Nach Operationen hinkt die Kernel-Tabelle oft hinterher. Statt eines festen Timeouts
+// wird `diskutil list` wiederholt, bis zwei Ausgaben uebereinstimmen, und dann neu gelesen.
+#[tauri::command(async)]
+pub fn refresh_devices() -> Result<Vec<PartitionDevice>, String> {
+    #[cfg(target_os = "macos")]
+    {
+        const ATTEMPTS: usize = 8;
+        let mut previous: Option<Vec<u8>> = None;
+        for _ in 0..ATTEMPTS {
+            let output = Command::new("diskutil")
+                .args(["list", "-plist"])
+                .output()
+                .map_err(|e| format!("diskutil failed: {e}"))?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                return Err(format!("diskutil error: {stderr}"));
+            }
+            if previous.as_deref() == Some(&output.stdout[..]) {
+                break;
+            }
+            previous = Some(output.stdout);
+            std::thread::sleep(std::time::Duration::from_millis(250));
+        }
+
+        return Ok(get_partition_devices());
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        Err("Device refresh is only supported on macOS.".to_string())
+    }
 }
 
 #[tauri::command]
@@ -299,8 +533,13 @@ pub fn get_partition_devices() -> Vec<PartitionDevice> {
                 .unwrap_or("unknown")
                 .to_string();
 
+            let (block_size, physical_block_size) = partition_info_dict(&identifier)
+                .map(|info| device_block_sizes(&info))
+                .unzip();
+
             let mut partitions = Vec::new();
             let partition_offsets = partition_offsets_for_disk(&identifier);
+            let partition_type_guids = partition_type_guids_for_disk(&identifier);
             let mut device_protected = false;
             let mut device_protection_reason: Option<String> = None;
             let parent_device = disk_dict
@@ -348,8 +587,16 @@ pub fn get_partition_devices() -> Vec<PartitionDevice> {
                         .and_then(|v| v.as_string())
                         .map(|s| s.to_string());
 
-                    let protection = partition_protection(&part_id, internal);
-                    let fs_type = partition_fs_type(&part_id);
+                    let info = partition_info_dict(&part_id);
+                    let protection = partition_protection(info.as_ref(), internal);
+                    let fs_type = partition_fs_type(info.as_ref());
+                    let (used, available) = if mount_point.is_some() {
+                        partition_usage(info.as_ref())
+                    } else {
+                        (None, None)
+                    };
+                    let (encrypted, locked) = partition_encryption(info.as_ref());
+                    let type_guid = partition_type_guids.get(&part_id).cloned();
                     if protection.0 {
                         device_protected = true;
                         if device_protection_reason.is_none() {
@@ -367,10 +614,23 @@ pub fn get_partition_devices() -> Vec<PartitionDevice> {
                         is_protected: protection.0,
                         protection_reason: protection.1,
                         fs_type,
+                        type_guid,
+                        used,
+                        available,
+                        encrypted,
+                        locked,
                     });
                 }
             }
 
+            let free_regions = if partition_offsets.len() == partitions.len() {
+                free_regions_for_disk(&content, size, partition_offsets.values().copied())
+            } else {
+                // Ohne vollstaendige Offsets wuerde belegter Platz als frei erscheinen
+                Vec::new()
+            };
+
+            let scheme = partition_scheme(&content);
             devices.push(PartitionDevice {
                 identifier,
                 size,
@@ -378,8 +638,13 @@ pub fn get_partition_devices() -> Vec<PartitionDevice> {
                 is_solid_state,
                 bus_protocol,
                 content,
+                scheme: scheme.to_string(),
+                writable_scheme: matches!(scheme, "gpt" | "mbr"),
                 parent_device,
                 partitions,
+                free_regions,
+                block_size,
+                physical_block_size,
                 is_protected: device_protected,
                 protection_reason: device_protection_reason,
             });
@@ -394,8 +659,49 @@ pub fn get_partition_devices() -> Vec<PartitionDevice> {
     }
 }
 
+// Luecken zwischen den Partitionen und am Disk-Ende, auf 1 MiB ausgerichtet. Vorne liegen
+// MBR/GPT-Header im ersten MiB, hinten bei GPT die Backup-Tabelle (max. 33 Sektoren a 512 B
+// bzw. 5 a 4 KiB); Bereiche unter 1 MiB werden nicht gemeldet.
 #[cfg(target_os = "macos")]
-fn partition_fs_type(identifier: &str) -> Option<String> {
+fn free_regions_for_disk(
+    content: &str,
+    disk_size: u64,
+    partitions: impl Iterator<Item = (u64, u64)>,
+) -> Vec<FreeRegion> {
+    const MIB: u64 = 1024 * 1024;
+    const GPT_BACKUP_RESERVE: u64 = 32 * 1024;
+
+    let footer = match content {
+        "GUID_partition_scheme" => GPT_BACKUP_RESERVE,
+        "FDisk_partition_scheme" => 0,
+        _ => return Vec::new(),
+    };
+    let align_up = |value: u64| value.div_ceil(MIB) * MIB;
+    let align_down = |value: u64| value / MIB * MIB;
+
+    let mut partitions: Vec<(u64, u64)> = partitions.collect();
+    partitions.sort_by_key(|(offset, _)| *offset);
+
+    let mut regions = Vec::new();
+    let mut cursor = MIB;
+    for (offset, size) in partitions {
+        let gap_end = align_down(offset);
+        if gap_end > cursor && gap_end - cursor >= MIB {
+            regions.push(FreeRegion { offset: cursor, size: gap_end - cursor });
+        }
+        cursor = cursor.max(align_up(offset + size));
+    }
+
+    let disk_end = align_down(disk_size.saturating_sub(footer));
+    if disk_end > cursor && disk_end - cursor >= MIB {
+        regions.push(FreeRegion { offset: cursor, size: disk_end - cursor });
+    }
+    regions
+}
+
+// Ein diskutil-info-Aufruf pro Partition; fs_type, Schutzstatus und Belegung teilen sich das Ergebnis.
+#[cfg(target_os = "macos")]
+fn partition_info_dict(identifier: &str) -> Option<plist::Dictionary> {
     let device = if identifier.starts_with("/dev/") {
         identifier.to_string()
     } else {
@@ -411,7 +717,37 @@ fn partition_fs_type(identifier: &str) -> Option<String> {
     }
 
     let plist = plist::Value::from_reader_xml(&output.stdout[..]).ok()?;
-    let dict = plist.as_dictionary()?;
+    plist.into_dictionary()
+}
+
+#[cfg(target_os = "macos")]
+fn partition_usage(info: Option<&plist::Dictionary>) -> (Option<u64>, Option<u64>) {
+    let Some(dict) = info else {
+        return (None, None);
+    };
+    let first = |keys: &[&str]| keys.iter().find_map(|key| dict.get(key).and_then(|v| v.as_unsigned_integer()));
+    let used = first(&["VolumeUsedSpace", "CapacityInUse", "UsedSpace"]);
+    let available = first(&["VolumeFreeSpace", "APFSContainerFree", "FreeSpace"]);
+    (used, available)
+}
+
+// FileVault/APFS-Verschluesselung und Sperrstatus aus diskutil info.
+#[cfg(target_os = "macos")]
+fn partition_encryption(info: Option<&plist::Dictionary>) -> (Option<bool>, Option<bool>) {
+    let Some(dict) = info else {
+        return (None, None);
+    };
+    let flag = |key: &str| dict.get(key).and_then(|v| v.as_boolean());
+    let encrypted = match (flag("Encryption"), flag("Encrypted"), flag("FileVault")) {
+        (None, None, None) => None,
+        (a, b, c) => Some(a.unwrap_or(false) || b.unwrap_or(false) || c.unwrap_or(false)),
+    };
+    (encrypted, flag("Locked"))
+}
+
+#[cfg(target_os = "macos")]
+fn partition_fs_type(info: Option<&plist::Dictionary>) -> Option<String> {
+    let dict = info?;
 
     let mut candidates = Vec::new();
     if let Some(value) = dict.get("FilesystemType").and_then(|v| v.as_string()) {
@@ -437,6 +773,12 @@ fn partition_fs_type(identifier: &str) -> Option<String> {
         if candidate.contains("ntfs") {
             return Some("ntfs".to_string());
         }
+        if candidate.contains("btrfs") {
+            return Some("btrfs".to_string());
+        }
+        if candidate.contains("xfs") {
+            return Some("xfs".to_string());
+        }
         if candidate.contains("ext4") || candidate.contains("linux") {
             return Some("ext4".to_string());
         }
@@ -468,15 +810,54 @@ fn partition_offsets_for_disk(disk_identifier: &str) -> HashMap<String, (u64, u6
         Err(_) => return HashMap::new(),
     };
 
+    // "diskutil list" enthaelt keine PartitionOffset; fehlende Offsets aus diskutil info
+    parse_partition_offsets(&plist)
+        .into_iter()
+        .filter_map(|(identifier, (offset, size))| {
+            let offset = offset.or_else(|| {
+                partition_info_dict(&identifier)?
+                    .get("PartitionOffset")
+                    .and_then(|v| v.as_unsigned_integer())
+            })?;
+            Some((identifier, (offset, size)))
+        })
+        .collect()
+}
+
+// Normalisiertes Partitionsschema aus dem Content-Feld von diskutil list. "apfs" sind die
+// synthetischen Container-Disks; "none" heisst ohne Tabelle (leer oder direkt formatiert).
+#[cfg(any(target_os = "macos", test))]
+fn partition_scheme(content: &str) -> &'static str {
+    match content {
+        "GUID_partition_scheme" => "gpt",
+        "FDisk_partition_scheme" => "mbr",
+        "Apple_partition_scheme" => "apm",
+        "EF57347C-0000-11AA-AA11-00306543ECAC" | "Apple_APFS_Container" => "apfs",
+        _ => "none",
+    }
+}
+
+// Partitionen liegen bei "diskutil list -plist <disk>" unter AllDisksAndPartitions,
+// in aelteren Ausgaben auf oberster Ebene unter "Partitions".
+#[cfg(any(target_os = "macos", test))]
+fn parse_partition_offsets(plist: &plist::Value) -> HashMap<String, (Option<u64>, u64)> {
+    use plist::Value;
+
     let dict = match plist.as_dictionary() {
         Some(d) => d,
         None => return HashMap::new(),
     };
 
-    let partitions = match dict.get("Partitions") {
-        Some(Value::Array(parts)) => parts,
-        _ => return HashMap::new(),
-    };
+    let mut partitions: Vec<&Value> = Vec::new();
+    if let Some(Value::Array(parts)) = dict.get("Partitions") {
+        partitions.extend(parts.iter());
+    } else if let Some(Value::Array(disks)) = dict.get("AllDisksAndPartitions") {
+        for disk in disks {
+            if let Some(Value::Array(parts)) = disk.as_dictionary().and_then(|d| d.get("Partitions")) {
+                partitions.extend(parts.iter());
+            }
+        }
+    }
 
     let mut offsets = HashMap::new();
     for part in partitions {
@@ -488,10 +869,10 @@ fn partition_offsets_for_disk(disk_identifier: &str) -> HashMap<String, (u64, u6
                 .to_string();
             let offset = part_dict
                 .get("PartitionOffset")
-                .and_then(|v| v.as_unsigned_integer())
-                .unwrap_or(0);
+                .and_then(|v| v.as_unsigned_integer());
             let size = part_dict
                 .get("PartitionSize")
+                .or_else(|| part_dict.get("Size"))
                 .and_then(|v| v.as_unsigned_integer())
                 .unwrap_or(0);
 
@@ -509,6 +890,64 @@ fn partition_offsets_for_disk(_disk_identifier: &str) -> HashMap<String, (u64, u
     HashMap::new()
 }
 
+// GPT Typ-GUIDs aller Partitionen einer Disk mit einem einzigen ioreg-Aufruf. "-n" wuerde
+// den IORegistry-Namen ("APPLE SSD ... Media") statt des BSD-Namens vergleichen, daher alle
+// IOMedia-Objekte holen und nach "BSD Name" filtern.
+#[cfg(target_os = "macos")]
+fn partition_type_guids_for_disk(disk_identifier: &str) -> HashMap<String, String> {
+    let output = Command::new("ioreg")
+        .args(["-a", "-r", "-c", "IOMedia"])
+        .output();
+    let output = match output {
+        Ok(o) if o.status.success() => o,
+        _ => return HashMap::new(),
+    };
+
+    match plist::Value::from_reader_xml(&output.stdout[..]) {
+        Ok(plist) => parse_partition_type_guids(&plist, disk_identifier),
+        Err(_) => HashMap::new(),
+    }
+}
+
+// IOMedia "Content" ist bei GPT die Typ-GUID (bei MBR z.B. "DOS_FAT_32", wird ignoriert).
+// Nur Partitionen der angefragten Disk: disk1 darf disk10s1 nicht mitnehmen.
+#[cfg(any(target_os = "macos", test))]
+fn parse_partition_type_guids(plist: &plist::Value, disk_identifier: &str) -> HashMap<String, String> {
+    use plist::Value;
+
+    fn collect(value: &Value, prefix: &str, guids: &mut HashMap<String, String>) {
+        let dict = match value.as_dictionary() {
+            Some(d) => d,
+            None => return,
+        };
+        let bsd_name = dict.get("BSD Name").and_then(|v| v.as_string());
+        let content = dict.get("Content").and_then(|v| v.as_string());
+        if let (Some(name), Some(content)) = (bsd_name, content) {
+            let is_partition = name
+                .strip_prefix(prefix)
+                .is_some_and(|number| !number.is_empty() && number.chars().all(|ch| ch.is_ascii_digit()));
+            let is_guid = content.len() == 36 && content.chars().filter(|ch| *ch == '-').count() == 4;
+            if is_partition && is_guid {
+                guids.insert(name.to_string(), content.to_uppercase());
+            }
+        }
+        if let Some(Value::Array(children)) = dict.get("IORegistryEntryChildren") {
+            for child in children {
+                collect(child, prefix, guids);
+            }
+        }
+    }
+
+    let prefix = format!("{}s", disk_identifier.trim_start_matches("/dev/"));
+    let mut guids = HashMap::new();
+    if let Some(entries) = plist.as_array() {
+        for entry in entries {
+            collect(entry, &prefix, &mut guids);
+        }
+    }
+    guids
+}
+
 #[cfg(target_os = "macos")]
 fn disk_external_flag(identifier: &str, disk_dict: &plist::Dictionary) -> bool {
     if let Some(external) = disk_external_flag_from_info(identifier) {
@@ -576,37 +1015,17 @@ fn disk_external_flag_from_dict(dict: &plist::Dictionary) -> bool {
 }
 
 #[cfg(not(target_os = "macos"))]
-fn partition_fs_type(_identifier: &str) -> Option<String> {
+fn partition_fs_type(_info: Option<&plist::Dictionary>) -> Option<String> {
     None
 }
 
 #[cfg(target_os = "macos")]
-fn partition_protection(identifier: &str, internal: bool) -> (bool, Option<String>) {
+fn partition_protection(info: Option<&plist::Dictionary>, internal: bool) -> (bool, Option<String>) {
     if !internal {
         return (false, None);
     }
 
-    let device = if identifier.starts_with("/dev/") {
-        identifier.to_string()
-    } else {
-        format!("/dev/{identifier}")
-    };
-
-    let output = Command::new("diskutil")
-        .args(["info", "-plist", &device])
-        .output();
-
-    let output = match output {
-        Ok(o) if o.status.success() => o,
-        _ => return (false, None),
-    };
-
-    let plist = match plist::Value::from_reader_xml(&output.stdout[..]) {
-        Ok(p) => p,
-        Err(_) => return (false, None),
-    };
-
-    let dict = match plist.as_dictionary() {
+    let dict = match info {
         Some(d) => d,
         None => return (false, None),
     };
@@ -632,11 +1051,18 @@ fn partition_protection(identifier: &str, internal: bool) -> (bool, Option<Strin
         );
     }
 
+    if let (_, Some(true)) = partition_encryption(Some(dict)) {
+        return (
+            true,
+            Some("Verschluesseltes Volume ist gesperrt (zuerst entsperren)".to_string()),
+        );
+    }
+
     (false, None)
 }
 
 #[cfg(not(target_os = "macos"))]
-fn partition_protection(_identifier: &str, _internal: bool) -> (bool, Option<String>) {
+fn partition_protection(_info: Option<&plist::Dictionary>, _internal: bool) -> (bool, Option<String>) {
     (false, None)
 }
 
@@ -669,6 +1095,81 @@ pub fn mount_disk(device_identifier: String) -> Result<(), String> {
     }
 }
 
+// Passphrase geht nur ueber stdin an diskutil (nie argv, Journal oder Log) und wird danach ueberschrieben.
+#[tauri::command(async)]
+pub fn unlock_volume(partition_identifier: String, passphrase: String) -> Result<UnlockResult, String> {
+    let result = unlock_volume_inner(&partition_identifier, &passphrase);
+    wipe_secret(passphrase);
+    result
+}
+
+#[cfg(target_os = "macos")]
+fn unlock_volume_inner(partition_identifier: &str, passphrase: &str) -> Result<UnlockResult, String> {
+    validate_token(partition_identifier, "partitionIdentifier", true)?;
+    let device = if partition_identifier.starts_with("/dev/") {
+        partition_identifier.to_string()
+    } else {
+        format!("/dev/{partition_identifier}")
+    };
+
+    let mut child = Command::new("diskutil")
+        .args(["apfs", "unlockVolume", &device, "-stdinpassphrase"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("diskutil failed: {e}"))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // Kein format!(): sonst bliebe eine zweite Kopie der Passphrase im Speicher
+        stdin
+            .write_all(passphrase.as_bytes())
+            .and_then(|_| stdin.write_all(b"\n"))
+            .map_err(|e| format!("diskutil stdin failed: {e}"))?;
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("diskutil failed: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        return Ok(UnlockResult {
+            device_identifier: device,
+            unlocked: false,
+            mount_point: None,
+            error: Some(if stderr.is_empty() { stdout } else { stderr }),
+        });
+    }
+
+    let mount_point = partition_info_dict(&device)
+        .and_then(|info| info.get("MountPoint").and_then(|v| v.as_string()).map(|s| s.to_string()))
+        .filter(|mp| !mp.is_empty());
+
+    Ok(UnlockResult {
+        device_identifier: device,
+        unlocked: true,
+        mount_point,
+        error: None,
+    })
+}
+
+#[cfg(not(target_os = "macos"))]
+fn unlock_volume_inner(_partition_identifier: &str, _passphrase: &str) -> Result<UnlockResult, String> {
+    Err("Unlock is only supported on macOS.".to_string())
+}
+
+// Ueberschreibt den Puffer eines Geheimnisses vor dem Freigeben (volatile, damit der
+// Compiler die Schreibzugriffe nicht wegoptimiert).
+fn wipe_secret(secret: String) {
+    let mut bytes = secret.into_bytes();
+    for byte in bytes.iter_mut() {
+        unsafe { std::ptr::write_volatile(byte, 0) };
+    }
+    std::sync::atomic::compiler_fence(Ordering::SeqCst);
+    drop(bytes);
+}
+
 #[tauri::command]
 pub fn mount_volume(device_identifier: String) -> Result<(), String> {
     #[cfg(target_os = "macos")]
@@ -698,45 +1199,201 @@ pub fn mount_volume(device_identifier: String) -> Result<(), String> {
     }
 }
 
-fn helper_paths(app: &tauri::AppHandle) -> Vec<std::path::PathBuf> {
-    let mut paths = Vec::new();
-    if let Ok(exe) = std::env::current_exe() {
-        if let Some(dir) = exe.parent() {
-            paths.push(dir.join("oxidisk_helper"));
+// Haengt nur das eine Volume aus; die Disk und ihre anderen Volumes bleiben verfuegbar
+pub fn unmount_volume(device_identifier: String) -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    {
+        let device = if device_identifier.starts_with("/dev/") {
+            device_identifier
+        } else {
+            format!("/dev/{device_identifier}")
+        };
+
+        let output = Command::new("diskutil")
+            .args(["unmount", &device])
+            .output()
+            .map_err(|e| format!("diskutil failed: {e}"))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("diskutil error: {stderr}"));
         }
+
+        return Ok(());
     }
-    if let Ok(path) = app
-        .path()
-        .resolve("helper/oxidisk_helper", BaseDirectory::Resource)
+
+    #[cfg(not(target_os = "macos"))]
     {
-        paths.push(path);
+        let _ = device_identifier;
+        Err("Unmount not supported on this platform".to_string())
     }
-    paths.push(std::path::PathBuf::from(
-        "/Library/PrivilegedHelperTools/com.oliverquick.oxidisk.helper",
-    ));
-    paths.push(std::path::PathBuf::from("/usr/local/bin/oxidisk_helper"));
-    paths.push(std::path::PathBuf::from("/opt/homebrew/bin/oxidisk_helper"));
-    paths
 }
 
-fn run_helper(app: &tauri::AppHandle, request: HelperRequest) -> Result<HelperResponse, String> {
-    let request_json = serde_json::to_vec(&request).map_err(|e| e.to_string())?;
+// readOnly ist fuer verdaechtige Laufwerke gedacht: erst lesend mounten, dann scannen.
+// Ein eigener Mountpunkt muss bereits als Verzeichnis existieren (diskutil legt ihn nicht an).
+#[tauri::command]
+pub fn mount_volume_opts(
+    device_identifier: String,
+    read_only: bool,
+    mount_point: Option<String>,
+) -> Result<String, String> {
+    #[cfg(target_os = "macos")]
+    {
+        validate_token(&device_identifier, "deviceIdentifier", true)?;
+        let device = if device_identifier.starts_with("/dev/") {
+            device_identifier
+        } else {
+            format!("/dev/{device_identifier}")
+        };
 
-    for path in helper_paths(app) {
-        if !path.exists() {
+        let mut args = vec!["mount".to_string()];
+        if read_only {
+            args.push("readOnly".to_string());
+        }
+        if let Some(mount_point) = mount_point.filter(|p| !p.trim().is_empty()) {
+            let path = std::path::Path::new(&mount_point);
+            if !path.is_absolute() || !path.is_dir() {
+                return Err(format!("Mount point must be an existing directory: {mount_point}"));
+            }
+            args.push("-mountPoint".to_string());
+            args.push(mount_point);
+        }
+        args.push(device.clone());
+
+        let output = Command::new("diskutil")
+            .args(&args)
+            .output()
+            .map_err(|e| format!("diskutil failed: {e}"))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("diskutil error: {stderr}"));
+        }
+
+        let mounted_at = partition_info_dict(&device)
+            .and_then(|info| info.get("MountPoint").and_then(|v| v.as_string()).map(|s| s.to_string()))
+            .filter(|p| !p.is_empty())
+            .ok_or_else(|| "Volume mounted but no mount point reported".to_string())?;
+        return Ok(mounted_at);
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = (device_identifier, read_only, mount_point);
+        Err("Mount not supported on this platform".to_string())
+    }
+}
+
+fn helper_paths(app: &tauri::AppHandle) -> Vec<std::path::PathBuf> {
+    let mut paths = Vec::new();
+    if let Ok(exe) = std::env::current_exe() {
+        if let Some(dir) = exe.parent() {
+            paths.push(dir.join("oxidisk_helper"));
+        }
+    }
+    if let Ok(path) = app
+        .path()
+        .resolve("helper/oxidisk_helper", BaseDirectory::Resource)
+    {
+        paths.push(path);
+    }
+    paths.push(std::path::PathBuf::from(
+        "/Library/PrivilegedHelperTools/com.oliverquick.oxidisk.helper",
+    ));
+    paths.push(std::path::PathBuf::from("/usr/local/bin/oxidisk_helper"));
+    paths.push(std::path::PathBuf::from("/opt/homebrew/bin/oxidisk_helper"));
+    paths
+}
+
+// Protokollrevision des JSON-Formats; muss zu HELPER_PROTOCOL_REVISION im Helper passen.
+const HELPER_PROTOCOL_REVISION: u64 = 1;
+
+static HELPER_PROTOCOL: OnceLock<Option<u64>> = OnceLock::new();
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HelperVersion {
+    version: Option<String>,
+    protocol: Option<u64>,
+    expected_protocol: u64,
+    app_version: String,
+    compatible: bool,
+}
+
+fn query_helper_version(app: &tauri::AppHandle) -> Result<(Option<String>, Option<u64>), String> {
+    let response = run_helper_raw(
+        app,
+        HelperRequest {
+            action: "version".to_string(),
+            payload: json!({}),
+        },
+    )?;
+    // Helper vor Einfuehrung von "version" kennen die Aktion nicht: Protokoll 0.
+    if !response.ok {
+        return Ok((None, Some(0)));
+    }
+    let details = response.details.unwrap_or_default();
+    let version = details.get("version").and_then(|v| v.as_str()).map(|s| s.to_string());
+    let protocol = details.get("protocol").and_then(|v| v.as_u64());
+    Ok((version, protocol))
+}
+
+#[tauri::command]
+pub fn helper_version(app: tauri::AppHandle) -> Result<HelperVersion, String> {
+    let (version, protocol) = query_helper_version(&app)?;
+    Ok(HelperVersion {
+        version,
+        protocol,
+        expected_protocol: HELPER_PROTOCOL_REVISION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        compatible: protocol.map(|p| p >= HELPER_PROTOCOL_REVISION).unwrap_or(false),
+    })
+}
+
+// Einmal pro Prozess: veralteter Helper wird als Warnung an die Antwort gehaengt.
+fn attach_protocol_warning(app: &tauri::AppHandle, mut response: HelperResponse) -> HelperResponse {
+    let protocol = HELPER_PROTOCOL.get_or_init(|| query_helper_version(app).ok().and_then(|(_, p)| p));
+    if let Some(protocol) = protocol {
+        if *protocol < HELPER_PROTOCOL_REVISION {
+            response.warning = Some(format!(
+                "Helper protocol {protocol} is older than expected {HELPER_PROTOCOL_REVISION}; please reinstall the helper."
+            ));
+        }
+    }
+    response
+}
+
+fn run_helper(app: &tauri::AppHandle, request: HelperRequest) -> Result<HelperResponse, String> {
+    let response = run_helper_raw(app, request)?;
+    Ok(attach_protocol_warning(app, response))
+}
+
+fn run_helper_raw(app: &tauri::AppHandle, request: HelperRequest) -> Result<HelperResponse, String> {
+    let request_json = serde_json::to_vec(&request).map_err(|e| e.to_string())?;
+
+    for path in helper_paths(app) {
+        if !path.exists() {
             continue;
         }
 
-        let mut child = Command::new("sudo")
+        let mut command = Command::new("sudo");
+        command
             .arg("-n")
             .arg(&path)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
+            .stderr(Stdio::piped());
+        // Eigene Prozessgruppe, damit Signale an die App (z.B. Ctrl-C) den Helper nicht erreichen.
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            command.process_group(0);
+        }
+        let mut child = command
             .spawn()
             .map_err(|e| format!("Helper start failed: {e}"))?;
 
-        set_active_helper_pid(Some(child.id()));
+        let registration = HelperRegistration::new(child.id());
 
         if let Some(mut stdin) = child.stdin.take() {
             stdin
@@ -744,24 +1401,26 @@ fn run_helper(app: &tauri::AppHandle, request: HelperRequest) -> Result<HelperRe
                 .map_err(|e| format!("Helper stdin failed: {e}"))?;
         }
 
-        let output = child
-            .wait_with_output()
-            .map_err(|e| format!("Helper run failed: {e}"))?;
+        // Log-/Progress-Zeilen gehen vorweg; die Antwort steht immer in der letzten Zeile.
+        let mut last_line = String::new();
+        let (status, stderr) = drive_helper(&mut child, helper_timeout_for(&request.action), |line| {
+            last_line = line;
+        })?;
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
+        if !status.success() {
             if stderr.contains("a password is required") || stderr.contains("a password is required") {
-                return Err("Helper requires sudoers setup. Please run setup first.".to_string());
+                return Err(coded_error("SUDOERS_REQUIRED", "Helper requires sudoers setup. Please run setup first."));
             }
-            return Err(format!("Helper error: {stderr}"));
+            return Err(coded_error("HELPER_FAILED", format!("Helper error: {stderr}")));
         }
 
-        let response: HelperResponse = serde_json::from_slice(&output.stdout)
-            .map_err(|e| format!("Helper response parse failed: {e}"))?;
+        let mut response: HelperResponse = serde_json::from_str(&last_line)
+            .map_err(|e| coded_error("PROTOCOL_ERROR", format!("Helper response parse failed: {e}")))?;
+        response.operation_id = Some(registration.0);
         return Ok(response);
     }
 
-    Err("Privileged helper not found. Please install the helper tool.".to_string())
+    Err(coded_error("HELPER_NOT_FOUND", "Privileged helper not found. Please install the helper tool."))
 }
 
 fn run_helper_stream(
@@ -776,73 +1435,184 @@ fn run_helper_stream(
             continue;
         }
 
-        let mut child = Command::new("sudo")
+        let mut command = Command::new("sudo");
+        command
             .arg("-n")
             .arg(&path)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
+            .stderr(Stdio::piped());
+        // Eigene Prozessgruppe, damit Signale an die App (z.B. Ctrl-C) den Helper nicht erreichen.
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            command.process_group(0);
+        }
+        let mut child = command
             .spawn()
             .map_err(|e| format!("Helper start failed: {e}"))?;
 
+        let registration = HelperRegistration::new(child.id());
+        let operation_id = registration.0;
+        let _ = window.emit(
+            "partition-operation-started",
+            json!({ "operationId": operation_id, "action": request.action }),
+        );
+
         if let Some(mut stdin) = child.stdin.take() {
             stdin
                 .write_all(&request_json)
                 .map_err(|e| format!("Helper stdin failed: {e}"))?;
         }
 
-        let stdout = child.stdout.take().ok_or_else(|| "Failed to read helper stdout".to_string())?;
-        let stderr = child.stderr.take().ok_or_else(|| "Failed to read helper stderr".to_string())?;
-        let mut stdout_reader = BufReader::new(stdout);
-        let mut stderr_reader = BufReader::new(stderr);
-
-        let mut buffer = String::new();
+        // Jede Progress-/Log-Zeile setzt den Leerlauf-Timer zurueck.
         let mut last_json: Option<String> = None;
-        loop {
-            buffer.clear();
-            let bytes = stdout_reader
-                .read_line(&mut buffer)
-                .map_err(|e| format!("Helper stdout failed: {e}"))?;
-            if bytes == 0 {
-                break;
-            }
-            let line = buffer.trim().to_string();
-            if line.is_empty() {
-                continue;
-            }
-            if let Ok(value) = serde_json::from_str::<Value>(&line) {
+        let result = drive_helper(&mut child, helper_timeout_for(&request.action), |line| {
+            if let Ok(mut value) = serde_json::from_str::<Value>(&line) {
+                if let Some(map) = value.as_object_mut() {
+                    map.insert("operationId".to_string(), json!(operation_id));
+                }
                 if value.get("type").and_then(|v| v.as_str()) == Some("progress") {
                     let _ = window.emit("partition-operation-progress", value);
-                    continue;
+                    return;
                 }
                 if value.get("type").and_then(|v| v.as_str()) == Some("log") {
                     let _ = window.emit("partition-operation-log", value);
-                    continue;
+                    return;
+                }
+                // Haelt nur den Leerlauf-Timer offen
+                if value.get("type").and_then(|v| v.as_str()) == Some("heartbeat") {
+                    return;
                 }
             }
             last_json = Some(line);
-        }
-
-        let status = child.wait().map_err(|e| format!("Helper run failed: {e}"))?;
-        let mut stderr_text = String::new();
-        let _ = stderr_reader.read_to_string(&mut stderr_text);
+        });
 
-        set_active_helper_pid(None);
+        drop(registration);
+        let (status, stderr_text) = result?;
 
         if !status.success() {
             if stderr_text.contains("a password is required") {
-                return Err("Helper requires sudoers setup. Please run setup first.".to_string());
+                return Err(coded_error("SUDOERS_REQUIRED", "Helper requires sudoers setup. Please run setup first."));
             }
-            return Err(format!("Helper error: {stderr_text}"));
+            return Err(coded_error("HELPER_FAILED", format!("Helper error: {stderr_text}")));
         }
 
         let last_json = last_json.ok_or_else(|| "No helper response".to_string())?;
-        let response: HelperResponse = serde_json::from_str(&last_json)
-            .map_err(|e| format!("Helper response parse failed: {e}"))?;
-        return Ok(response);
+        let mut response: HelperResponse = serde_json::from_str(&last_json)
+            .map_err(|e| coded_error("PROTOCOL_ERROR", format!("Helper response parse failed: {e}")))?;
+        response.operation_id = Some(operation_id);
+        return Ok(attach_protocol_warning(app, response));
+    }
+
+    Err(coded_error("HELPER_NOT_FOUND", "Privileged helper not found. Please install the helper tool."))
+}
+
+// Leerlauf-Timeouts in Sekunden (schreibende Operationen / Metadaten-Aufrufe); 0 = Standard.
+static HELPER_WRITE_TIMEOUT_SECS: AtomicU64 = AtomicU64::new(0);
+static HELPER_METADATA_TIMEOUT_SECS: AtomicU64 = AtomicU64::new(0);
+const DEFAULT_WRITE_TIMEOUT_SECS: u64 = 10 * 60;
+const DEFAULT_METADATA_TIMEOUT_SECS: u64 = 2 * 60;
+// Der Helper sendet waehrend stiller Werkzeuge alle 30 s einen Heartbeat (TOOL_HEARTBEAT)
+const MIN_HELPER_TIMEOUT_SECS: u64 = 60;
+
+#[tauri::command]
+pub fn set_helper_timeouts(write_secs: Option<u64>, metadata_secs: Option<u64>) {
+    let clamp = |secs: Option<u64>| secs.filter(|secs| *secs > 0).map_or(0, |secs| secs.max(MIN_HELPER_TIMEOUT_SECS));
+    HELPER_WRITE_TIMEOUT_SECS.store(clamp(write_secs), Ordering::SeqCst);
+    HELPER_METADATA_TIMEOUT_SECS.store(clamp(metadata_secs), Ordering::SeqCst);
+}
+
+// Nur bekannte, kurze Metadaten-Aufrufe bekommen das knappe Timeout; alles andere (auch
+// neu hinzukommende Aktionen) gilt als potentiell lang laufend.
+fn helper_timeout_for(action: &str) -> std::time::Duration {
+    let metadata = matches!(
+        action,
+        "version"
+            | "smart_info"
+            | "preview_resize"
+            | "set_label_uuid"
+            | "rename_volume"
+            | "set_partition_name"
+            | "force_unmount"
+            | "safe_eject"
+            | "fstab_entry"
+            | "filesystem_constraints"
+            | "supported_filesystems"
+            | "apfs_list_volumes"
+            | "apfs_list_snapshots"
+            | "get_journal"
+            | "clear_journal"
+            | "get_audit_log"
+            | "clear_audit_log"
+    );
+    let (configured, default) = if metadata {
+        (HELPER_METADATA_TIMEOUT_SECS.load(Ordering::SeqCst), DEFAULT_METADATA_TIMEOUT_SECS)
+    } else {
+        (HELPER_WRITE_TIMEOUT_SECS.load(Ordering::SeqCst), DEFAULT_WRITE_TIMEOUT_SECS)
+    };
+    std::time::Duration::from_secs(if configured == 0 { default } else { configured })
+}
+
+// Liest stdout des Helpers zeilenweise in einem eigenen Thread; bleibt idle_timeout lang
+// jede Ausgabe aus, wird der Helper beendet und TIMEOUT gemeldet.
+fn drive_helper(
+    child: &mut std::process::Child,
+    idle_timeout: std::time::Duration,
+    mut on_line: impl FnMut(String),
+) -> Result<(std::process::ExitStatus, String), String> {
+    let stdout = child.stdout.take().ok_or_else(|| "Failed to read helper stdout".to_string())?;
+    let stderr = child.stderr.take().ok_or_else(|| "Failed to read helper stderr".to_string())?;
+
+    let (tx, rx) = std::sync::mpsc::channel::<String>();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    let stderr_thread = std::thread::spawn(move || {
+        let mut text = String::new();
+        let _ = BufReader::new(stderr).read_to_string(&mut text);
+        text
+    });
+
+    loop {
+        match rx.recv_timeout(idle_timeout) {
+            Ok(line) => {
+                let line = line.trim().to_string();
+                if !line.is_empty() {
+                    on_line(line);
+                }
+            }
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
+                terminate_helper(child);
+                return Err(coded_error(
+                    "TIMEOUT",
+                    format!("Helper produced no output for {} seconds and was stopped", idle_timeout.as_secs()),
+                ));
+            }
+            Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => break,
+        }
     }
 
-    Err("Privileged helper not found. Please install the helper tool.".to_string())
+    let status = child.wait().map_err(|e| format!("Helper run failed: {e}"))?;
+    let stderr_text = stderr_thread.join().unwrap_or_default();
+    Ok((status, stderr_text))
+}
+
+// SIGTERM an sudo wird an den Helper weitergereicht; SIGKILL nur als letzter Ausweg.
+fn terminate_helper(child: &mut std::process::Child) {
+    let _ = signal_helper(child.id());
+    for _ in 0..50 {
+        if let Ok(Some(_)) = child.try_wait() {
+            return;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    let _ = child.kill();
+    let _ = child.wait();
 }
 
 fn read_id_username() -> Result<String, String> {
@@ -857,10 +1627,13 @@ fn read_id_username() -> Result<String, String> {
     Ok(username)
 }
 
+// Leere Werte und fuehrendes '-' wuerden von diskutil/sudo als Option gelesen.
 fn validate_token(value: &str, field: &str, allow_slash: bool) -> Result<(), String> {
-    let ok = value.chars().all(|ch| {
-        ch.is_ascii_alphanumeric() || ch == '_' || ch == '-' || ch == '.' || (allow_slash && ch == '/')
-    });
+    let ok = !value.is_empty()
+        && !value.starts_with('-')
+        && value.chars().all(|ch| {
+            ch.is_ascii_alphanumeric() || ch == '_' || ch == '-' || ch == '.' || (allow_slash && ch == '/')
+        });
     if ok {
         Ok(())
     } else {
@@ -868,6 +1641,50 @@ fn validate_token(value: &str, field: &str, allow_slash: bool) -> Result<(), Str
     }
 }
 
+// Helper-Pfade duerfen Leerzeichen enthalten (z.B. "/Applications/Oxi Disk.app"),
+// muessen aber absolut sein und duerfen keine sudoers- oder Shell-Sonderzeichen enthalten.
+#[cfg(any(target_os = "macos", test))]
+fn validate_helper_path(path: &str) -> Result<(), String> {
+    let ok = path.starts_with('/')
+        && !path.split('/').any(|part| part == "..")
+        && path
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '_' | '-' | '.' | '/' | ' '));
+    if ok {
+        Ok(())
+    } else {
+        Err("Invalid characters in helper path".to_string())
+    }
+}
+
+// In sudoers trennt ein Leerzeichen Befehl und Argumente, daher maskieren.
+#[cfg(any(target_os = "macos", test))]
+fn sudoers_entry(username: &str, helper_path: &str) -> String {
+    let escaped = helper_path.replace(' ', "\\ ");
+    format!("{username} ALL=(root) NOPASSWD: {escaped}\n")
+}
+
+// Inhalt und Zielpfad kommen ueber "on run argv" und "quoted form of" ins Skript, nie per
+// String-Interpolation. Erst das privilegierte Skript legt per mktemp eine root-eigene Datei an,
+// prueft genau diese mit visudo und installiert sie; kein vom Nutzer beschreibbarer Pfad dazwischen.
+#[cfg(any(target_os = "macos", test))]
+fn sudoers_install_args(content: &str, target: &str) -> Vec<String> {
+    vec![
+        "-e".to_string(),
+        "on run argv".to_string(),
+        "-e".to_string(),
+        "set entry to quoted form of (item 1 of argv)".to_string(),
+        "-e".to_string(),
+        "set dst to quoted form of (item 2 of argv)".to_string(),
+        "-e".to_string(),
+        "do shell script \"tmp=$(/usr/bin/mktemp /tmp/oxidisk-sudoers.XXXXXX) || exit 1; /usr/bin/printf '%s' \" & entry & \" > \\\"$tmp\\\" && /usr/sbin/visudo -cf \\\"$tmp\\\" && /usr/bin/install -m 0440 -o root -g wheel \\\"$tmp\\\" \" & dst & \"; rc=$?; /bin/rm -f \\\"$tmp\\\"; exit $rc\" with administrator privileges".to_string(),
+        "-e".to_string(),
+        "end run".to_string(),
+        content.to_string(),
+        target.to_string(),
+    ]
+}
+
 #[tauri::command]
 pub fn install_sudoers_helper(app: tauri::AppHandle) -> Result<HelperResponse, String> {
     #[cfg(target_os = "macos")]
@@ -885,18 +1702,13 @@ pub fn install_sudoers_helper(app: tauri::AppHandle) -> Result<HelperResponse, S
             .ok_or_else(|| "Invalid helper path".to_string())?
             .to_string();
 
-        validate_token(&helper_path_str, "helper path", true)?;
+        validate_helper_path(&helper_path_str)?;
 
         let sudoers_path = "/etc/sudoers.d/oxidisk";
-        let sudoers_line = format!("{username} ALL=(root) NOPASSWD: {helper_path_str}");
-
-        let command = format!(
-            "/bin/sh -c \"/usr/bin/printf '%s\\n' '{sudoers_line}' > {sudoers_path} && /bin/chmod 440 {sudoers_path}\""
-        );
+        let entry = sudoers_entry(&username, &helper_path_str);
 
         let output = Command::new("osascript")
-            .arg("-e")
-            .arg(format!("do shell script \"{command}\" with administrator privileges"))
+            .args(sudoers_install_args(&entry, sudoers_path))
             .output()
             .map_err(|e| format!("Failed to run osascript: {e}"))?;
 
@@ -907,10 +1719,13 @@ pub fn install_sudoers_helper(app: tauri::AppHandle) -> Result<HelperResponse, S
 
         return Ok(HelperResponse {
             ok: true,
+            code: None,
             message: Some("Sudoers installed".to_string()),
             details: Some(
                 json!(SudoersInstallResult { helper_path: helper_path_str, sudoers_path: sudoers_path.to_string() })
             ),
+            warning: None,
+            operation_id: None,
         });
     }
 
@@ -920,13 +1735,30 @@ pub fn install_sudoers_helper(app: tauri::AppHandle) -> Result<HelperResponse, S
     }
 }
 
+fn mkfs_options_payload(opts: &MkfsOptionsRequest) -> Value {
+    json!({
+        "blockSize": opts.block_size,
+        "inodeRatio": opts.inode_ratio,
+        "extraArgs": opts.extra_args.clone().unwrap_or_default(),
+    })
+}
+
+// Fehler mit Code werden als "CODE: Text" weitergegeben, damit die UI ohne Textvergleich verzweigen kann.
+fn coded_error(code: &str, message: impl std::fmt::Display) -> String {
+    format!("{code}: {message}")
+}
+
 fn ok_or_message(response: HelperResponse) -> Result<HelperResponse, String> {
     if response.ok {
         Ok(response)
     } else {
-        Err(response
+        let message = response
             .message
-            .unwrap_or("Helper reported failure.".to_string()))
+            .unwrap_or("Helper reported failure.".to_string());
+        Err(match response.code {
+            Some(code) => coded_error(&code, message),
+            None => message,
+        })
     }
 }
 
@@ -937,6 +1769,9 @@ pub fn wipe_device(app: tauri::AppHandle, request: WipeDeviceRequest) -> Result<
         "tableType": request.table_type,
         "formatType": request.format_type,
         "label": request.label,
+        "dryRun": request.dry_run.unwrap_or(false),
+        "confirmationToken": request.confirmation_token,
+        "mountAfter": request.mount_after.unwrap_or(false),
     });
 
     let response = run_helper(
@@ -951,14 +1786,21 @@ pub fn wipe_device(app: tauri::AppHandle, request: WipeDeviceRequest) -> Result<
 }
 
 #[tauri::command]
-pub fn secure_erase(app: tauri::AppHandle, request: SecureEraseRequest) -> Result<HelperResponse, String> {
+pub fn secure_erase(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    request: SecureEraseRequest,
+) -> Result<HelperResponse, String> {
     let payload = json!({
         "deviceIdentifier": request.device_identifier,
         "level": request.level,
+        "fastPath": request.fast_path.unwrap_or(false),
+        "confirmationToken": request.confirmation_token,
     });
 
-    let response = run_helper(
+    let response = run_helper_stream(
         &app,
+        &window,
         HelperRequest {
             action: "secure_erase".to_string(),
             payload,
@@ -976,6 +1818,8 @@ pub fn create_partition_table(
     let payload = json!({
         "deviceIdentifier": request.device_identifier,
         "tableType": request.table_type,
+        "dryRun": request.dry_run.unwrap_or(false),
+        "confirmationToken": request.confirmation_token,
     });
 
     let response = run_helper(
@@ -989,6 +1833,30 @@ pub fn create_partition_table(
     ok_or_message(response)
 }
 
+// MBR <-> GPT ohne Datenverlust (sgdisk); der Helper prueft vorher, ob das Layout passt
+#[tauri::command]
+pub fn convert_partition_table(
+    app: tauri::AppHandle,
+    request: ConvertTableRequest,
+) -> Result<HelperResponse, String> {
+    let payload = json!({
+        "deviceIdentifier": request.device_identifier,
+        "target": request.target,
+        "dryRun": request.dry_run.unwrap_or(false),
+        "confirmationToken": request.confirmation_token,
+    });
+
+    let response = run_helper(
+        &app,
+        HelperRequest {
+            action: "convert_partition_table".to_string(),
+            payload,
+        },
+    )?;
+
+    ok_or_message(response)
+}
+
 #[tauri::command]
 pub fn create_partition(
     app: tauri::AppHandle,
@@ -999,6 +1867,10 @@ pub fn create_partition(
         "formatType": request.format_type,
         "label": request.label,
         "size": request.size,
+        "start": request.start,
+        "opts": request.opts.as_ref().map(mkfs_options_payload),
+        "mountAfter": request.mount_after.unwrap_or(false),
+        "confirmationToken": request.confirmation_token,
     });
 
     let response = run_helper(
@@ -1012,6 +1884,42 @@ pub fn create_partition(
     ok_or_message(response)
 }
 
+#[tauri::command]
+pub fn apply_partition_layout(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    request: PartitionLayoutRequest,
+) -> Result<HelperResponse, String> {
+    let partitions: Vec<Value> = request
+        .partitions
+        .iter()
+        .map(|entry| {
+            json!({
+                "size": entry.size,
+                "formatType": entry.format_type,
+                "label": entry.label,
+            })
+        })
+        .collect();
+    let payload = json!({
+        "deviceIdentifier": request.device_identifier,
+        "tableType": request.table_type,
+        "partitions": partitions,
+        "confirmationToken": request.confirmation_token,
+    });
+
+    let response = run_helper_stream(
+        &app,
+        &window,
+        HelperRequest {
+            action: "apply_partition_layout".to_string(),
+            payload,
+        },
+    )?;
+
+    ok_or_message(response)
+}
+
 #[tauri::command]
 pub fn delete_partition(
     app: tauri::AppHandle,
@@ -1019,6 +1927,8 @@ pub fn delete_partition(
 ) -> Result<HelperResponse, String> {
     let payload = json!({
         "partitionIdentifier": request.partition_identifier,
+        "dryRun": request.dry_run.unwrap_or(false),
+        "confirmationToken": request.confirmation_token,
     });
 
     let response = run_helper(
@@ -1041,6 +1951,10 @@ pub fn format_partition(
         "partitionIdentifier": request.partition_identifier,
         "formatType": request.format_type,
         "label": request.label,
+        "opts": request.opts.as_ref().map(mkfs_options_payload),
+        "dryRun": request.dry_run.unwrap_or(false),
+        "confirmationToken": request.confirmation_token,
+        "mountAfter": request.mount_after.unwrap_or(false),
     });
 
     let response = run_helper(
@@ -1076,6 +1990,102 @@ pub fn set_label_uuid(
     ok_or_message(response)
 }
 
+#[tauri::command]
+pub fn rename_volume(
+    app: tauri::AppHandle,
+    device_identifier: String,
+    name: String,
+) -> Result<HelperResponse, String> {
+    let payload = json!({
+        "deviceIdentifier": device_identifier,
+        "name": name,
+    });
+
+    let response = run_helper(
+        &app,
+        HelperRequest {
+            action: "rename_volume".to_string(),
+            payload,
+        },
+    )?;
+
+    ok_or_message(response)
+}
+
+// Finder zeigt .VolumeIcon.icns nur an, wenn am Volume-Root das Custom-Icon-Flag
+// (kHasCustomIcon in com.apple.FinderInfo) gesetzt ist.
+#[tauri::command]
+pub fn set_volume_icon(mount_point: String, icon_path: String) -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    {
+        let root = std::path::Path::new(&mount_point);
+        if !root.is_absolute() || !root.is_dir() {
+            return Err(format!("Mount point not found: {mount_point}"));
+        }
+        let icon = std::path::Path::new(&icon_path);
+        let is_icns = icon
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.eq_ignore_ascii_case("icns"))
+            .unwrap_or(false);
+        if !icon.is_file() || !is_icns {
+            return Err(format!("Icon must be an existing .icns file: {icon_path}"));
+        }
+
+        std::fs::copy(icon, root.join(".VolumeIcon.icns"))
+            .map_err(|e| format!("Failed to write volume icon: {e}"))?;
+
+        // SetFile gibt es nur mit den Xcode Command Line Tools; sonst FinderInfo direkt schreiben
+        let set_file = Command::new("SetFile").args(["-a", "C", &mount_point]).output();
+        let flagged = matches!(set_file, Ok(ref output) if output.status.success());
+        if !flagged {
+            let output = Command::new("xattr")
+                .args([
+                    "-wx",
+                    "com.apple.FinderInfo",
+                    "0000000000000000040000000000000000000000000000000000000000000000",
+                    &mount_point,
+                ])
+                .output()
+                .map_err(|e| format!("xattr failed: {e}"))?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                return Err(format!("xattr error: {stderr}"));
+            }
+        }
+
+        return Ok(());
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = (mount_point, icon_path);
+        Err("Custom volume icons are only supported on macOS".to_string())
+    }
+}
+
+#[tauri::command]
+pub fn set_partition_name(
+    app: tauri::AppHandle,
+    partition_identifier: String,
+    name: String,
+) -> Result<HelperResponse, String> {
+    let payload = json!({
+        "partitionIdentifier": partition_identifier,
+        "name": name,
+    });
+
+    let response = run_helper(
+        &app,
+        HelperRequest {
+            action: "set_partition_name".to_string(),
+            payload,
+        },
+    )?;
+
+    ok_or_message(response)
+}
+
 #[tauri::command]
 pub fn check_partition(
     app: tauri::AppHandle,
@@ -1106,6 +2116,7 @@ pub fn resize_partition(
     let payload = json!({
         "partitionIdentifier": request.partition_identifier,
         "newSize": request.new_size,
+        "confirmationToken": request.confirmation_token,
     });
 
     let response = run_helper_stream(
@@ -1120,6 +2131,29 @@ pub fn resize_partition(
     ok_or_message(response)
 }
 
+// Nur Vorschau: rechnet neues Partitionsende und freien Bereich aus, ohne etwas zu aendern
+#[tauri::command]
+pub fn preview_resize(
+    app: tauri::AppHandle,
+    partition_identifier: String,
+    new_size: String,
+) -> Result<HelperResponse, String> {
+    let payload = json!({
+        "partitionIdentifier": partition_identifier,
+        "newSize": new_size,
+    });
+
+    let response = run_helper(
+        &app,
+        HelperRequest {
+            action: "preview_resize".to_string(),
+            payload,
+        },
+    )?;
+
+    ok_or_message(response)
+}
+
 #[tauri::command]
 pub fn move_partition(
     app: tauri::AppHandle,
@@ -1129,6 +2163,7 @@ pub fn move_partition(
     let payload = json!({
         "partitionIdentifier": request.partition_identifier,
         "newStart": request.new_start,
+        "confirmationToken": request.confirmation_token,
     });
 
     let response = run_helper_stream(
@@ -1143,6 +2178,21 @@ pub fn move_partition(
     ok_or_message(response)
 }
 
+// Setzt einen per Journal erfassten, unterbrochenen Move fort
+#[tauri::command]
+pub fn resume_move(app: tauri::AppHandle, window: tauri::Window) -> Result<HelperResponse, String> {
+    let response = run_helper_stream(
+        &app,
+        &window,
+        HelperRequest {
+            action: "resume_move".to_string(),
+            payload: json!({}),
+        },
+    )?;
+
+    ok_or_message(response)
+}
+
 #[tauri::command]
 pub fn copy_partition(
     app: tauri::AppHandle,
@@ -1152,6 +2202,7 @@ pub fn copy_partition(
     let payload = json!({
         "sourcePartition": request.source_partition,
         "targetDevice": request.target_device,
+        "verify": request.verify.unwrap_or(false),
     });
 
     let response = run_helper_stream(
@@ -1176,6 +2227,8 @@ pub fn flash_image(
         "sourcePath": request.source_path,
         "targetDevice": request.target_device,
         "verify": request.verify.unwrap_or(true),
+        "writeChecksum": request.write_checksum.unwrap_or(false),
+        "confirmationToken": request.confirmation_token,
     });
 
     let response = run_helper_stream(
@@ -1215,6 +2268,7 @@ pub fn hash_image(
 ) -> Result<HelperResponse, String> {
     let payload = json!({
         "sourcePath": request.source_path,
+        "algorithms": request.algorithms.unwrap_or_default(),
     });
 
     let response = run_helper_stream(
@@ -1239,6 +2293,9 @@ pub fn backup_image(
         "sourceDevice": request.source_device,
         "targetPath": request.target_path,
         "compress": request.compress.unwrap_or(false),
+        "compression": request.compression,
+        "force": request.force.unwrap_or(false),
+        "writeChecksum": request.write_checksum.unwrap_or(false),
     });
 
     let response = run_helper_stream(
@@ -1266,6 +2323,7 @@ pub fn windows_install(
         "tpmBypass": request.tpm_bypass.unwrap_or(false),
         "localAccount": request.local_account.unwrap_or(false),
         "privacyDefaults": request.privacy_defaults.unwrap_or(false),
+        "confirmationToken": request.confirmation_token,
     });
 
     let response = run_helper_stream(
@@ -1281,23 +2339,39 @@ pub fn windows_install(
 }
 
 #[tauri::command]
-pub fn cancel_helper_operation() -> Result<(), String> {
-    if let Some(pid) = get_active_helper_pid() {
-        let output = Command::new("kill")
-            .args(["-TERM", &pid.to_string()])
-            .output()
-            .map_err(|e| format!("Cancel failed: {e}"))?;
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(format!("Cancel error: {stderr}"));
+pub fn cancel_helper_operation(operation_id: Option<u64>) -> Result<(), String> {
+    // Ohne ID wird die zuletzt gestartete Operation abgebrochen.
+    let pid = {
+        let guard = active_helpers()
+            .lock()
+            .map_err(|_| "Operation registry poisoned".to_string())?;
+        match operation_id {
+            Some(id) => guard.get(&id).copied(),
+            None => guard.iter().max_by_key(|(id, _)| **id).map(|(_, pid)| *pid),
         }
-        set_active_helper_pid(None);
-        return Ok(());
+    };
+
+    if let Some(pid) = pid {
+        return signal_helper(pid);
     }
 
     Err("No active operation to cancel".to_string())
 }
 
+// SIGTERM nur an sudo, das es an den Helper weiterreicht. Nicht an die Prozessgruppe:
+// darin laufen auch resize2fs, sgdisk oder diskutil, die nie mitten im Lauf enden duerfen.
+fn signal_helper(pid: u32) -> Result<(), String> {
+    let output = Command::new("kill")
+        .args(["-TERM", &pid.to_string()])
+        .output()
+        .map_err(|e| format!("Cancel failed: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Cancel error: {stderr}"));
+    }
+    Ok(())
+}
+
 #[tauri::command]
 pub fn preflight_partition(
     app: tauri::AppHandle,
@@ -1309,6 +2383,7 @@ pub fn preflight_partition(
         "operation": request.operation,
         "formatType": request.format_type,
         "newSize": request.new_size,
+        "sourcePath": request.source_path,
     });
 
     let response = run_helper(
@@ -1343,6 +2418,136 @@ pub fn force_unmount_partition(
     ok_or_message(response)
 }
 
+// readOnly=false misst zusaetzlich die Schreibrate in freiem Bereich (nur nicht gemountete, ungeschuetzte Laufwerke)
+#[tauri::command]
+pub fn benchmark_device(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    device_identifier: String,
+    read_only: bool,
+    confirmation_token: Option<String>,
+) -> Result<HelperResponse, String> {
+    let payload = json!({
+        "deviceIdentifier": device_identifier,
+        "readOnly": read_only,
+        "confirmationToken": confirmation_token,
+    });
+
+    let response = run_helper_stream(
+        &app,
+        &window,
+        HelperRequest {
+            action: "benchmark_device".to_string(),
+            payload,
+        },
+    )?;
+
+    ok_or_message(response)
+}
+
+// Beschreibbarer NTFS-Mount ueber ntfs-3g; ohne Sidecar nur lesend (readWrite=false + Warnung)
+#[tauri::command]
+pub fn mount_ntfs_rw(app: tauri::AppHandle, device_identifier: String) -> Result<HelperResponse, String> {
+    let payload = json!({
+        "deviceIdentifier": device_identifier,
+    });
+
+    let response = run_helper(
+        &app,
+        HelperRequest {
+            action: "mount_ntfs_rw".to_string(),
+            payload,
+        },
+    )?;
+
+    ok_or_message(response)
+}
+
+// Nur Signaturen entfernen (wipefs -a), kein vollstaendiges Loeschen
+#[tauri::command]
+pub fn wipe_signatures(
+    app: tauri::AppHandle,
+    device_identifier: String,
+    confirmation_token: Option<String>,
+) -> Result<HelperResponse, String> {
+    let payload = json!({
+        "deviceIdentifier": device_identifier,
+        "confirmationToken": confirmation_token,
+    });
+
+    let response = run_helper(
+        &app,
+        HelperRequest {
+            action: "wipe_signatures".to_string(),
+            payload,
+        },
+    )?;
+
+    ok_or_message(response)
+}
+
+// Vorgeschlagene /etc/fstab-Zeile (UUID, Typ, Optionen) fuer Linux-Dual-Boot
+#[tauri::command]
+pub fn fstab_entry(app: tauri::AppHandle, partition_identifier: String) -> Result<HelperResponse, String> {
+    let payload = json!({
+        "partitionIdentifier": partition_identifier,
+    });
+
+    let response = run_helper(
+        &app,
+        HelperRequest {
+            action: "fstab_entry".to_string(),
+            payload,
+        },
+    )?;
+
+    ok_or_message(response)
+}
+
+#[tauri::command]
+pub fn filesystem_constraints(app: tauri::AppHandle) -> Result<HelperResponse, String> {
+    let response = run_helper(
+        &app,
+        HelperRequest {
+            action: "filesystem_constraints".to_string(),
+            payload: json!({}),
+        },
+    )?;
+
+    ok_or_message(response)
+}
+
+// Faehigkeiten je Dateisystem (create/resize/label/uuid) samt benoetigter Sidecars
+#[tauri::command]
+pub fn supported_filesystems(app: tauri::AppHandle) -> Result<HelperResponse, String> {
+    let response = run_helper(
+        &app,
+        HelperRequest {
+            action: "supported_filesystems".to_string(),
+            payload: json!({}),
+        },
+    )?;
+
+    ok_or_message(response)
+}
+
+#[tauri::command]
+pub fn safe_eject(app: tauri::AppHandle, device_identifier: String) -> Result<HelperResponse, String> {
+    let payload = json!({
+        "deviceIdentifier": device_identifier,
+    });
+
+    let response = run_helper(
+        &app,
+        HelperRequest {
+            action: "safe_eject".to_string(),
+            payload,
+        },
+    )?;
+
+    ok_or_message(response)
+}
+
 #[tauri::command]
 pub fn get_operation_journal(app: tauri::AppHandle) -> Result<HelperResponse, String> {
     let response = run_helper(
@@ -1356,6 +2561,33 @@ pub fn get_operation_journal(app: tauri::AppHandle) -> Result<HelperResponse, St
     ok_or_message(response)
 }
 
+// Audit-Log aller Helper-Aktionen, neueste zuletzt; limit begrenzt auf die letzten N Eintraege
+#[tauri::command]
+pub fn get_audit_log(app: tauri::AppHandle, limit: Option<u64>) -> Result<HelperResponse, String> {
+    let response = run_helper(
+        &app,
+        HelperRequest {
+            action: "get_audit_log".to_string(),
+            payload: json!({ "limit": limit }),
+        },
+    )?;
+
+    ok_or_message(response)
+}
+
+#[tauri::command]
+pub fn clear_audit_log(app: tauri::AppHandle) -> Result<HelperResponse, String> {
+    let response = run_helper(
+        &app,
+        HelperRequest {
+            action: "clear_audit_log".to_string(),
+            payload: json!({}),
+        },
+    )?;
+
+    ok_or_message(response)
+}
+
 #[tauri::command]
 pub fn clear_operation_journal(app: tauri::AppHandle) -> Result<HelperResponse, String> {
     let response = run_helper(
@@ -1392,12 +2624,89 @@ pub fn apfs_list_volumes(app: tauri::AppHandle, container_identifier: String) ->
     Ok(info)
 }
 
+#[tauri::command]
+pub fn apfs_set_quota(
+    app: tauri::AppHandle,
+    volume_identifier: String,
+    reserve: Option<u64>,
+    quota: Option<u64>,
+) -> Result<HelperResponse, String> {
+    validate_token(&volume_identifier, "volumeIdentifier", true)?;
+
+    let payload = json!({
+        "volumeIdentifier": volume_identifier,
+        "reserve": reserve,
+        "quota": quota,
+    });
+
+    let response = run_helper(
+        &app,
+        HelperRequest {
+            action: "apfs_set_quota".to_string(),
+            payload,
+        },
+    )?;
+
+    ok_or_message(response)
+}
+
+#[tauri::command]
+pub fn apfs_list_snapshots(app: tauri::AppHandle, volume_identifier: String) -> Result<Vec<ApfsSnapshotInfo>, String> {
+    validate_token(&volume_identifier, "volumeIdentifier", true)?;
+
+    let payload = json!({
+        "volumeIdentifier": volume_identifier,
+    });
+
+    let response = run_helper(
+        &app,
+        HelperRequest {
+            action: "apfs_list_snapshots".to_string(),
+            payload,
+        },
+    )?;
+
+    let response = ok_or_message(response)?;
+    let snapshots = response
+        .details
+        .and_then(|details| details.get("snapshots").cloned())
+        .ok_or_else(|| "Snapshot details missing".to_string())?;
+    serde_json::from_value(snapshots).map_err(|e| format!("Invalid snapshot details: {e}"))
+}
+
+#[tauri::command]
+pub fn apfs_delete_snapshot(
+    app: tauri::AppHandle,
+    volume_identifier: String,
+    snapshot_name: String,
+    confirmation_token: Option<String>,
+) -> Result<HelperResponse, String> {
+    validate_token(&volume_identifier, "volumeIdentifier", true)?;
+
+    let payload = json!({
+        "volumeIdentifier": volume_identifier,
+        "snapshotName": snapshot_name,
+        "confirmationToken": confirmation_token,
+    });
+
+    let response = run_helper(
+        &app,
+        HelperRequest {
+            action: "apfs_delete_snapshot".to_string(),
+            payload,
+        },
+    )?;
+
+    ok_or_message(response)
+}
+
 #[tauri::command]
 pub fn apfs_add_volume(app: tauri::AppHandle, request: ApfsAddVolumeRequest) -> Result<HelperResponse, String> {
     let payload = json!({
         "containerIdentifier": request.container_identifier,
         "name": request.name,
         "role": request.role,
+        "groupWith": request.group_with,
     });
 
     let response = run_helper(
@@ -1418,6 +2727,7 @@ pub fn apfs_delete_volume(
 ) -> Result<HelperResponse, String> {
     let payload = json!({
         "volumeIdentifier": request.volume_identifier,
+        "confirmationToken": request.confirmation_token,
     });
 
     let response = run_helper(
@@ -1431,12 +2741,105 @@ pub fn apfs_delete_volume(
     ok_or_message(response)
 }
 
+#[tauri::command(async)]
+pub fn trim_device(app: tauri::AppHandle, device_identifier: String) -> Result<HelperResponse, String> {
+    validate_token(&device_identifier, "deviceIdentifier", true)?;
+
+    let payload = json!({
+        "deviceIdentifier": device_identifier,
+    });
+
+    let response = run_helper(
+        &app,
+        HelperRequest {
+            action: "trim_device".to_string(),
+            payload,
+        },
+    )?;
+
+    ok_or_message(response)
+}
+
+// Langlaufend: der Fortschritt kommt als progress-Events ueber run_helper_stream
+#[tauri::command(async)]
+pub fn encrypt_volume(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    request: ApfsEncryptVolumeRequest,
+) -> Result<HelperResponse, String> {
+    validate_token(&request.volume_identifier, "volumeIdentifier", true)?;
+
+    let payload = json!({
+        "volumeIdentifier": request.volume_identifier,
+        "passphrase": request.passphrase,
+    });
+    wipe_secret(request.passphrase);
+
+    let response = run_helper_stream(
+        &app,
+        &window,
+        HelperRequest {
+            action: "encrypt_volume".to_string(),
+            payload,
+        },
+    )?;
+
+    ok_or_message(response)
+}
+
+// Ohne smartctl gibt es keinen Report, nur den Sidecar-Status (found = false)
+#[tauri::command(async)]
+pub fn smart_info(app: tauri::AppHandle, device_identifier: String) -> Result<SmartInfo, String> {
+    validate_token(&device_identifier, "deviceIdentifier", true)?;
+
+    let sidecar = sidecar_status_for(&app, "smartctl");
+    if !sidecar.found {
+        return Ok(SmartInfo {
+            device_identifier,
+            model: None,
+            serial: None,
+            health: None,
+            reallocated_sectors: None,
+            power_on_hours: None,
+            temperature_celsius: None,
+            percentage_used: None,
+            is_ssd: None,
+            power_mode: None,
+            smartctl: Some(sidecar),
+        });
+    }
+
+    let payload = json!({
+        "deviceIdentifier": device_identifier,
+    });
+
+    let response = run_helper(
+        &app,
+        HelperRequest {
+            action: "smart_info".to_string(),
+            payload,
+        },
+    )?;
+
+    let response = ok_or_message(response)?;
+    let details = response
+        .details
+        .ok_or_else(|| "SMART details missing".to_string())?;
+    let mut info: SmartInfo =
+        serde_json::from_value(details).map_err(|e| format!("Invalid SMART details: {e}"))?;
+    info.smartctl = Some(sidecar);
+    Ok(info)
+}
+
 #[tauri::command]
 pub fn get_sidecar_status(app: tauri::AppHandle) -> Vec<SidecarStatus> {
     let binaries = [
         "sgdisk",
         "resize2fs",
         "ntfsresize",
+        "fatresize",
+        "btrfs",
+        "xfs_growfs",
         "mkfs.ext4",
         "mkfs.ntfs",
         "mkfs.btrfs",
@@ -1448,7 +2851,15 @@ pub fn get_sidecar_status(app: tauri::AppHandle) -> Vec<SidecarStatus> {
         "e2label",
         "tune2fs",
         "ntfslabel",
+        "e2image",
+        "ntfsclone",
         "wipefs",
+        "smartctl",
+        "zpool",
+        "exfatlabel",
+        "fatlabel",
+        "wimlib-imagex",
+        "ntfs-3g",
     ];
 
     binaries
@@ -1490,10 +2901,7 @@ pub fn get_partition_bounds(device_identifier: String) -> Result<PartitionBounds
             .get("PartitionSize")
             .and_then(|v| v.as_unsigned_integer())
             .ok_or_else(|| "PartitionSize missing".to_string())?;
-        let block_size = dict
-            .get("DeviceBlockSize")
-            .and_then(|v| v.as_unsigned_integer())
-            .unwrap_or(512);
+        let (block_size, physical_block_size) = device_block_sizes(dict);
 
         let disk = dict
             .get("ParentWholeDisk")
@@ -1501,7 +2909,43 @@ pub fn get_partition_bounds(device_identifier: String) -> Result<PartitionBounds
             .ok_or_else(|| "ParentWholeDisk missing".to_string())?;
         let disk_path = format!("/dev/{disk}");
 
-        let (min_start, max_start) = partition_bounds_for_disk(&disk_path, &device, size)?;
+        let (min_start, max_start, next_start) = partition_bounds_for_disk(&disk_path, &device, size)?;
+
+        // Untergrenze wie im Preflight: belegter Platz plus 5 % Puffer; XFS kann nicht schrumpfen.
+        // Ohne Belegungsangabe (z.B. nicht gemountetes ext4) bleibt min_size leer.
+        const MIB: u64 = 1024 * 1024;
+        let fs_type = partition_fs_type(Some(dict));
+        let shrink_supported = !matches!(fs_type.as_deref(), Some("xfs"));
+        let used = ["VolumeUsedSpace", "UsedSpace", "VolumeAllocatedSpace"]
+            .iter()
+            .find_map(|key| dict.get(key).and_then(|v| v.as_unsigned_integer()));
+        let min_size = if shrink_supported {
+            used.map(|used| {
+                let with_buffer = ((used as f64) * 1.05).ceil() as u64;
+                (with_buffer.div_ceil(MIB) * MIB).min(size)
+            })
+        } else {
+            Some(size)
+        };
+
+        // Wachsen bis zur naechsten Partition bzw. bis vor die GPT-Backup-Tabelle
+        let limit = match next_start {
+            Some(next) => next,
+            None => {
+                let disk_info = partition_info_dict(disk);
+                let disk_size = disk_info
+                    .as_ref()
+                    .and_then(|info| info.get("Size").and_then(|v| v.as_unsigned_integer()))
+                    .unwrap_or(offset + size);
+                let is_gpt = disk_info
+                    .as_ref()
+                    .and_then(|info| info.get("Content").and_then(|v| v.as_string()))
+                    == Some("GUID_partition_scheme");
+                let reserve = if is_gpt { 32 * 1024 } else { 0 };
+                disk_size.saturating_sub(reserve) / MIB * MIB
+            }
+        };
+        let max_size = limit.saturating_sub(offset).max(size);
 
         return Ok(PartitionBounds {
             offset,
@@ -1509,6 +2953,10 @@ pub fn get_partition_bounds(device_identifier: String) -> Result<PartitionBounds
             min_start,
             max_start,
             block_size,
+            physical_block_size,
+            min_size,
+            max_size,
+            shrink_supported,
         });
     }
 
@@ -1518,6 +2966,50 @@ pub fn get_partition_bounds(device_identifier: String) -> Result<PartitionBounds
     }
 }
 
+#[tauri::command]
+pub fn check_alignment(device_identifier: String) -> Result<DiskAlignmentReport, String> {
+    #[cfg(target_os = "macos")]
+    {
+        const ALIGNMENT_BOUNDARY: u64 = 1024 * 1024;
+
+        let disk = whole_disk_identifier(&device_identifier);
+        let info = partition_info_dict(&disk).ok_or_else(|| format!("diskutil info failed for {disk}"))?;
+        let (_, physical_block_size) = device_block_sizes(&info);
+
+        let mut partitions: Vec<PartitionAlignment> = partition_offsets_for_disk(&disk)
+            .into_iter()
+            .map(|(identifier, (offset, _size))| {
+                let aligned_1mib = offset % ALIGNMENT_BOUNDARY == 0;
+                let aligned_physical = offset % physical_block_size == 0;
+                // Naechste 1-MiB-Grenze ab dem aktuellen Offset (nie davor, sonst Ueberlappung)
+                let suggested_offset = offset.div_ceil(ALIGNMENT_BOUNDARY) * ALIGNMENT_BOUNDARY;
+                PartitionAlignment {
+                    identifier,
+                    offset,
+                    aligned_1mib,
+                    aligned_physical,
+                    suggested_offset,
+                }
+            })
+            .collect();
+        partitions.sort_by_key(|p| p.offset);
+
+        let all_aligned = partitions.iter().all(|p| p.aligned_1mib && p.aligned_physical);
+
+        return Ok(DiskAlignmentReport {
+            device_identifier: disk,
+            physical_block_size,
+            all_aligned,
+            partitions,
+        });
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        Err("Alignment check is only supported on macOS.".to_string())
+    }
+}
+
 #[tauri::command]
 pub fn eject_disk(device_identifier: String) -> Result<(), String> {
     #[cfg(target_os = "macos")]
@@ -1547,6 +3039,72 @@ pub fn eject_disk(device_identifier: String) -> Result<(), String> {
     }
 }
 
+#[tauri::command(async)]
+pub fn get_disk_io_stats(device_identifier: String, interval_ms: Option<u64>) -> Result<DiskIoStats, String> {
+    #[cfg(target_os = "macos")]
+    {
+        let interval_ms = interval_ms.unwrap_or(1000).clamp(100, 10_000);
+        let disk = whole_disk_identifier(&device_identifier);
+
+        let (read_before, write_before) = read_io_counters(&disk)?;
+        std::thread::sleep(std::time::Duration::from_millis(interval_ms));
+        let (read_after, write_after) = read_io_counters(&disk)?;
+
+        let bytes_read = read_after.saturating_sub(read_before);
+        let bytes_written = write_after.saturating_sub(write_before);
+
+        return Ok(DiskIoStats {
+            device_identifier: disk,
+            interval_ms,
+            bytes_read,
+            bytes_written,
+            read_bytes_per_sec: bytes_read * 1000 / interval_ms,
+            write_bytes_per_sec: bytes_written * 1000 / interval_ms,
+        });
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = (device_identifier, interval_ms);
+        Err("Disk I/O counters are only supported on macOS.".to_string())
+    }
+}
+
+// Unterscheidet fehlenden Helper, fehlende sudoers-Freigabe und einsatzbereiten Helper,
+// analog zu den Fehlercodes aus run_helper.
+#[tauri::command]
+pub fn helper_status(app: tauri::AppHandle) -> HelperStatus {
+    let path = helper_paths(&app).into_iter().find(|path| path.exists());
+    let Some(path) = path else {
+        return HelperStatus {
+            state: "missing".to_string(),
+            code: Some("HELPER_NOT_FOUND".to_string()),
+            found: false,
+            authorized: false,
+            path: None,
+        };
+    };
+
+    // sudo -n -l <cmd> gibt nur dann 0 zurueck, wenn der Aufruf ohne Passwort erlaubt ist.
+    let authorized = Command::new("sudo")
+        .arg("-n")
+        .arg("-l")
+        .arg(&path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false);
+
+    HelperStatus {
+        state: if authorized { "ready" } else { "unauthorized" }.to_string(),
+        code: if authorized { None } else { Some("SUDOERS_REQUIRED".to_string()) },
+        found: true,
+        authorized,
+        path: path.to_str().map(|s| s.to_string()),
+    }
+}
+
 fn sidecar_status_for(app: &tauri::AppHandle, binary: &str) -> SidecarStatus {
     let path = find_sidecar(app, binary);
     let mut status = SidecarStatus {
@@ -1560,9 +3118,15 @@ fn sidecar_status_for(app: &tauri::AppHandle, binary: &str) -> SidecarStatus {
         let output = Command::new(&path).arg("--version").output();
         if let Ok(output) = output {
             if output.status.success() {
+                // Manche Tools (z.B. smartctl) beginnen mit Leerzeilen oder schreiben nach stderr
                 let stdout = String::from_utf8_lossy(&output.stdout);
-                let first = stdout.lines().next().map(|s| s.to_string());
-                status.version = first;
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                status.version = stdout
+                    .lines()
+                    .chain(stderr.lines())
+                    .map(|line| line.trim())
+                    .find(|line| !line.is_empty())
+                    .map(|line| line.to_string());
             }
         }
     }
@@ -1590,7 +3154,8 @@ fn find_sidecar(app: &tauri::AppHandle, binary: &str) -> Option<std::path::PathB
 }
 
 #[cfg(target_os = "macos")]
-fn partition_bounds_for_disk(disk: &str, device: &str, size: u64) -> Result<(u64, u64), String> {
+// (min_start, max_start, Start der naechsten Partition)
+fn partition_bounds_for_disk(disk: &str, device: &str, size: u64) -> Result<(u64, u64, Option<u64>), String> {
     use plist::Value;
 
     let output = Command::new("diskutil")
@@ -1653,5 +3218,342 @@ fn partition_bounds_for_disk(disk: &str, device: &str, size: u64) -> Result<(u64
         _ => prev_end.max(1024 * 1024),
     };
 
-    Ok((prev_end.max(1024 * 1024), max_start))
+    Ok((prev_end.max(1024 * 1024), max_start, next_start))
+}
+
+#[cfg(target_os = "macos")]
+fn whole_disk_identifier(identifier: &str) -> String {
+    let cleaned = identifier.trim_start_matches("/dev/").trim_start_matches('r');
+    match cleaned.strip_prefix("disk") {
+        Some(rest) => match rest.find('s') {
+            Some(idx) => format!("disk{}", &rest[..idx]),
+            None => cleaned.to_string(),
+        },
+        None => cleaned.to_string(),
+    }
+}
+
+// Kumulierte Byte-Zähler des IOBlockStorageDriver, dessen IOMedia die BSD Disk ist
+#[cfg(target_os = "macos")]
+fn read_io_counters(disk: &str) -> Result<(u64, u64), String> {
+    use plist::Value;
+
+    let output = Command::new("ioreg")
+        .args(["-a", "-r", "-c", "IOBlockStorageDriver", "-d", "2"])
+        .output()
+        .map_err(|e| format!("ioreg failed: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("ioreg error: {stderr}"));
+    }
+
+    let plist = Value::from_reader_xml(&output.stdout[..]).map_err(|e| e.to_string())?;
+    let drivers = plist.as_array().ok_or_else(|| "Invalid plist".to_string())?;
+
+    for driver in drivers {
+        let dict = match driver.as_dictionary() {
+            Some(dict) => dict,
+            None => continue,
+        };
+        let matches = dict
+            .get("IORegistryEntryChildren")
+            .and_then(|v| v.as_array())
+            .map(|children| {
+                children.iter().any(|child| {
+                    child
+                        .as_dictionary()
+                        .and_then(|c| c.get("BSD Name"))
+                        .and_then(|v| v.as_string())
+                        == Some(disk)
+                })
+            })
+            .unwrap_or(false);
+        if !matches {
+            continue;
+        }
+
+        let stats = dict
+            .get("Statistics")
+            .and_then(|v| v.as_dictionary())
+            .ok_or_else(|| format!("No I/O statistics for {disk}"))?;
+        let read = stats
+            .get("Bytes (Read)")
+            .and_then(|v| v.as_unsigned_integer())
+            .unwrap_or(0);
+        let write = stats
+            .get("Bytes (Write)")
+            .and_then(|v| v.as_unsigned_integer())
+            .unwrap_or(0);
+        return Ok((read, write));
+    }
+
+    Err(format!("No I/O counters found for {disk}"))
+}
+
+static DISK_WATCH_STOP: OnceLock<Mutex<Option<std::sync::Arc<AtomicBool>>>> = OnceLock::new();
+
+#[cfg(target_os = "macos")]
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct DiskWatchEvent {
+    identifier: String,
+}
+
+#[tauri::command]
+pub fn start_disk_watch(app: tauri::AppHandle, interval_ms: Option<u64>) -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    {
+        let lock = DISK_WATCH_STOP.get_or_init(|| Mutex::new(None));
+        let mut guard = lock.lock().map_err(|_| "Disk watch lock poisoned".to_string())?;
+        if guard.is_some() {
+            return Ok(());
+        }
+
+        let stop = std::sync::Arc::new(AtomicBool::new(false));
+        *guard = Some(stop.clone());
+        let interval = std::time::Duration::from_millis(interval_ms.unwrap_or(2000).max(250));
+
+        // Polling ueber diskutil list; DiskArbitration wuerde eine eigene Run-Loop benoetigen.
+        std::thread::spawn(move || {
+            // Schlaegt diskutil list fehl, bleibt der letzte gueltige Stand stehen; sonst wuerden
+            // alle Disks als entfernt und beim naechsten Erfolg als neu angeschlossen gemeldet.
+            let mut last = disk_watch_snapshot();
+            while !stop.load(Ordering::SeqCst) {
+                std::thread::sleep(interval);
+                if stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                let Some(current) = disk_watch_snapshot() else {
+                    continue;
+                };
+                if let Some(previous) = &last {
+                    for (identifier, signature) in &current {
+                        let event = match previous.get(identifier) {
+                            None => "disk-attached",
+                            Some(old) if old != signature => "disk-changed",
+                            Some(_) => continue,
+                        };
+                        let _ = app.emit(event, DiskWatchEvent { identifier: identifier.clone() });
+                    }
+                    for identifier in previous.keys() {
+                        if !current.contains_key(identifier) {
+                            let _ = app.emit("disk-removed", DiskWatchEvent { identifier: identifier.clone() });
+                        }
+                    }
+                }
+                last = Some(current);
+            }
+        });
+
+        return Ok(());
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = (app, interval_ms);
+        Err("Disk watch not supported on this platform".to_string())
+    }
+}
+
+#[tauri::command]
+pub fn stop_disk_watch() {
+    let lock = DISK_WATCH_STOP.get_or_init(|| Mutex::new(None));
+    if let Ok(mut guard) = lock.lock() {
+        if let Some(stop) = guard.take() {
+            stop.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+// Identifier -> Signatur (Groesse, Inhalt, Mountpoint, Name) fuer Disks und Partitionen.
+// None, wenn diskutil list fehlschlaegt oder keine lesbare Liste liefert.
+#[cfg(target_os = "macos")]
+fn disk_watch_snapshot() -> Option<HashMap<String, String>> {
+    use plist::Value;
+
+    let mut snapshot = HashMap::new();
+    let output = Command::new("diskutil")
+        .args(["list", "-plist"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+    let plist = Value::from_reader_xml(&output.stdout[..]).ok()?;
+    let all_disks = plist
+        .as_dictionary()
+        .and_then(|d| d.get("AllDisksAndPartitions"))
+        .and_then(|v| v.as_array())?;
+
+    let signature = |dict: &plist::Dictionary| {
+        let text = |key: &str| dict.get(key).and_then(|v| v.as_string()).unwrap_or("").to_string();
+        let size = dict.get("Size").and_then(|v| v.as_unsigned_integer()).unwrap_or(0);
+        format!("{size}|{}|{}|{}", text("Content"), text("MountPoint"), text("VolumeName"))
+    };
+
+    for entry in all_disks {
+        let Some(disk_dict) = entry.as_dictionary() else {
+            continue;
+        };
+        if let Some(identifier) = disk_dict.get("DeviceIdentifier").and_then(|v| v.as_string()) {
+            snapshot.insert(identifier.to_string(), signature(disk_dict));
+        }
+        for key in ["Partitions", "APFSVolumes"] {
+            if let Some(Value::Array(parts)) = disk_dict.get(key) {
+                for part in parts.iter().filter_map(|p| p.as_dictionary()) {
+                    if let Some(identifier) = part.get("DeviceIdentifier").and_then(|v| v.as_string()) {
+                        snapshot.insert(identifier.to_string(), signature(part));
+                    }
+                }
+            }
+        }
+    }
+
+    Some(snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIST_INTEL_DISK0: &str = include_str!("../../tests/fixtures/diskutil_list_intel_disk0.plist");
+    const LIST_MBR_DISK4: &str = include_str!("../../tests/fixtures/diskutil_list_mbr_disk4.plist");
+    const IOREG_IOMEDIA: &str = include_str!("../../tests/fixtures/ioreg_iomedia.plist");
+
+    fn fixture(xml: &str) -> plist::Value {
+        plist::Value::from_reader_xml(xml.as_bytes()).expect("fixture plist")
+    }
+
+    #[test]
+    fn partition_offsets_from_gpt_list() {
+        let offsets = parse_partition_offsets(&fixture(LIST_INTEL_DISK0));
+        assert_eq!(offsets.len(), 3);
+        assert_eq!(offsets.get("disk0s1"), Some(&(None, 209715200)));
+        assert_eq!(offsets.get("disk0s2"), Some(&(None, 400000000000)));
+        assert_eq!(offsets.get("disk0s3"), Some(&(None, 100000595968)));
+    }
+
+    #[test]
+    fn partition_offsets_from_mbr_list() {
+        let offsets = parse_partition_offsets(&fixture(LIST_MBR_DISK4));
+        assert_eq!(offsets.len(), 2);
+        assert_eq!(offsets.get("disk4s1"), Some(&(None, 8589934592)));
+        assert_eq!(offsets.get("disk4s2"), Some(&(None, 23420993536)));
+    }
+
+    #[test]
+    fn partition_offsets_keep_top_level_partition_offset() {
+        let plist = plist::Value::Dictionary(
+            [(
+                "Partitions".to_string(),
+                plist::Value::Array(vec![plist::Value::Dictionary(
+                    [
+                        ("DeviceIdentifier".to_string(), plist::Value::String("disk2s1".to_string())),
+                        ("PartitionOffset".to_string(), plist::Value::Integer(20480.into())),
+                        ("PartitionSize".to_string(), plist::Value::Integer(1048576.into())),
+                    ]
+                    .into_iter()
+                    .collect(),
+                )]),
+            )]
+            .into_iter()
+            .collect(),
+        );
+        let offsets = parse_partition_offsets(&plist);
+        assert_eq!(offsets.get("disk2s1"), Some(&(Some(20480), 1048576)));
+    }
+
+    #[test]
+    fn helper_timeout_defaults_unknown_actions_to_write_timeout() {
+        let long = std::time::Duration::from_secs(DEFAULT_WRITE_TIMEOUT_SECS);
+        for action in [
+            "convert_partition_table",
+            "wipe_signatures",
+            "benchmark_device",
+            "trim_device",
+            "mount_ntfs_rw",
+            "future_action",
+        ] {
+            assert_eq!(helper_timeout_for(action), long, "{action}");
+        }
+        assert_eq!(helper_timeout_for("version"), std::time::Duration::from_secs(DEFAULT_METADATA_TIMEOUT_SECS));
+    }
+
+    // Wie "diskutil apfs resizeContainer": laenger still als das Timeout, nur Heartbeats
+    #[test]
+    fn silent_long_write_with_heartbeats_is_not_timed_out() {
+        let script = r#"for i in 1 2 3 4 5 6; do sleep 0.2; echo '{"type":"heartbeat","tool":"diskutil"}'; done; echo '{"success":true}'"#;
+        let mut child = Command::new("sh")
+            .args(["-c", script])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("spawn sh");
+        let mut last_line = String::new();
+        let (status, _) = drive_helper(&mut child, std::time::Duration::from_millis(600), |line| last_line = line)
+            .expect("heartbeats keep the helper alive");
+        assert!(status.success());
+        assert_eq!(last_line, r#"{"success":true}"#);
+    }
+
+    #[test]
+    fn partition_type_guids_match_bsd_names() {
+        let plist = fixture(IOREG_IOMEDIA);
+        let guids = parse_partition_type_guids(&plist, "/dev/disk0");
+        assert_eq!(guids.len(), 3);
+        assert_eq!(guids["disk0s1"], "C12A7328-F81F-11D2-BA4B-00A0C93EC93B");
+        assert_eq!(guids["disk0s2"], "7C3457EF-0000-11AA-AA11-00306543ECAC");
+        assert_eq!(guids["disk0s3"], "EBD0A0A2-B9E5-4433-87C0-68B6B72699C7");
+
+        // MBR-Content ist keine GUID; disk1 darf disk10s1 nicht erfassen
+        assert!(parse_partition_type_guids(&plist, "disk4").is_empty());
+        assert!(parse_partition_type_guids(&plist, "disk1").is_empty());
+        assert_eq!(parse_partition_type_guids(&plist, "disk10").len(), 1);
+    }
+
+    #[test]
+    fn sudoers_install_handles_helper_path_with_space() {
+        let helper_path = "/Applications/Oxi Disk.app/Contents/MacOS/oxidisk_helper";
+        validate_helper_path(helper_path).expect("path with space is valid");
+
+        let entry = sudoers_entry("oliver", helper_path);
+        assert_eq!(
+            entry,
+            "oliver ALL=(root) NOPASSWD: /Applications/Oxi\\ Disk.app/Contents/MacOS/oxidisk_helper\n"
+        );
+
+        let args = sudoers_install_args(&entry, "/etc/sudoers.d/oxidisk");
+        assert_eq!(args[args.len() - 2], entry);
+        assert_eq!(args[args.len() - 1], "/etc/sudoers.d/oxidisk");
+        let script = &args[..args.len() - 2];
+        assert!(script.iter().all(|arg| !arg.contains("oliver") && !arg.contains("Oxi Disk")));
+        // Geprueft und installiert wird dieselbe, erst im privilegierten Skript angelegte Datei
+        let shell = script.iter().find(|arg| arg.starts_with("do shell script")).expect("shell step");
+        assert!(shell.contains("/usr/bin/mktemp"));
+        assert!(shell.contains("visudo -cf \\\"$tmp\\\""));
+        assert!(shell.contains("wheel \\\"$tmp\\\""));
+    }
+
+    #[test]
+    fn helper_path_rejects_shell_and_sudoers_metacharacters() {
+        for path in [
+            "relative/oxidisk_helper",
+            "/Applications/Oxi'Disk.app/helper",
+            "/Applications/Oxi\"Disk.app/helper",
+            "/tmp/helper, ALL",
+            "/tmp/helper;rm",
+            "/Applications/../tmp/helper",
+        ] {
+            assert!(validate_helper_path(path).is_err(), "{path}");
+        }
+    }
+
+    #[test]
+    fn partition_scheme_from_known_content_strings() {
+        assert_eq!(partition_scheme("GUID_partition_scheme"), "gpt");
+        assert_eq!(partition_scheme("FDisk_partition_scheme"), "mbr");
+        assert_eq!(partition_scheme("EF57347C-0000-11AA-AA11-00306543ECAC"), "apfs");
+        assert_eq!(partition_scheme("Apple_partition_scheme"), "apm");
+        assert_eq!(partition_scheme(""), "none");
+        assert_eq!(partition_scheme("DOS_FAT_32"), "none");
+        assert_eq!(partition_scheme("unknown"), "none");
+    }
 }
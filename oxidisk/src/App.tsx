@@ -2508,9 +2508,9 @@ export default function App() {
               onChange={(event) => setSecureEraseLevel(event.currentTarget.value)}
               data={[
                 { value: "0", label: "Stufe 0 (Schnell, Nullen)" },
-                { value: "1", label: "Stufe 1 (Zufall, 2 Durchgaenge)" },
+                { value: "1", label: "Stufe 1 (Zufall, 1 Durchgang)" },
                 { value: "2", label: "Stufe 2 (DoD, 7 Durchgaenge)" },
-                { value: "3", label: "Stufe 3 (Gutmann, 35 Durchgaenge)" },
+                { value: "3", label: "Stufe 3 (DoE, 3 Durchgaenge)" },
               ]}
             />
           )}